@@ -0,0 +1,336 @@
+//! `?acl` 子资源：bucket 和对象的 ACL 读写，以及创建时的 `x-amz-acl`、`x-amz-grant-*` 请求头。
+use crate::auth::Principal;
+use crate::bucket_handler::Owner;
+use crate::chunked::S3Body;
+use crate::error::{S3Error, S3ErrorCode};
+use crate::response::{S3_XMLNS, xml_response};
+use crate::state::AppState;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use server::metadata::acl::{
+  ALL_USERS_URI, AUTHENTICATED_USERS_URI, AccessControlList, CannedAcl, Grant, Grantee, Permission,
+};
+
+pub const X_AMZ_ACL: &str = "x-amz-acl";
+const XSI_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-instance";
+/// `x-amz-grant-*` 请求头及对应的权限
+const GRANT_HEADERS: [(&str, Permission); 5] = [
+  ("x-amz-grant-full-control", Permission::FullControl),
+  ("x-amz-grant-read", Permission::Read),
+  ("x-amz-grant-write", Permission::Write),
+  ("x-amz-grant-read-acp", Permission::ReadAcp),
+  ("x-amz-grant-write-acp", Permission::WriteAcp),
+];
+
+#[derive(Serialize, Deserialize, Default)]
+struct GranteeEntry {
+  #[serde(rename = "@xmlns:xsi", default, skip_deserializing)]
+  xmlns_xsi: String,
+  #[serde(rename = "@xsi:type", default, skip_deserializing)]
+  kind: String,
+  #[serde(rename = "ID", skip_serializing_if = "Option::is_none")]
+  id: Option<String>,
+  #[serde(rename = "DisplayName", skip_serializing_if = "Option::is_none")]
+  display_name: Option<String>,
+  #[serde(rename = "EmailAddress", skip_serializing_if = "Option::is_none")]
+  email_address: Option<String>,
+  #[serde(rename = "URI", skip_serializing_if = "Option::is_none")]
+  uri: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GrantEntry {
+  #[serde(rename = "Grantee")]
+  grantee: GranteeEntry,
+  #[serde(rename = "Permission")]
+  permission: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GrantList {
+  #[serde(rename = "Grant", default)]
+  grants: Vec<GrantEntry>,
+}
+
+#[derive(Deserialize)]
+struct AccessControlPolicy {
+  #[serde(rename = "AccessControlList")]
+  access_control_list: GrantList,
+}
+
+#[derive(Serialize)]
+struct AccessControlPolicyResult {
+  #[serde(rename = "@xmlns")]
+  xmlns: &'static str,
+  #[serde(rename = "Owner")]
+  owner: Owner,
+  #[serde(rename = "AccessControlList")]
+  access_control_list: GrantList,
+}
+
+fn malformed_acl() -> S3Error {
+  S3ErrorCode::MalformedACLError.into()
+}
+
+fn email_unsupported() -> S3Error {
+  S3Error::new(S3ErrorCode::NotImplemented).with_message("Email address grantees are not supported")
+}
+
+fn parse_acl_xml(body: &[u8], owner: &str) -> Result<AccessControlList, S3Error> {
+  let policy: AccessControlPolicy = std::str::from_utf8(body)
+    .ok()
+    .and_then(|xml| quick_xml::de::from_str(xml).ok())
+    .ok_or_else(malformed_acl)?;
+  let grants = policy
+    .access_control_list
+    .grants
+    .into_iter()
+    .map(|grant| {
+      let permission = Permission::from_name(&grant.permission).ok_or_else(malformed_acl)?;
+      let grantee = match grant.grantee {
+        GranteeEntry { id: Some(id), .. } => Grantee::User(id),
+        GranteeEntry { uri: Some(uri), .. } => {
+          Grantee::from_group_uri(&uri).ok_or_else(malformed_acl)?
+        }
+        GranteeEntry {
+          email_address: Some(_),
+          ..
+        } => return Err(email_unsupported()),
+        _ => return Err(malformed_acl()),
+      };
+      Ok(Grant::new(grantee, permission))
+    })
+    .collect::<Result<_, S3Error>>()?;
+  Ok(AccessControlList {
+    owner: owner.to_string(),
+    grants,
+  })
+}
+
+/// 解析 `id="..."`、`uri="..."` 形式的授权列表，逗号分隔
+fn parse_grantees(value: &str) -> Result<Vec<Grantee>, S3Error> {
+  let invalid = || {
+    S3Error::new(S3ErrorCode::InvalidArgument)
+      .with_message(format!("Invalid grant header: {value}"))
+  };
+  value
+    .split(',')
+    .map(|grantee| {
+      let (kind, id) = grantee.trim().split_once('=').ok_or_else(invalid)?;
+      let id = id.trim().trim_matches('"');
+      match kind.trim().to_ascii_lowercase().as_str() {
+        "id" if !id.is_empty() => Ok(Grantee::User(id.to_string())),
+        "uri" => Grantee::from_group_uri(id).ok_or_else(invalid),
+        "emailaddress" => Err(email_unsupported()),
+        _ => Err(invalid()),
+      }
+    })
+    .collect()
+}
+
+/// 按 `x-amz-acl` 或 `x-amz-grant-*` 构造 ACL，都没有时返回 None；两者不能同时使用
+pub(crate) fn header_acl(
+  headers: &HeaderMap,
+  owner: &str,
+  bucket_owner: &str,
+) -> Result<Option<AccessControlList>, S3Error> {
+  let header = |name| {
+    headers
+      .get(name)
+      .map(|value| value.to_str().map_err(|_| malformed_acl()))
+      .transpose()
+  };
+  let mut grants = Vec::new();
+  for (name, permission) in GRANT_HEADERS {
+    if let Some(value) = header(name)? {
+      grants.extend(
+        parse_grantees(value)?
+          .into_iter()
+          .map(|grantee| Grant::new(grantee, permission)),
+      );
+    }
+  }
+  match header(X_AMZ_ACL)? {
+    Some(_) if !grants.is_empty() => Err(
+      S3Error::new(S3ErrorCode::InvalidRequest)
+        .with_message("Specifying both Canned ACLs and Header Grants is not allowed"),
+    ),
+    Some(canned) => CannedAcl::from_name(canned)
+      .map(|canned| Some(canned.build(owner, bucket_owner)))
+      .ok_or_else(|| S3ErrorCode::InvalidArgument.into()),
+    None if grants.is_empty() => Ok(None),
+    None => Ok(Some(AccessControlList {
+      owner: owner.to_string(),
+      grants,
+    })),
+  }
+}
+
+/// 新对象的 ACL：按请求头构造，缺省为 private，owner 为上传者
+pub(crate) fn new_object_acl(
+  state: &AppState,
+  bucket: &str,
+  principal: &Principal,
+  headers: &HeaderMap,
+) -> Result<AccessControlList, S3Error> {
+  let owner = principal.owner_id();
+  let bucket_owner = state.storage.buckets.get_bucket(bucket)?.owner;
+  Ok(
+    header_acl(headers, owner, &bucket_owner)?.unwrap_or_else(|| AccessControlList::private(owner)),
+  )
+}
+
+/// PutBucketAcl / PutObjectAcl 的请求：请求头和 XML 请求体二选一
+fn request_acl(
+  headers: &HeaderMap,
+  body: &[u8],
+  owner: &str,
+  bucket_owner: &str,
+) -> Result<AccessControlList, S3Error> {
+  match header_acl(headers, owner, bucket_owner)? {
+    Some(_) if !body.is_empty() => Err(
+      S3Error::new(S3ErrorCode::UnexpectedContent)
+        .with_message("This request does not support content when ACL headers are specified"),
+    ),
+    Some(acl) => Ok(acl),
+    None if body.is_empty() => Err(malformed_acl()),
+    None => parse_acl_xml(body, owner),
+  }
+}
+
+fn acl_response(acl: AccessControlList) -> Response {
+  let grants = acl
+    .grants
+    .into_iter()
+    .map(|grant| {
+      let grantee = match grant.grantee {
+        Grantee::User(id) => GranteeEntry {
+          kind: "CanonicalUser".into(),
+          display_name: Some(id.clone()),
+          id: Some(id),
+          ..Default::default()
+        },
+        group => GranteeEntry {
+          kind: "Group".into(),
+          uri: Some(
+            match group {
+              Grantee::AllUsers => ALL_USERS_URI,
+              _ => AUTHENTICATED_USERS_URI,
+            }
+            .into(),
+          ),
+          ..Default::default()
+        },
+      };
+      GrantEntry {
+        grantee: GranteeEntry {
+          xmlns_xsi: XSI_NAMESPACE.into(),
+          ..grantee
+        },
+        permission: grant.permission.as_str().into(),
+      }
+    })
+    .collect();
+  xml_response(
+    "AccessControlPolicy",
+    &AccessControlPolicyResult {
+      xmlns: S3_XMLNS,
+      owner: Owner::new(&acl.owner),
+      access_control_list: GrantList { grants },
+    },
+  )
+}
+
+// GET /{bucket}?acl
+pub async fn get_bucket_acl(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+) -> Result<Response, S3Error> {
+  let meta = state.storage.buckets.get_bucket(&bucket)?;
+  Ok(acl_response(meta.acl))
+}
+
+// PUT /{bucket}?acl
+pub async fn put_bucket_acl(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+  headers: HeaderMap,
+  S3Body(body): S3Body,
+) -> Result<StatusCode, S3Error> {
+  let meta = state.storage.buckets.get_bucket(&bucket)?;
+  let acl = request_acl(&headers, &body, &meta.owner, &meta.owner)?;
+  state.storage.buckets.put_bucket_acl(&bucket, acl)?;
+  Ok(StatusCode::OK)
+}
+
+// GET /{bucket}/{key}?acl
+pub async fn get_object_acl(
+  State(state): State<AppState>,
+  Path((bucket, key)): Path<(String, String)>,
+) -> Result<Response, S3Error> {
+  let meta = state.storage.objects.head_object(&bucket, &key)?;
+  Ok(acl_response(meta.acl))
+}
+
+// PUT /{bucket}/{key}?acl
+pub async fn put_object_acl(
+  State(state): State<AppState>,
+  Path((bucket, key)): Path<(String, String)>,
+  headers: HeaderMap,
+  S3Body(body): S3Body,
+) -> Result<StatusCode, S3Error> {
+  let bucket_owner = state.storage.buckets.get_bucket(&bucket)?.owner;
+  let meta = state.storage.objects.head_object(&bucket, &key)?;
+  let acl = request_acl(&headers, &body, &meta.acl.owner, &bucket_owner)?;
+  state.storage.objects.put_object_acl(&bucket, &key, acl)?;
+  Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn acl_headers_and_xml() {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+      "x-amz-grant-read",
+      format!("id=\"bob\", uri=\"{ALL_USERS_URI}\"")
+        .parse()
+        .unwrap(),
+    );
+    let acl = header_acl(&headers, "alice", "alice").unwrap().unwrap();
+    assert_eq!(
+      acl.grants,
+      [
+        Grant::new(Grantee::User("bob".into()), Permission::Read),
+        Grant::new(Grantee::AllUsers, Permission::Read)
+      ]
+    );
+    headers.insert(X_AMZ_ACL, "public-read".parse().unwrap());
+    assert!(header_acl(&headers, "alice", "alice").is_err());
+    assert!(
+      header_acl(&HeaderMap::new(), "alice", "alice")
+        .unwrap()
+        .is_none()
+    );
+
+    let xml = format!(
+      r#"<AccessControlPolicy><Owner><ID>alice</ID></Owner><AccessControlList>
+        <Grant><Grantee xmlns:xsi="{XSI_NAMESPACE}" xsi:type="CanonicalUser"><ID>bob</ID></Grantee><Permission>WRITE</Permission></Grant>
+        <Grant><Grantee xmlns:xsi="{XSI_NAMESPACE}" xsi:type="Group"><URI>{AUTHENTICATED_USERS_URI}</URI></Grantee><Permission>READ</Permission></Grant>
+      </AccessControlList></AccessControlPolicy>"#
+    );
+    let acl = parse_acl_xml(xml.as_bytes(), "alice").unwrap();
+    assert_eq!(
+      acl.grants,
+      [
+        Grant::new(Grantee::User("bob".into()), Permission::Write),
+        Grant::new(Grantee::AuthenticatedUsers, Permission::Read)
+      ]
+    );
+    assert!(parse_acl_xml(b"<AccessControlPolicy>", "alice").is_err());
+  }
+}
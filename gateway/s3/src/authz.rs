@@ -0,0 +1,351 @@
+//! 授权层：把请求映射为 S3 操作（[`Action`]），按 bucket 策略和 ACL 求值。
+//! 策略中显式 Deny 优先，其次 Allow；策略没有语句匹配时放行 bucket owner 和 ACL 授权的请求，其余返回 AccessDenied。
+use crate::auth::Principal;
+use crate::copy_source::{X_AMZ_COPY_SOURCE, parse_copy_source};
+use crate::dispatch::{Endpoint, has_param};
+use crate::error::{S3Error, S3ErrorCode};
+use crate::state::AppState;
+use axum::extract::{ConnectInfo, Query, Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::Response;
+use percent_encoding::percent_decode_str;
+use server::error::StorageError;
+use server::metadata::acl::Permission;
+use server::metadata::bucket_meta::BucketMeta;
+use server::metadata::constant::Action;
+use server::policy::{ConditionContext, Decision, PolicyRequest, evaluate, resource_arn};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+/// 请求对应的操作和资源
+#[derive(Debug, PartialEq, Eq)]
+pub struct Operation {
+  pub endpoint: Endpoint,
+  pub action: Action,
+  pub bucket: Option<String>,
+  pub key: Option<String>,
+}
+
+/// 授权所需的请求信息：请求方以及策略条件键的取值，由授权层写入请求扩展
+#[derive(Debug, Clone)]
+pub struct AccessContext {
+  pub principal: Principal,
+  pub conditions: ConditionContext,
+}
+
+impl AccessContext {
+  /// `trusted_proxy` 为 true 时才按 `X-Forwarded-Proto` 判断 `aws:SecureTransport`：
+  /// 网关只监听明文 HTTP，该请求头和请求行中的 scheme 都可以由客户端任意设置
+  pub fn new(req: &Request, principal: Principal, trusted_proxy: bool) -> Self {
+    let mut conditions = ConditionContext::new();
+    let headers = req.headers();
+    let secure = trusted_proxy
+      && headers
+        .get("x-forwarded-proto")
+        .is_some_and(|proto| proto.as_bytes().eq_ignore_ascii_case(b"https"));
+    conditions.insert("aws:securetransport".into(), secure.to_string());
+    if let Some(ConnectInfo(addr)) = req.extensions().get::<ConnectInfo<SocketAddr>>() {
+      conditions.insert("aws:sourceip".into(), addr.ip().to_string());
+    }
+    for (key, header) in [
+      ("aws:referer", header::REFERER),
+      ("aws:useragent", header::USER_AGENT),
+    ] {
+      if let Some(Ok(value)) = headers.get(header).map(|value| value.to_str()) {
+        conditions.insert(key.into(), value.to_string());
+      }
+    }
+    if let Principal::User(access_key) = &principal {
+      conditions.insert("aws:username".into(), access_key.clone());
+      conditions.insert("aws:userid".into(), access_key.clone());
+    }
+    // ListObjects 的参数，如 s3:prefix
+    if let Ok(Query(query)) = Query::<HashMap<String, String>>::try_from_uri(req.uri()) {
+      for name in ["prefix", "delimiter", "max-keys", "versionId"] {
+        if let Some(value) = query.get(name) {
+          conditions.insert(format!("s3:{}", name.to_ascii_lowercase()), value.clone());
+        }
+      }
+    }
+    // x-amz-* 请求头，如 s3:x-amz-acl、s3:x-amz-server-side-encryption
+    for (name, value) in headers {
+      if name.as_str().starts_with("x-amz-")
+        && let Ok(value) = value.to_str()
+      {
+        conditions.insert(format!("s3:{name}"), value.to_string());
+      }
+    }
+    Self {
+      principal,
+      conditions,
+    }
+  }
+}
+
+/// 按 bucket 策略和归属判断请求方能否对资源执行操作
+pub fn authorize(
+  state: &AppState,
+  access: &AccessContext,
+  action: Action,
+  bucket: &str,
+  key: Option<&str>,
+) -> Result<(), S3Error> {
+  let meta = match state.storage.buckets.get_bucket(bucket) {
+    Ok(meta) => meta,
+    // bucket 不存在时交给 handler 返回 NoSuchBucket
+    Err(err) if matches!(err.downcast_ref(), Some(StorageError::NoSuchBucket { .. })) => {
+      return Ok(());
+    }
+    Err(err) => return Err(err.into()),
+  };
+  let owner = meta.owner == access.principal.owner_id();
+  // owner 始终可以管理策略，避免写错 Deny 语句后无法恢复
+  if owner
+    && matches!(
+      action,
+      Action::GetBucketPolicy | Action::PutBucketPolicy | Action::DeleteBucketPolicy
+    )
+  {
+    return Ok(());
+  }
+  let resource = resource_arn(bucket, key);
+  let request = PolicyRequest {
+    principal: match &access.principal {
+      Principal::Anonymous => None,
+      Principal::User(access_key) => Some(access_key),
+    },
+    action,
+    resource: &resource,
+    context: &access.conditions,
+  };
+  let decision = meta
+    .policy
+    .as_ref()
+    .map_or(Decision::NotApplicable, |policy| evaluate(policy, &request));
+  match decision {
+    Decision::Allow => Ok(()),
+    Decision::NotApplicable
+      if owner || acl_allows(state, &meta, request.principal, action, key) =>
+    {
+      Ok(())
+    }
+    Decision::Deny | Decision::NotApplicable => Err(S3ErrorCode::AccessDenied.into()),
+  }
+}
+
+/// 操作需要的 ACL 权限，以及按对象 ACL（true）还是 bucket ACL（false）判断
+fn acl_permission(action: Action) -> Option<(Permission, bool)> {
+  Some(match action {
+    Action::ListBucket | Action::ListBucketVersions | Action::ListBucketMultipartUploads => {
+      (Permission::Read, false)
+    }
+    Action::PutObject
+    | Action::DeleteObject
+    | Action::DeleteObjectVersion
+    | Action::AbortMultipartUpload => (Permission::Write, false),
+    Action::GetBucketAcl => (Permission::ReadAcp, false),
+    Action::PutBucketAcl => (Permission::WriteAcp, false),
+    Action::GetObject | Action::GetObjectVersion => (Permission::Read, true),
+    Action::GetObjectAcl => (Permission::ReadAcp, true),
+    Action::PutObjectAcl => (Permission::WriteAcp, true),
+    _ => return None,
+  })
+}
+
+fn acl_allows(
+  state: &AppState,
+  meta: &BucketMeta,
+  principal: Option<&str>,
+  action: Action,
+  key: Option<&str>,
+) -> bool {
+  match (acl_permission(action), key) {
+    (Some((permission, false)), _) => meta.acl.allows(principal, permission),
+    // 对象不存在时无从判断，与 S3 一致返回 AccessDenied
+    (Some((permission, true)), Some(key)) => state
+      .storage
+      .objects
+      .head_object(&meta.name, key)
+      .is_ok_and(|object| object.acl.allows(principal, permission)),
+    _ => false,
+  }
+}
+
+/// 按方法、路径和查询子资源确定操作，与 `dispatch` 使用同一份解析结果
+pub fn resolve_operation(req: &Request) -> Result<Operation, S3Error> {
+  let path = percent_decode_str(req.uri().path().trim_start_matches('/')).decode_utf8_lossy();
+  let (bucket, key) = match path.split_once('/') {
+    Some((bucket, key)) => (bucket, Some(key).filter(|key| !key.is_empty())),
+    None => (path.as_ref(), None),
+  };
+  let bucket = Some(bucket).filter(|bucket| !bucket.is_empty());
+  let endpoint = Endpoint::resolve(req, bucket, key)?;
+  Ok(Operation {
+    endpoint,
+    action: endpoint.action(has_param(req, "versionId")),
+    bucket: bucket.map(str::to_string),
+    key: key.map(str::to_string),
+  })
+}
+
+/// 授权中间件，需放在认证层之内以读取 Principal
+pub async fn authorize_request(
+  State(state): State<AppState>,
+  mut req: Request,
+  next: Next,
+) -> Result<Response, S3Error> {
+  let principal = req
+    .extensions()
+    .get::<Principal>()
+    .cloned()
+    .unwrap_or(Principal::Anonymous);
+  let access = AccessContext::new(&req, principal, state.trusted_proxy);
+  let operation = resolve_operation(&req)?;
+  match (operation.endpoint, &operation.bucket) {
+    // 认证通过即可列出自己的 bucket 或创建 bucket
+    (Endpoint::ListBuckets | Endpoint::CreateBucket, _) | (_, None) => {}
+    // DeleteObjects 在 handler 中逐个 key 授权
+    (Endpoint::DeleteObjects, _) => {}
+    (_, Some(bucket)) => {
+      authorize(
+        &state,
+        &access,
+        operation.action,
+        bucket,
+        operation.key.as_deref(),
+      )?;
+    }
+  }
+  // 复制还需要对源对象有读权限
+  if matches!(
+    operation.endpoint,
+    Endpoint::CopyObject | Endpoint::UploadPartCopy
+  ) && let Some(source) = req.headers().get(X_AMZ_COPY_SOURCE)
+    && let Ok(source) = parse_copy_source(source.to_str().unwrap_or_default())
+  {
+    let action = match source.version_id {
+      Some(_) => Action::GetObjectVersion,
+      None => Action::GetObject,
+    };
+    authorize(&state, &access, action, &source.bucket, Some(&source.key))?;
+  }
+  req.extensions_mut().insert(access);
+  req.extensions_mut().insert(operation.endpoint);
+  Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use axum::body::Body;
+  use axum::http::Method;
+
+  fn operation(method: Method, uri: &str) -> Operation {
+    resolve_operation(
+      &Request::builder()
+        .method(method)
+        .uri(uri)
+        .body(Body::empty())
+        .unwrap(),
+    )
+    .unwrap()
+  }
+
+  fn rejected(method: Method, uri: &str) -> S3ErrorCode {
+    resolve_operation(
+      &Request::builder()
+        .method(method)
+        .uri(uri)
+        .body(Body::empty())
+        .unwrap(),
+    )
+    .unwrap_err()
+    .code()
+  }
+
+  #[test]
+  fn resolve_actions() {
+    assert_eq!(operation(Method::GET, "/").action, Action::ListAllMyBuckets);
+    assert_eq!(operation(Method::PUT, "/bkt").action, Action::CreateBucket);
+    assert_eq!(
+      operation(Method::GET, "/bkt?list-type=2").action,
+      Action::ListBucket
+    );
+    assert_eq!(
+      operation(Method::DELETE, "/bkt?tagging").action,
+      Action::PutBucketTagging
+    );
+    assert_eq!(
+      operation(Method::DELETE, "/bkt?policy").action,
+      Action::DeleteBucketPolicy
+    );
+    assert_eq!(
+      operation(Method::DELETE, "/bkt/a?uploadId=1").action,
+      Action::AbortMultipartUpload
+    );
+    let get = operation(Method::GET, "/bkt/dir/a%20b.txt");
+    assert_eq!(
+      get,
+      Operation {
+        endpoint: Endpoint::GetObject,
+        action: Action::GetObject,
+        bucket: Some("bkt".into()),
+        key: Some("dir/a b.txt".into()),
+      }
+    );
+  }
+
+  #[test]
+  fn secure_transport() {
+    let secure = |uri: &str, trusted_proxy: bool| {
+      let req = Request::builder()
+        .uri(uri)
+        .header("x-forwarded-proto", "https")
+        .body(Body::empty())
+        .unwrap();
+      let access = AccessContext::new(&req, Principal::Anonymous, trusted_proxy);
+      access.conditions["aws:securetransport"].clone()
+    };
+    // 客户端自带的 X-Forwarded-Proto 和请求行中的 https 都不可信
+    assert_eq!(secure("/bkt/a", false), "false");
+    assert_eq!(secure("https://host/bkt/a", false), "false");
+    assert_eq!(secure("/bkt/a", true), "true");
+  }
+
+  // 不支持的子资源和方法组合不能落到删除 bucket、列出对象等默认操作上
+  #[test]
+  fn unsupported_sub_resources() {
+    for uri in [
+      "/bkt?acl",
+      "/bkt?versioning",
+      "/bkt?object-lock",
+      "/bkt?location",
+    ] {
+      assert_eq!(rejected(Method::DELETE, uri), S3ErrorCode::MethodNotAllowed);
+    }
+    assert_eq!(
+      rejected(Method::DELETE, "/bkt/a?acl"),
+      S3ErrorCode::MethodNotAllowed
+    );
+    assert_eq!(
+      rejected(Method::DELETE, "/bkt?website"),
+      S3ErrorCode::NotImplemented
+    );
+    assert_eq!(
+      rejected(Method::GET, "/bkt?logging"),
+      S3ErrorCode::NotImplemented
+    );
+    let location = operation(Method::GET, "/bkt?location");
+    assert_eq!(location.endpoint, Endpoint::GetBucketLocation);
+    assert_eq!(location.action, Action::GetBucketLocation);
+    assert_eq!(
+      operation(Method::DELETE, "/bkt").endpoint,
+      Endpoint::DeleteBucket
+    );
+    assert_eq!(
+      operation(Method::DELETE, "/bkt/a?versionId=1").action,
+      Action::DeleteObjectVersion
+    );
+  }
+}
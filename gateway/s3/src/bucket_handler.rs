@@ -1,33 +1,96 @@
+use crate::acl_handler::header_acl;
+use crate::auth::Principal;
+use crate::chunked::S3Body;
+use crate::error::{S3Error, S3ErrorCode};
+use crate::object_lock_handler::X_AMZ_BUCKET_OBJECT_LOCK_ENABLED;
+use crate::response::{S3_XMLNS, format_timestamp, xml_response};
+use crate::state::AppState;
 use axum::{
-    body::Body,
-    extract::Path,
-    http::{HeaderMap, StatusCode},
-    response::Response,
-    Json,
+  extract::{Extension, Path, State},
+  http::{HeaderMap, StatusCode, header},
+  response::{IntoResponse, Response},
 };
-use std::collections::HashMap;
+use serde::Serialize;
+use server::bucket::CreateBucketOptions;
+use server::metadata::bucket_meta::BucketMeta;
+use server::policy::parse_policy;
 use tracing::debug;
 
 pub const BUCKET_TAG: &str = "bucket";
+/// 创建 bucket 时开启内容去重（MaxIO 扩展）
+pub const X_MAXIO_BUCKET_DEDUP: &str = "x-maxio-bucket-dedup";
+
+#[derive(Serialize)]
+pub struct Owner {
+  #[serde(rename = "ID")]
+  pub id: String,
+  #[serde(rename = "DisplayName")]
+  pub display_name: String,
+}
+
+impl Owner {
+  pub fn new(id: &str) -> Self {
+    Self {
+      id: id.to_string(),
+      display_name: id.to_string(),
+    }
+  }
+}
+
+#[derive(Serialize)]
+struct BucketEntry {
+  #[serde(rename = "Name")]
+  name: String,
+  #[serde(rename = "CreationDate")]
+  creation_date: String,
+}
+
+#[derive(Serialize)]
+struct BucketList {
+  #[serde(rename = "Bucket")]
+  buckets: Vec<BucketEntry>,
+}
+
+#[derive(Serialize)]
+struct ListAllMyBucketsResult {
+  #[serde(rename = "@xmlns")]
+  xmlns: &'static str,
+  #[serde(rename = "Owner")]
+  owner: Owner,
+  #[serde(rename = "Buckets")]
+  buckets: BucketList,
+}
+
 // List Buckets - GET /
 #[utoipa::path(
     get,
     path = "/",
     tag = BUCKET_TAG,
     responses(
-        (status = 200, description = "Object uploaded successfully"),
+        (status = 200, description = "ListAllMyBucketsResult XML", content_type = "application/xml"),
         (status = 500, description = "Internal server error")
     )
 )]
-pub async fn list_buckets() -> Response {
-  // TODO: 返回符合 S3 规范的 XML
-  Response::builder()
-    .status(StatusCode::OK)
-    .header("Content-Type", "application/xml")
-    .body(Body::from(
-      "<ListAllMyBucketsResult>...</ListAllMyBucketsResult>",
-    ))
-    .unwrap()
+pub async fn list_buckets(
+  State(state): State<AppState>,
+  Extension(principal): Extension<Principal>,
+) -> Result<Response, S3Error> {
+  let buckets = state.storage.buckets.list_buckets()?;
+  let result = ListAllMyBucketsResult {
+    xmlns: S3_XMLNS,
+    owner: Owner::new(principal.owner_id()),
+    buckets: BucketList {
+      buckets: buckets
+        .into_iter()
+        .filter(|bucket| bucket.owner == principal.owner_id())
+        .map(|bucket| BucketEntry {
+          name: bucket.name,
+          creation_date: format_timestamp(bucket.created_at),
+        })
+        .collect(),
+    },
+  };
+  Ok(xml_response("ListAllMyBucketsResult", &result))
 }
 
 // Create Bucket - PUT /{bucket}
@@ -41,12 +104,63 @@ pub async fn list_buckets() -> Response {
     responses(
         (status = 200, description = "Bucket created"),
         (status = 400, description = "Invalid bucket name"),
+        (status = 409, description = "Bucket already exists"),
     ),
     tag = BUCKET_TAG
 )]
-pub async fn create_bucket(Path(bucket): Path<String>) -> StatusCode {
+pub async fn create_bucket(
+  State(state): State<AppState>,
+  Extension(principal): Extension<Principal>,
+  Path(bucket): Path<String>,
+  headers: HeaderMap,
+) -> Result<impl IntoResponse, S3Error> {
   debug!("Create bucket: {}", bucket);
-  StatusCode::NO_CONTENT // 204
+  let owner = principal.owner_id();
+  // ACL、去重和 Object Lock 与 bucket 在同一个事务中创建，不会留下只完成一半的 bucket
+  let options = CreateBucketOptions {
+    acl: header_acl(&headers, owner, owner)?,
+    dedup: bool_header(&headers, X_MAXIO_BUCKET_DEDUP)?,
+    object_lock: bool_header(&headers, X_AMZ_BUCKET_OBJECT_LOCK_ENABLED)?,
+  };
+  state
+    .storage
+    .buckets
+    .create_bucket(&bucket, owner, options)?;
+  Ok([(header::LOCATION, format!("/{bucket}"))])
+}
+
+/// 取值为 true/false（大小写不敏感）的请求头，缺省为 false
+fn bool_header(headers: &HeaderMap, name: &'static str) -> Result<bool, S3Error> {
+  match headers.get(name).map(|v| v.to_str()) {
+    None => Ok(false),
+    Some(Ok(value)) if value.eq_ignore_ascii_case("true") => Ok(true),
+    Some(Ok(value)) if value.eq_ignore_ascii_case("false") => Ok(false),
+    Some(_) => Err(
+      S3Error::new(S3ErrorCode::InvalidArgument)
+        .with_message(format!("{name} must be true or false")),
+    ),
+  }
+}
+
+// Head Bucket - HEAD /{bucket}
+#[utoipa::path(
+    head,
+    path = "/{bucket}",
+    params(
+        ("bucket" = String, Path, description = "Bucket 名称")
+    ),
+    responses(
+        (status = 200, description = "Bucket exists"),
+        (status = 404, description = "Bucket not found"),
+    ),
+    tag = BUCKET_TAG
+)]
+pub async fn head_bucket(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+) -> Result<StatusCode, S3Error> {
+  state.storage.buckets.get_bucket(&bucket)?;
+  Ok(StatusCode::OK)
 }
 
 // Delete Bucket - DELETE /{bucket}
@@ -58,100 +172,94 @@ pub async fn create_bucket(Path(bucket): Path<String>) -> StatusCode {
         ("bucket" = String, Path, description = "Bucket 名称")
     ),
     responses(
-        (status = 200, description = "Bucket deleted"),
+        (status = 204, description = "Bucket deleted"),
         (status = 404, description = "Bucket not found"),
+        (status = 409, description = "Bucket not empty"),
     ),
     tag = BUCKET_TAG
 )]
-pub async fn delete_bucket(Path(bucket): Path<String>) -> StatusCode {
-  println!("Delete bucket: {}", bucket);
-  StatusCode::NO_CONTENT
+pub async fn delete_bucket(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+) -> Result<StatusCode, S3Error> {
+  debug!("Delete bucket: {}", bucket);
+  state.storage.buckets.delete_bucket(&bucket)?;
+  Ok(StatusCode::NO_CONTENT)
+}
+
+/// 策略只能由 bucket owner 管理；其他用户即使被策略授权也返回 405，与 S3 一致
+fn check_policy_owner(
+  state: &AppState,
+  bucket: &str,
+  principal: &Principal,
+) -> Result<BucketMeta, S3Error> {
+  let meta = state.storage.buckets.get_bucket(bucket)?;
+  if meta.owner != principal.owner_id() {
+    return Err(S3ErrorCode::MethodNotAllowed.into());
+  }
+  Ok(meta)
 }
 
 // Get Bucket Policy - GET /{bucket}?policy
-pub async fn get_bucket_policy(Path(bucket): Path<String>) -> Response {
-  // TODO: 返回 JSON 格式的策略，header Content-Type: application/json
-  Response::builder()
-    .status(StatusCode::OK)
-    .header("Content-Type", "application/json")
-    .body(Body::from(r#"{"Version":"2012-10-17","Statement":[]}"#))
-    .unwrap()
+pub async fn get_bucket_policy(
+  State(state): State<AppState>,
+  Extension(principal): Extension<Principal>,
+  Path(bucket): Path<String>,
+) -> Result<Response, S3Error> {
+  let meta = check_policy_owner(&state, &bucket, &principal)?;
+  let Some(policy) = meta.policy else {
+    return Err(S3Error::new(S3ErrorCode::NoSuchBucketPolicy).with_resource(bucket));
+  };
+  Ok(
+    (
+      [(header::CONTENT_TYPE, "application/json")],
+      policy.document,
+    )
+      .into_response(),
+  )
 }
 
 // Put Bucket Policy - PUT /{bucket}?policy
 pub async fn put_bucket_policy(
+  State(state): State<AppState>,
+  Extension(principal): Extension<Principal>,
   Path(bucket): Path<String>,
-  policy: Json<HashMap<String, serde_json::Value>>,
-) -> StatusCode {
-  println!("Put bucket policy for {}: {:?}", bucket, policy);
-  StatusCode::NO_CONTENT
-}
-
-// Get Bucket Location - GET /{bucket}?location
-pub async fn get_bucket_location(Path(bucket): Path<String>) -> Response {
-  let xml = format!(
-    r#"<?xml version="1.0" encoding="UTF-8"?>
-        <LocationConstraint xmlns="http://s3.amazonaws.com/doc/2006-03-01/">us-east-1</LocationConstraint>"#
+  S3Body(body): S3Body,
+) -> Result<StatusCode, S3Error> {
+  check_policy_owner(&state, &bucket, &principal)?;
+  let document = std::str::from_utf8(&body).map_err(|_| S3ErrorCode::MalformedPolicy)?;
+  let policy = parse_policy(&bucket, document).map_err(anyhow::Error::from)?;
+  debug!(
+    "Put bucket policy for {}: {} statements",
+    bucket,
+    policy.statements.len()
   );
-
-  Response::builder()
-    .status(StatusCode::OK)
-    .header("Content-Type", "application/xml")
-    .body(Body::from(xml))
-    .unwrap()
+  state
+    .storage
+    .buckets
+    .put_bucket_policy(&bucket, Some(policy))?;
+  Ok(StatusCode::NO_CONTENT)
 }
 
-// Get Bucket ACL - GET /{bucket}?acl
-pub async fn get_bucket_acl(Path(bucket): Path<String>) -> Response {
-  let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
-    <AccessControlPolicy xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
-      <Owner>
-        <ID>owner-id</ID>
-        <DisplayName>owner-name</DisplayName>
-      </Owner>
-      <AccessControlList>
-        <Grant>
-          <Grantee xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:type="CanonicalUser">
-            <ID>grantee-id</ID>
-            <DisplayName>grantee-name</DisplayName>
-          </Grantee>
-          <Permission>FULL_CONTROL</Permission>
-        </Grant>
-      </AccessControlList>
-    </AccessControlPolicy>"#;
-
-  Response::builder()
-    .status(StatusCode::OK)
-    .header("Content-Type", "application/xml")
-    .body(Body::from(xml))
-    .unwrap()
-}
-
-// Put Bucket ACL - PUT /{bucket}?acl
-pub async fn put_bucket_acl(Path(bucket): Path<String>, _headers: HeaderMap) -> StatusCode {
-  println!("Put bucket ACL: {}", bucket);
-  StatusCode::NO_CONTENT
+// Delete Bucket Policy - DELETE /{bucket}?policy
+pub async fn delete_bucket_policy(
+  State(state): State<AppState>,
+  Extension(principal): Extension<Principal>,
+  Path(bucket): Path<String>,
+) -> Result<StatusCode, S3Error> {
+  check_policy_owner(&state, &bucket, &principal)?;
+  state.storage.buckets.put_bucket_policy(&bucket, None)?;
+  Ok(StatusCode::NO_CONTENT)
 }
 
-// Get Bucket CORS - GET /{bucket}?cors
-pub async fn get_bucket_cors(Path(bucket): Path<String>) -> Response {
+// Get Bucket Location - GET /{bucket}?location
+pub async fn get_bucket_location(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+) -> Result<Response, S3Error> {
+  state.storage.buckets.get_bucket(&bucket)?;
   let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
-    <CORSConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
-      <CORSRule>
-        <AllowedOrigin>*</AllowedOrigin>
-        <AllowedMethod>GET</AllowedMethod>
-      </CORSRule>
-    </CORSConfiguration>"#;
-
-  Response::builder()
-    .status(StatusCode::OK)
-    .header("Content-Type", "application/xml")
-    .body(Body::from(xml))
-    .unwrap()
-}
+        <LocationConstraint xmlns="http://s3.amazonaws.com/doc/2006-03-01/">us-east-1</LocationConstraint>"#;
 
-// Put Bucket CORS - PUT /{bucket}?cors
-pub async fn put_bucket_cors(Path(bucket): Path<String>, _body: Body) -> StatusCode {
-  println!("Put bucket CORS: {}", bucket);
-  StatusCode::NO_CONTENT
+  Ok(([(header::CONTENT_TYPE, "application/xml")], xml).into_response())
 }
@@ -0,0 +1,212 @@
+//! 请求数据的完整性校验：`Content-MD5`、`x-amz-checksum-*` 请求头和尾部校验值，以及返回给客户端的校验值。
+use crate::chunked::X_AMZ_TRAILER;
+use crate::error::{S3Error, S3ErrorCode};
+use axum::http::{HeaderMap, HeaderValue};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::Serialize;
+use server::checksum::{ChecksumAlgorithm, ChecksumType, IntegrityCheck, ObjectChecksum};
+
+pub const CONTENT_MD5: &str = "content-md5";
+pub const X_AMZ_SDK_CHECKSUM_ALGORITHM: &str = "x-amz-sdk-checksum-algorithm";
+/// CreateMultipartUpload 指定各分片使用的校验算法
+pub const X_AMZ_CHECKSUM_ALGORITHM: &str = "x-amz-checksum-algorithm";
+pub const X_AMZ_CHECKSUM_TYPE: &str = "x-amz-checksum-type";
+/// GET/HEAD 时为 `ENABLED` 才返回校验值
+pub const X_AMZ_CHECKSUM_MODE: &str = "x-amz-checksum-mode";
+
+fn invalid_request(message: impl Into<String>) -> S3Error {
+  S3Error::new(S3ErrorCode::InvalidRequest).with_message(message.into())
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+  headers.get(name).map(|v| v.to_str().unwrap_or_default())
+}
+
+/// 解析写入请求（PutObject / UploadPart）的完整性校验要求
+pub fn request_integrity(headers: &HeaderMap) -> Result<IntegrityCheck, S3Error> {
+  let content_md5 = match header_str(headers, CONTENT_MD5) {
+    None => None,
+    Some(value) => Some(
+      STANDARD
+        .decode(value.trim())
+        .ok()
+        .and_then(|digest| <[u8; 16]>::try_from(digest).ok())
+        .ok_or(S3ErrorCode::InvalidDigest)?,
+    ),
+  };
+
+  let mut provided = ChecksumAlgorithm::ALL
+    .into_iter()
+    .filter_map(|algorithm| Some((algorithm, header_str(headers, algorithm.header_name())?)));
+  let checksum = provided.next();
+  // 尾部校验值已在解码 aws-chunked 请求体时验证
+  let trailer = header_str(headers, X_AMZ_TRAILER).and_then(ChecksumAlgorithm::from_header_name);
+  if provided.next().is_some() || (checksum.is_some() && trailer.is_some()) {
+    return Err(invalid_request(
+      "Expecting a single x-amz-checksum- header. Multiple checksum Types are not allowed.",
+    ));
+  }
+  let sdk_algorithm = match header_str(headers, X_AMZ_SDK_CHECKSUM_ALGORITHM) {
+    None => None,
+    Some(name) => Some(
+      ChecksumAlgorithm::from_name(name)
+        .ok_or_else(|| invalid_request("Checksum algorithm provided is unsupported."))?,
+    ),
+  };
+
+  let (algorithm, expected) = match (checksum, trailer) {
+    (Some((algorithm, value)), _) => {
+      let valid = STANDARD
+        .decode(value.trim())
+        .is_ok_and(|digest| digest.len() == algorithm.digest_len());
+      if !valid {
+        return Err(invalid_request(format!(
+          "Value for {} header is invalid.",
+          algorithm.header_name()
+        )));
+      }
+      (Some(algorithm), Some(value.trim().to_string()))
+    }
+    (None, Some(algorithm)) => (Some(algorithm), None),
+    (None, None) => (sdk_algorithm, None),
+  };
+  if sdk_algorithm.is_some() && sdk_algorithm != algorithm {
+    return Err(invalid_request(
+      "Value for x-amz-sdk-checksum-algorithm header is invalid.",
+    ));
+  }
+  Ok(IntegrityCheck {
+    content_md5,
+    algorithm,
+    expected,
+  })
+}
+
+/// CreateMultipartUpload 的 `x-amz-checksum-algorithm` 和 `x-amz-checksum-type`，类型缺省为 COMPOSITE
+pub fn upload_checksum(
+  headers: &HeaderMap,
+) -> Result<Option<(ChecksumAlgorithm, ChecksumType)>, S3Error> {
+  let checksum_type = match header_str(headers, X_AMZ_CHECKSUM_TYPE) {
+    None => None,
+    Some(name) => Some(
+      ChecksumType::from_name(name)
+        .ok_or_else(|| invalid_request("Value for x-amz-checksum-type header is invalid."))?,
+    ),
+  };
+  let Some(name) = header_str(headers, X_AMZ_CHECKSUM_ALGORITHM) else {
+    if checksum_type.is_some() {
+      return Err(invalid_request(
+        "The x-amz-checksum-type header can only be used with the x-amz-checksum-algorithm header.",
+      ));
+    }
+    return Ok(None);
+  };
+  let algorithm = ChecksumAlgorithm::from_name(name)
+    .ok_or_else(|| invalid_request("Checksum algorithm provided is unsupported."))?;
+  Ok(Some((
+    algorithm,
+    checksum_type.unwrap_or(ChecksumType::Composite),
+  )))
+}
+
+/// 分片上传的校验算法和类型响应头
+pub fn upload_checksum_headers(checksum: Option<(ChecksumAlgorithm, ChecksumType)>) -> HeaderMap {
+  let mut headers = HeaderMap::new();
+  if let Some((algorithm, checksum_type)) = checksum {
+    headers.insert(
+      X_AMZ_CHECKSUM_ALGORITHM,
+      HeaderValue::from_static(algorithm.name()),
+    );
+    headers.insert(
+      X_AMZ_CHECKSUM_TYPE,
+      HeaderValue::from_static(checksum_type.name()),
+    );
+  }
+  headers
+}
+
+/// 校验值响应头，如 `x-amz-checksum-crc32`
+pub fn value_headers(algorithm: ChecksumAlgorithm, value: &str) -> HeaderMap {
+  let mut headers = HeaderMap::new();
+  if let Ok(value) = value.parse() {
+    headers.insert(algorithm.header_name(), value);
+  }
+  headers
+}
+
+/// 对象的校验值和校验类型响应头
+pub fn checksum_headers(checksum: Option<&ObjectChecksum>) -> HeaderMap {
+  let Some(checksum) = checksum else {
+    return HeaderMap::new();
+  };
+  let mut headers = value_headers(checksum.algorithm, &checksum.value);
+  headers.insert(
+    X_AMZ_CHECKSUM_TYPE,
+    HeaderValue::from_static(checksum.checksum_type.name()),
+  );
+  headers
+}
+
+/// GET/HEAD 请求是否带有 `x-amz-checksum-mode: ENABLED`
+pub fn checksum_mode_enabled(headers: &HeaderMap) -> bool {
+  header_str(headers, X_AMZ_CHECKSUM_MODE).is_some_and(|mode| mode.eq_ignore_ascii_case("ENABLED"))
+}
+
+/// XML 响应中按算法区分的校验值元素，如 `<ChecksumCRC32>`
+#[derive(Serialize, Default)]
+pub struct ChecksumElements {
+  #[serde(rename = "ChecksumCRC32", skip_serializing_if = "Option::is_none")]
+  crc32: Option<String>,
+  #[serde(rename = "ChecksumCRC32C", skip_serializing_if = "Option::is_none")]
+  crc32c: Option<String>,
+  #[serde(rename = "ChecksumSHA1", skip_serializing_if = "Option::is_none")]
+  sha1: Option<String>,
+  #[serde(rename = "ChecksumSHA256", skip_serializing_if = "Option::is_none")]
+  sha256: Option<String>,
+}
+
+impl ChecksumElements {
+  pub fn new(algorithm: Option<ChecksumAlgorithm>, value: Option<String>) -> Self {
+    let mut elements = Self::default();
+    let slot = match algorithm {
+      Some(ChecksumAlgorithm::Crc32) => &mut elements.crc32,
+      Some(ChecksumAlgorithm::Crc32c) => &mut elements.crc32c,
+      Some(ChecksumAlgorithm::Sha1) => &mut elements.sha1,
+      Some(ChecksumAlgorithm::Sha256) => &mut elements.sha256,
+      None => return elements,
+    };
+    *slot = value;
+    elements
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_integrity_headers() {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_MD5, "XUFAKrxLKna5cZ2REBfFkg==".parse().unwrap());
+    headers.insert("x-amz-checksum-crc32", "DUoRhQ==".parse().unwrap());
+    let check = request_integrity(&headers).unwrap();
+    assert_eq!(check.algorithm, Some(ChecksumAlgorithm::Crc32));
+    assert_eq!(check.expected.as_deref(), Some("DUoRhQ=="));
+    assert!(check.content_md5.is_some());
+
+    headers.insert("x-amz-checksum-sha1", "AAAA".parse().unwrap());
+    assert!(request_integrity(&headers).is_err());
+    headers.remove("x-amz-checksum-sha1");
+    headers.insert(CONTENT_MD5, "not-md5".parse().unwrap());
+    assert!(request_integrity(&headers).is_err());
+
+    // 尾部校验只记录算法
+    let mut headers = HeaderMap::new();
+    headers.insert(X_AMZ_TRAILER, "x-amz-checksum-crc32c".parse().unwrap());
+    headers.insert(X_AMZ_SDK_CHECKSUM_ALGORITHM, "CRC32C".parse().unwrap());
+    let check = request_integrity(&headers).unwrap();
+    assert_eq!(check.algorithm, Some(ChecksumAlgorithm::Crc32c));
+    assert_eq!(check.expected, None);
+  }
+}
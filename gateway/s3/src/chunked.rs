@@ -0,0 +1,504 @@
+//! `Content-Encoding: aws-chunked` 请求体解码。
+//! SDK 以 `<hex 大小>;chunk-signature=<签名>\r\n<数据>\r\n` 分块发送，最后是大小为 0 的块和可选的尾部校验头。
+//! 解码时逐块校验签名链并去掉分块外壳，只缓存单个分块，不会把整个对象读入内存。
+//! 非分块请求的 `x-amz-content-sha256` 为请求体摘要时，在读取过程中计算 SHA-256 并在结束时比对。
+use crate::auth::{ChunkSigner, UNSIGNED_PAYLOAD, X_AMZ_CONTENT_SHA256};
+use crate::error::{S3Error, S3ErrorCode};
+use axum::body::{Body, BodyDataStream};
+use axum::extract::{FromRequest, Request};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use bytes::{Bytes, BytesMut};
+use futures_util::StreamExt;
+use server::checksum::{Checksum, ChecksumAlgorithm};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+pub const STREAMING_SIGNED: &str = "STREAMING-AWS4-HMAC-SHA256-PAYLOAD";
+pub const STREAMING_SIGNED_TRAILER: &str = "STREAMING-AWS4-HMAC-SHA256-PAYLOAD-TRAILER";
+pub const STREAMING_UNSIGNED_TRAILER: &str = "STREAMING-UNSIGNED-PAYLOAD-TRAILER";
+pub const X_AMZ_DECODED_CONTENT_LENGTH: &str = "x-amz-decoded-content-length";
+pub const X_AMZ_TRAILER: &str = "x-amz-trailer";
+const TRAILER_SIGNATURE: &str = "x-amz-trailer-signature";
+
+/// 单个分块的大小上限，超过视为请求体格式错误
+const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+/// 分块头和尾部校验头的单行长度上限
+const MAX_LINE_SIZE: usize = 4096;
+
+#[derive(Debug)]
+pub enum ChunkedError {
+  SignatureDoesNotMatch,
+  IncompleteBody,
+  Malformed(&'static str),
+  BadDigest(ChecksumAlgorithm),
+  /// 请求体与签名的 `x-amz-content-sha256` 不一致
+  ContentSha256Mismatch,
+  Body(axum::Error),
+}
+
+impl fmt::Display for ChunkedError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ChunkedError::SignatureDoesNotMatch => write!(f, "chunk signature does not match"),
+      ChunkedError::IncompleteBody => write!(f, "incomplete aws-chunked body"),
+      ChunkedError::Malformed(message) => write!(f, "malformed aws-chunked body: {message}"),
+      ChunkedError::BadDigest(algorithm) => write!(f, "{} checksum mismatch", algorithm.name()),
+      ChunkedError::ContentSha256Mismatch => write!(f, "x-amz-content-sha256 mismatch"),
+      ChunkedError::Body(err) => write!(f, "failed to read request body: {err}"),
+    }
+  }
+}
+
+impl std::error::Error for ChunkedError {}
+
+struct ChunkedDecoder {
+  inner: BodyDataStream,
+  buf: BytesMut,
+  /// None 表示 `STREAMING-UNSIGNED-PAYLOAD-TRAILER`，分块不带签名
+  signer: Option<ChunkSigner>,
+  previous_signature: String,
+  /// x-amz-trailer 声明的尾部校验算法
+  trailer: Option<(ChecksumAlgorithm, Checksum)>,
+  decoded_length: u64,
+  received: u64,
+  done: bool,
+}
+
+impl ChunkedDecoder {
+  /// 从底层流读入更多数据，流结束时返回 false
+  async fn fill(&mut self) -> Result<bool, ChunkedError> {
+    match self.inner.next().await {
+      Some(Ok(data)) => {
+        self.buf.extend_from_slice(&data);
+        Ok(true)
+      }
+      Some(Err(err)) => Err(ChunkedError::Body(err)),
+      None => Ok(false),
+    }
+  }
+
+  /// 读取一行（不含 CRLF）；流在行中间结束时返回 IncompleteBody
+  async fn read_line(&mut self) -> Result<Option<String>, ChunkedError> {
+    loop {
+      if let Some(pos) = self.buf.windows(2).position(|w| w == b"\r\n") {
+        let line = self.buf.split_to(pos + 2);
+        let line = std::str::from_utf8(&line[..pos])
+          .map_err(|_| ChunkedError::Malformed("chunk header is not valid UTF-8"))?;
+        return Ok(Some(line.to_string()));
+      }
+      if self.buf.len() > MAX_LINE_SIZE {
+        return Err(ChunkedError::Malformed("chunk header is too long"));
+      }
+      if !self.fill().await? {
+        return match self.buf.is_empty() {
+          true => Ok(None),
+          false => Err(ChunkedError::IncompleteBody),
+        };
+      }
+    }
+  }
+
+  async fn read_exact(&mut self, size: usize) -> Result<Bytes, ChunkedError> {
+    while self.buf.len() < size {
+      if !self.fill().await? {
+        return Err(ChunkedError::IncompleteBody);
+      }
+    }
+    Ok(self.buf.split_to(size).freeze())
+  }
+
+  /// 返回下一个解码后的分块，全部读完并通过校验后返回 None
+  async fn next_chunk(&mut self) -> Result<Option<Bytes>, ChunkedError> {
+    if self.done {
+      return Ok(None);
+    }
+    let line = self
+      .read_line()
+      .await?
+      .ok_or(ChunkedError::IncompleteBody)?;
+    let (size, signature) = match line.split_once(';') {
+      Some((size, extension)) => (size, extension.trim().strip_prefix("chunk-signature=")),
+      None => (line.as_str(), None),
+    };
+    let size = usize::from_str_radix(size.trim(), 16)
+      .map_err(|_| ChunkedError::Malformed("invalid chunk size"))?;
+    if size > MAX_CHUNK_SIZE {
+      return Err(ChunkedError::Malformed("chunk size is too large"));
+    }
+    let data = self.read_exact(size).await?;
+
+    if let Some(signer) = &self.signer {
+      let signature = signature.ok_or(ChunkedError::SignatureDoesNotMatch)?;
+      if !signer.verify_chunk(&self.previous_signature, &data, signature) {
+        return Err(ChunkedError::SignatureDoesNotMatch);
+      }
+      self.previous_signature = signature.to_string();
+    }
+
+    if size == 0 {
+      self.finish().await?;
+      self.done = true;
+      return Ok(None);
+    }
+    // 数据后紧跟 CRLF
+    if self.read_line().await?.as_deref() != Some("") {
+      return Err(ChunkedError::Malformed(
+        "chunk data is not followed by CRLF",
+      ));
+    }
+    self.received += size as u64;
+    if self.received > self.decoded_length {
+      return Err(ChunkedError::Malformed(
+        "body is longer than x-amz-decoded-content-length",
+      ));
+    }
+    if let Some((_, checksum)) = &mut self.trailer {
+      checksum.update(&data);
+    }
+    Ok(Some(data))
+  }
+
+  /// 读取并校验尾部：`x-amz-checksum-*` 以及签名模式下的 `x-amz-trailer-signature`
+  async fn finish(&mut self) -> Result<(), ChunkedError> {
+    if self.received != self.decoded_length {
+      return Err(ChunkedError::IncompleteBody);
+    }
+    let mut trailers = Vec::new();
+    let mut trailer_signature = None;
+    while let Some(line) = self.read_line().await? {
+      if line.is_empty() {
+        break;
+      }
+      let (name, value) = line
+        .split_once(':')
+        .ok_or(ChunkedError::Malformed("invalid trailing header"))?;
+      let (name, value) = (name.trim().to_ascii_lowercase(), value.trim().to_string());
+      if name == TRAILER_SIGNATURE {
+        trailer_signature = Some(value);
+      } else {
+        trailers.push((name, value));
+      }
+    }
+
+    if let Some((algorithm, checksum)) = self.trailer.take() {
+      let expected = trailers
+        .iter()
+        .find(|(name, _)| name == algorithm.header_name())
+        .map(|(_, value)| value.as_str())
+        .ok_or(ChunkedError::Malformed(
+          "declared trailing checksum is missing",
+        ))?;
+      if checksum.finalize() != expected {
+        return Err(ChunkedError::BadDigest(algorithm));
+      }
+    }
+    // 签名模式下只有带尾部的请求才有尾部签名
+    if let Some(signer) = &self.signer
+      && !trailers.is_empty()
+    {
+      let canonical: String = trailers
+        .iter()
+        .map(|(name, value)| format!("{name}:{value}\n"))
+        .collect();
+      let signature = trailer_signature.ok_or(ChunkedError::SignatureDoesNotMatch)?;
+      if !signer.verify_trailer(&self.previous_signature, &canonical, &signature) {
+        return Err(ChunkedError::SignatureDoesNotMatch);
+      }
+    }
+    Ok(())
+  }
+}
+
+/// 边读边计算请求体的 SHA-256，读完后与 `x-amz-content-sha256` 比对
+struct Sha256Verifier {
+  inner: BodyDataStream,
+  hasher: Sha256,
+  expected: [u8; 32],
+}
+
+impl Sha256Verifier {
+  async fn next_chunk(&mut self) -> Result<Option<Bytes>, ChunkedError> {
+    match self.inner.next().await {
+      Some(Ok(data)) => {
+        self.hasher.update(&data);
+        Ok(Some(data))
+      }
+      Some(Err(err)) => Err(ChunkedError::Body(err)),
+      None => match self.hasher.finalize_reset()[..] == self.expected {
+        true => Ok(None),
+        false => Err(ChunkedError::ContentSha256Mismatch),
+      },
+    }
+  }
+}
+
+/// 请求体摘要必须是 64 位十六进制
+fn parse_content_sha256(value: &str) -> Result<[u8; 32], S3Error> {
+  let mut digest = [0u8; 32];
+  match value.len() == 64 && hex::decode_to_slice(value, &mut digest).is_ok() {
+    true => Ok(digest),
+    false => Err(
+      S3Error::new(S3ErrorCode::InvalidArgument)
+        .with_message("x-amz-content-sha256 must be UNSIGNED-PAYLOAD, STREAMING-AWS4-HMAC-SHA256-PAYLOAD or a valid sha256 value."),
+    ),
+  }
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+  headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+/// 请求体为 aws-chunked 时替换为解码后的流式请求体；声明了请求体摘要时替换为边读边校验的请求体
+pub fn decode_request(req: Request, signer: Option<ChunkSigner>) -> Result<Request, S3Error> {
+  let headers = req.headers();
+  let signer = match header_str(headers, X_AMZ_CONTENT_SHA256) {
+    Some(STREAMING_SIGNED | STREAMING_SIGNED_TRAILER) => match signer {
+      Some(signer) => Some(signer),
+      None => {
+        return Err(
+          S3Error::new(S3ErrorCode::InvalidRequest)
+            .with_message("Streaming signed payloads require header-based SigV4 authentication"),
+        );
+      }
+    },
+    Some(STREAMING_UNSIGNED_TRAILER) => None,
+    None | Some(UNSIGNED_PAYLOAD) => return Ok(req),
+    Some(content_sha256) => {
+      let expected = parse_content_sha256(content_sha256)?;
+      let (parts, body) = req.into_parts();
+      let verifier = Sha256Verifier {
+        inner: body.into_data_stream(),
+        hasher: Sha256::new(),
+        expected,
+      };
+      let stream = futures_util::stream::try_unfold(verifier, |mut verifier| async move {
+        let chunk = verifier.next_chunk().await?;
+        Ok::<_, ChunkedError>(chunk.map(|chunk| (chunk, verifier)))
+      });
+      return Ok(Request::from_parts(parts, Body::from_stream(stream)));
+    }
+  };
+
+  let decoded_length = header_str(headers, X_AMZ_DECODED_CONTENT_LENGTH)
+    .and_then(|v| v.parse().ok())
+    .ok_or_else(|| {
+      S3Error::new(S3ErrorCode::MissingContentLength)
+        .with_message("You must provide the x-amz-decoded-content-length HTTP header.")
+    })?;
+  let trailer = match header_str(headers, X_AMZ_TRAILER) {
+    None => None,
+    Some(name) => match ChecksumAlgorithm::from_header_name(name) {
+      Some(algorithm) => Some((algorithm, algorithm.hasher())),
+      None => {
+        return Err(
+          S3Error::new(S3ErrorCode::InvalidRequest)
+            .with_message("The value specified in the x-amz-trailer header is not supported"),
+        );
+      }
+    },
+  };
+
+  let (mut parts, body) = req.into_parts();
+  // 去掉 aws-chunked，保留其余的内容编码
+  if let Some(encoding) = header_str(&parts.headers, header::CONTENT_ENCODING.as_str()) {
+    let remaining: Vec<&str> = encoding
+      .split(',')
+      .map(str::trim)
+      .filter(|e| !e.eq_ignore_ascii_case("aws-chunked") && !e.is_empty())
+      .collect();
+    match HeaderValue::from_str(&remaining.join(",")) {
+      Ok(value) if !remaining.is_empty() => {
+        parts.headers.insert(header::CONTENT_ENCODING, value);
+      }
+      _ => {
+        parts.headers.remove(header::CONTENT_ENCODING);
+      }
+    }
+  }
+  parts.headers.remove(header::CONTENT_LENGTH);
+
+  let decoder = ChunkedDecoder {
+    inner: body.into_data_stream(),
+    buf: BytesMut::new(),
+    previous_signature: signer
+      .as_ref()
+      .map(|signer| signer.seed_signature.clone())
+      .unwrap_or_default(),
+    signer,
+    trailer,
+    decoded_length,
+    received: 0,
+    done: false,
+  };
+  let stream = futures_util::stream::try_unfold(decoder, |mut decoder| async move {
+    let chunk = decoder.next_chunk().await?;
+    Ok::<_, ChunkedError>(chunk.map(|chunk| (chunk, decoder)))
+  });
+  Ok(Request::from_parts(parts, Body::from_stream(stream)))
+}
+
+/// 在错误链中查找请求体解码错误；请求体经过 axum::Error 和 io::Error 两层包装
+pub fn find_chunked_error(err: &anyhow::Error) -> Option<&ChunkedError> {
+  err.chain().find_map(|cause| {
+    let mut current: Option<&(dyn std::error::Error + 'static)> = Some(cause);
+    if let Some(io_err) = cause.downcast_ref::<std::io::Error>() {
+      current = io_err.get_ref().map(|inner| inner as _);
+    }
+    while let Some(err) = current {
+      if let Some(chunked) = err.downcast_ref::<ChunkedError>() {
+        return Some(chunked);
+      }
+      current = err.source();
+    }
+    None
+  })
+}
+
+/// 读取完整请求体的提取器；读取失败时返回对应的 S3 错误（分块签名不匹配、
+/// x-amz-content-sha256 不一致、请求体不完整），而不是 axum 默认的纯文本 400
+pub struct S3Body(pub Bytes);
+
+impl<S: Send + Sync> FromRequest<S> for S3Body {
+  type Rejection = S3Error;
+
+  async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+    let rejection = match Bytes::from_request(req, state).await {
+      Ok(body) => return Ok(S3Body(body)),
+      Err(rejection) => rejection,
+    };
+    let status = rejection.status();
+    let err = anyhow::Error::from(rejection);
+    if let Some(chunked_err) = find_chunked_error(&err) {
+      return Err(chunked_err.into());
+    }
+    Err(S3Error::new(match status {
+      StatusCode::PAYLOAD_TOO_LARGE => S3ErrorCode::EntityTooLarge,
+      _ => S3ErrorCode::IncompleteBody,
+    }))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{S3Body, decode_request};
+  use crate::auth::{ChunkSigner, X_AMZ_CONTENT_SHA256, signing_key};
+  use crate::error::S3ErrorCode;
+  use axum::body::Body;
+  use axum::extract::{FromRequest, Request};
+
+  fn request(content_sha256: &str, body: Vec<u8>, decoded_length: usize) -> Request {
+    Request::builder()
+      .method("PUT")
+      .uri("/examplebucket/chunkObject.txt")
+      .header(X_AMZ_CONTENT_SHA256, content_sha256)
+      .header("content-encoding", "aws-chunked")
+      .header("x-amz-decoded-content-length", decoded_length)
+      .body(Body::from(body))
+      .unwrap()
+  }
+
+  async fn decode(req: Request, signer: Option<ChunkSigner>) -> Result<Vec<u8>, axum::Error> {
+    let req = decode_request(req, signer).unwrap();
+    assert!(req.headers().get("content-encoding").is_none());
+    let body = axum::body::to_bytes(req.into_body(), usize::MAX).await?;
+    Ok(body.to_vec())
+  }
+
+  // AWS 文档中的分块签名示例：66560 字节的 'a'，每块 64KiB
+  #[tokio::test]
+  async fn signed_chunks() {
+    let signer = ChunkSigner {
+      signing_key: signing_key(
+        "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY",
+        "20130524",
+        "us-east-1",
+        "s3",
+      ),
+      amz_date: "20130524T000000Z".into(),
+      scope: "20130524/us-east-1/s3/aws4_request".into(),
+      seed_signature: "4f232c4386841ef735655705268965c44a0e4690baa4adea153f7db9fa80a0a9".into(),
+    };
+    let chunks = [
+      (
+        65536,
+        "ad80c730a21e5b8d04586a2213dd63b9a0e99e0e2307b0ade35a65485a288648",
+      ),
+      (
+        1024,
+        "0055627c9e194cb4542bae2aa5492e3c1575bbb81b612b7d234b86a503ef5497",
+      ),
+      (
+        0,
+        "b6c6ea8a5354eaf15b3cb7646744f4275b71ea724fed81ceb9323e279d449df9",
+      ),
+    ];
+    let mut body = Vec::new();
+    for (size, signature) in chunks {
+      body.extend(format!("{size:x};chunk-signature={signature}\r\n").into_bytes());
+      body.extend(vec![b'a'; size]);
+      body.extend(b"\r\n");
+    }
+
+    let req = request(super::STREAMING_SIGNED, body.clone(), 66560);
+    let data = decode(req, Some(signer.clone())).await.unwrap();
+    assert_eq!(data, vec![b'a'; 66560]);
+
+    // 篡改第二块的数据后签名链校验失败
+    let mut tampered = body;
+    tampered[65536 + 200] = b'b';
+    let req = request(super::STREAMING_SIGNED, tampered, 66560);
+    assert!(decode(req, Some(signer)).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn unsigned_trailer_checksum() {
+    let body = |crc: &str| {
+      format!("5\r\nhello\r\n6\r\n world\r\n0\r\nx-amz-checksum-crc32:{crc}\r\n\r\n").into_bytes()
+    };
+    let with_trailer = |body| {
+      let mut req = request(super::STREAMING_UNSIGNED_TRAILER, body, 11);
+      req
+        .headers_mut()
+        .insert("x-amz-trailer", "x-amz-checksum-crc32".parse().unwrap());
+      req
+    };
+    let data = decode(with_trailer(body("DUoRhQ==")), None).await.unwrap();
+    assert_eq!(data, b"hello world");
+    assert!(decode(with_trailer(body("AAAAAA==")), None).await.is_err());
+    // 实际长度与 x-amz-decoded-content-length 不一致
+    let req = request(super::STREAMING_UNSIGNED_TRAILER, body("DUoRhQ=="), 12);
+    assert!(decode(req, None).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn content_sha256_mismatch() {
+    let put = |content_sha256: &str, body: &'static str| {
+      Request::builder()
+        .method("PUT")
+        .uri("/bucket/key")
+        .header(X_AMZ_CONTENT_SHA256, content_sha256)
+        .body(Body::from(body))
+        .unwrap()
+    };
+    let read = |req: Request| async move {
+      let req = decode_request(req, None).map_err(|err| err.code())?;
+      match S3Body::from_request(req, &()).await {
+        Ok(S3Body(body)) => Ok(body),
+        Err(err) => Err(err.code()),
+      }
+    };
+    // sha256("hello")
+    let hello = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+    assert_eq!(read(put(hello, "hello")).await.unwrap(), "hello");
+    // 重放签名请求但替换请求体
+    assert_eq!(
+      read(put(hello, "hellO")).await.unwrap_err(),
+      S3ErrorCode::XAmzContentSHA256Mismatch
+    );
+    assert!(read(put(super::UNSIGNED_PAYLOAD, "any")).await.is_ok());
+    assert_eq!(
+      read(put("not-a-digest", "hello")).await.unwrap_err(),
+      S3ErrorCode::InvalidArgument
+    );
+  }
+}
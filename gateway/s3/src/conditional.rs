@@ -0,0 +1,141 @@
+//! 条件请求（RFC 9110 第 13 节）：GET/HEAD 的 If-Match 等检查，复制源的 `x-amz-copy-source-if-*` 检查，以及 PUT 的条件写入。
+use crate::error::{S3Error, S3ErrorCode};
+use crate::response::{format_http_date, parse_http_date};
+use axum::http::{HeaderMap, HeaderValue, header};
+use server::metadata::object_meta::ObjectMeta;
+use server::object::PutCondition;
+
+fn header_str(headers: &HeaderMap, name: impl header::AsHeaderName) -> Option<&str> {
+  headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+/// 去掉 ETag 两侧的引号和弱校验前缀 `W/`
+fn unquote(etag: &str) -> &str {
+  let etag = etag.trim();
+  let etag = etag.strip_prefix("W/").unwrap_or(etag);
+  etag.trim_matches('"')
+}
+
+/// If-Match / If-None-Match 的值是 `*` 或逗号分隔的 ETag 列表
+fn etag_matches(value: &str, etag: &str) -> bool {
+  value
+    .split(',')
+    .any(|candidate| candidate.trim() == "*" || unquote(candidate) == etag)
+}
+
+/// 304 也以错误返回，只带 ETag 和 Last-Modified，不带响应体
+fn not_modified(meta: &ObjectMeta) -> S3Error {
+  let mut err = S3Error::new(S3ErrorCode::NotModified);
+  if let Ok(etag) = HeaderValue::from_str(&format!("\"{}\"", meta.etag)) {
+    err = err.with_header(header::ETAG, etag);
+  }
+  if let Ok(date) = HeaderValue::from_str(&format_http_date(meta.last_modified)) {
+    err = err.with_header(header::LAST_MODIFIED, date);
+  }
+  err
+}
+
+enum Failed {
+  Precondition,
+  NotModified,
+}
+
+/// 按 [If-Match, If-Unmodified-Since, If-None-Match, If-Modified-Since] 对应的头名检查。
+/// If-Match 存在时忽略 If-Unmodified-Since，If-None-Match 存在时忽略 If-Modified-Since
+fn evaluate(headers: &HeaderMap, names: [&str; 4], meta: &ObjectMeta) -> Result<(), Failed> {
+  let [
+    if_match,
+    if_unmodified_since,
+    if_none_match,
+    if_modified_since,
+  ] = names;
+  match header_str(headers, if_match) {
+    Some(value) if !etag_matches(value, &meta.etag) => return Err(Failed::Precondition),
+    Some(_) => {}
+    None => {
+      if let Some(since) = header_str(headers, if_unmodified_since).and_then(parse_http_date)
+        && meta.last_modified > since
+      {
+        return Err(Failed::Precondition);
+      }
+    }
+  }
+  match header_str(headers, if_none_match) {
+    Some(value) if etag_matches(value, &meta.etag) => return Err(Failed::NotModified),
+    Some(_) => {}
+    None => {
+      if let Some(since) = header_str(headers, if_modified_since).and_then(parse_http_date)
+        && meta.last_modified <= since
+      {
+        return Err(Failed::NotModified);
+      }
+    }
+  }
+  Ok(())
+}
+
+/// GET/HEAD 的条件检查，不满足时返回 PreconditionFailed 或 NotModified
+pub fn check_preconditions(headers: &HeaderMap, meta: &ObjectMeta) -> Result<(), S3Error> {
+  let names = [
+    header::IF_MATCH.as_str(),
+    header::IF_UNMODIFIED_SINCE.as_str(),
+    header::IF_NONE_MATCH.as_str(),
+    header::IF_MODIFIED_SINCE.as_str(),
+  ];
+  match evaluate(headers, names, meta) {
+    Ok(()) => Ok(()),
+    Err(Failed::Precondition) => Err(S3ErrorCode::PreconditionFailed.into()),
+    Err(Failed::NotModified) => Err(not_modified(meta)),
+  }
+}
+
+/// CopyObject / UploadPartCopy 对源对象的 `x-amz-copy-source-if-*` 检查，不满足时一律返回 412
+pub fn check_copy_source_preconditions(
+  headers: &HeaderMap,
+  meta: &ObjectMeta,
+) -> Result<(), S3Error> {
+  let names = [
+    "x-amz-copy-source-if-match",
+    "x-amz-copy-source-if-unmodified-since",
+    "x-amz-copy-source-if-none-match",
+    "x-amz-copy-source-if-modified-since",
+  ];
+  evaluate(headers, names, meta).map_err(|_| S3ErrorCode::PreconditionFailed.into())
+}
+
+/// 解析 PUT 的条件头：`If-None-Match: *` 只创建，`If-Match: <etag>` 比较后交换
+pub fn put_condition(headers: &HeaderMap) -> Result<Option<PutCondition>, S3Error> {
+  let if_match = header_str(headers, header::IF_MATCH);
+  let if_none_match = header_str(headers, header::IF_NONE_MATCH);
+  let invalid = |message| Err(S3Error::new(S3ErrorCode::NotImplemented).with_message(message));
+  match (if_match, if_none_match) {
+    (None, None) => Ok(None),
+    (Some(_), Some(_)) => invalid("If-Match and If-None-Match cannot be combined"),
+    (None, Some(value)) if value.trim() == "*" => Ok(Some(PutCondition::IfNoneMatch)),
+    (None, Some(_)) => invalid("If-None-Match only supports the value *"),
+    (Some(value), None) if value.contains(',') || value.trim() == "*" => {
+      invalid("If-Match only supports a single ETag")
+    }
+    (Some(value), None) => Ok(Some(PutCondition::IfMatch(unquote(value).to_string()))),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::etag_matches;
+  use crate::response::{format_http_date, parse_http_date};
+
+  #[test]
+  fn etags_and_dates() {
+    assert!(etag_matches("\"abc\"", "abc"));
+    assert!(etag_matches("W/\"x\", \"abc\"", "abc"));
+    assert!(etag_matches("*", "abc"));
+    assert!(!etag_matches("\"abd\"", "abc"));
+    assert_eq!(format_http_date(784111777), "Sun, 06 Nov 1994 08:49:37 GMT");
+    assert_eq!(
+      parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+      Some(784111777)
+    );
+    assert_eq!(parse_http_date("yesterday"), None);
+  }
+}
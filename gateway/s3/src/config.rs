@@ -1,11 +1,146 @@
+use crate::auth::{Authenticator, Credentials};
+use crate::vhost::VirtualHosts;
+use anyhow::Context;
+use figment::Figment;
+use figment::providers::{Env, Format, Serialized, Toml};
 use serde::{Deserialize, Serialize};
+use server::config::{SecurityConfig, ServiceConfig};
+use server::encryption::WrappingKey;
+use server::storage::Storage;
+use std::net::SocketAddr;
+use std::time::Duration;
 
-#[derive(Serialize, Deserialize, Debug, Default)]
-struct S3GatewayConfig {
-  port: u16,
-  address: String,
+#[derive(Serialize, Deserialize, Debug)]
+pub struct S3GatewayConfig {
+  /// 监听地址（bind_address、http_port）、数据目录（data_root）、分片上传暂存目录（temp_dir）
+  /// 以及 `[security]` 中的默认用户、匿名访问和 SSE-S3 主密钥
+  #[serde(flatten)]
+  pub service: ServiceConfig,
+  /// 未完成的分片上传超过该时长（秒）后被自动中止
+  pub multipart_expiry_secs: u64,
+  /// 过期分片上传的清理间隔（秒）
+  pub multipart_cleanup_interval_secs: u64,
+  /// 生命周期规则的执行间隔（秒）
+  pub lifecycle_interval_secs: u64,
+  /// 生命周期任务每个写事务处理的版本数
+  pub lifecycle_batch_size: usize,
+  /// 去重 bucket 中无引用数据块的回收间隔（秒）
+  pub chunk_sweep_interval_secs: u64,
+  /// 额外的用户，在 `s3.toml` 中以 `[[credentials]]` 配置
+  pub credentials: Vec<CredentialConfig>,
+  /// 请求时间与服务器时间允许的最大偏差（秒）
+  pub max_clock_skew_secs: u64,
+  /// 虚拟主机风格访问的基础域名，如 `s3.example.local` 对应 `bucket.s3.example.local/key`
+  pub domains: Vec<String>,
+  /// 网关位于终止 TLS 的反向代理之后时开启，信任 `X-Forwarded-Proto` 判断 `aws:SecureTransport`；
+  /// 网关本身只监听明文 HTTP，未开启时所有请求都视为非 HTTPS
+  pub trusted_proxy: bool,
 }
 
-fn load_config() -> S3GatewayConfig {
-  return S3GatewayConfig::default();
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CredentialConfig {
+  pub access_key: String,
+  pub secret_key: String,
+}
+
+impl Default for S3GatewayConfig {
+  fn default() -> Self {
+    Self {
+      service: ServiceConfig {
+        security: SecurityConfig {
+          access_key: Some("maxio".to_string()),
+          secret_key: Some("maxiosecret".to_string()),
+          ..Default::default()
+        },
+        ..Default::default()
+      },
+      multipart_expiry_secs: 7 * 24 * 3600,
+      multipart_cleanup_interval_secs: 3600,
+      lifecycle_interval_secs: 3600,
+      lifecycle_batch_size: 1000,
+      chunk_sweep_interval_secs: 3600,
+      credentials: Vec::new(),
+      max_clock_skew_secs: 15 * 60,
+      domains: Vec::new(),
+      trusted_proxy: false,
+    }
+  }
+}
+
+/// 依次合并默认值、`s3.toml` 与 `MAXIO_S3_` 前缀的环境变量，嵌套的表用 `__` 分隔，
+/// 如 `MAXIO_S3_SECURITY__MASTER_KEY`
+pub fn load_config() -> anyhow::Result<S3GatewayConfig> {
+  dotenvy::dotenv().ok();
+  let config = Figment::from(Serialized::defaults(S3GatewayConfig::default()))
+    .merge(Toml::file("s3.toml"))
+    .merge(Env::prefixed("MAXIO_S3_").split("__"))
+    .extract()?;
+  Ok(config)
+}
+
+impl S3GatewayConfig {
+  pub fn authenticator(&self) -> Authenticator {
+    let mut credentials = Credentials::default();
+    if let (Some(access_key), Some(secret_key)) = (
+      &self.service.security.access_key,
+      &self.service.security.secret_key,
+    ) {
+      credentials.insert(access_key, secret_key);
+    }
+    for credential in &self.credentials {
+      credentials.insert(&credential.access_key, &credential.secret_key);
+    }
+    Authenticator {
+      credentials,
+      max_clock_skew: Duration::from_secs(self.max_clock_skew_secs),
+      allow_anonymous: self.service.security.allow_anonymous,
+    }
+  }
+
+  /// 打开存储，配置了主密钥时启用 SSE-S3
+  pub fn open_storage(&self) -> anyhow::Result<Storage> {
+    let storage = Storage::open(&self.service.data_root, &self.service.temp_dir)?;
+    Ok(match &self.service.security.master_key {
+      Some(key) => {
+        storage.with_master_key(WrappingKey::from_base64(key).context("invalid master_key")?)
+      }
+      None => storage,
+    })
+  }
+
+  pub fn listen_address(&self) -> SocketAddr {
+    SocketAddr::new(self.service.bind_address, self.service.http_port)
+  }
+
+  pub fn virtual_hosts(&self) -> VirtualHosts {
+    VirtualHosts::new(&self.domains)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // ServiceConfig 的字段在顶层，`[security]` 只覆盖写出的项
+  #[test]
+  fn flattened_service_config() {
+    let toml = r#"
+      http_port = 3901
+      temp_dir = "/tmp/staging"
+
+      [security]
+      allow_anonymous = true
+    "#;
+    let config: S3GatewayConfig = Figment::from(Serialized::defaults(S3GatewayConfig::default()))
+      .merge(Toml::string(toml))
+      .extract()
+      .unwrap();
+    assert_eq!(config.listen_address().to_string(), "0.0.0.0:3901");
+    assert_eq!(
+      config.service.temp_dir,
+      std::path::Path::new("/tmp/staging")
+    );
+    assert!(config.service.security.allow_anonymous);
+    assert_eq!(config.service.security.access_key.as_deref(), Some("maxio"));
+  }
 }
@@ -0,0 +1,246 @@
+//! `?cors` 子资源：bucket 的 CORS 配置读写，以及按配置应答浏览器的预检和实际请求。
+use crate::chunked::S3Body;
+use crate::error::{S3Error, S3ErrorCode};
+use crate::response::{S3_XMLNS, xml_response};
+use crate::state::AppState;
+use axum::extract::{Path, Request, State};
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use server::metadata::cors::{CorsConfiguration, CorsRule};
+
+#[derive(Serialize, Deserialize)]
+struct CorsRuleEntry {
+  #[serde(rename = "ID", skip_serializing_if = "Option::is_none")]
+  id: Option<String>,
+  #[serde(rename = "AllowedOrigin", default)]
+  allowed_origins: Vec<String>,
+  #[serde(rename = "AllowedMethod", default)]
+  allowed_methods: Vec<String>,
+  #[serde(rename = "AllowedHeader", default)]
+  allowed_headers: Vec<String>,
+  #[serde(rename = "ExposeHeader", default)]
+  expose_headers: Vec<String>,
+  #[serde(rename = "MaxAgeSeconds", skip_serializing_if = "Option::is_none")]
+  max_age_seconds: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct CorsConfigurationRequest {
+  #[serde(rename = "CORSRule", default)]
+  rules: Vec<CorsRuleEntry>,
+}
+
+#[derive(Serialize)]
+struct CorsConfigurationResult {
+  #[serde(rename = "@xmlns")]
+  xmlns: &'static str,
+  #[serde(rename = "CORSRule")]
+  rules: Vec<CorsRuleEntry>,
+}
+
+fn parse_cors(body: &[u8]) -> Result<CorsConfiguration, S3Error> {
+  let request: CorsConfigurationRequest = std::str::from_utf8(body)
+    .ok()
+    .and_then(|xml| quick_xml::de::from_str(xml).ok())
+    .ok_or(S3ErrorCode::MalformedXML)?;
+  let rules = request
+    .rules
+    .into_iter()
+    .map(|rule| CorsRule {
+      id: rule.id,
+      allowed_origins: rule.allowed_origins,
+      allowed_methods: rule.allowed_methods,
+      allowed_headers: rule.allowed_headers,
+      expose_headers: rule.expose_headers,
+      max_age_seconds: rule.max_age_seconds,
+    })
+    .collect();
+  Ok(CorsConfiguration { rules })
+}
+
+// GET /{bucket}?cors
+pub async fn get_bucket_cors(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+) -> Result<Response, S3Error> {
+  let meta = state.storage.buckets.get_bucket(&bucket)?;
+  let Some(cors) = meta.cors else {
+    return Err(S3Error::new(S3ErrorCode::NoSuchCORSConfiguration).with_resource(bucket));
+  };
+  let rules = cors
+    .rules
+    .into_iter()
+    .map(|rule| CorsRuleEntry {
+      id: rule.id,
+      allowed_origins: rule.allowed_origins,
+      allowed_methods: rule.allowed_methods,
+      allowed_headers: rule.allowed_headers,
+      expose_headers: rule.expose_headers,
+      max_age_seconds: rule.max_age_seconds,
+    })
+    .collect();
+  Ok(xml_response(
+    "CORSConfiguration",
+    &CorsConfigurationResult {
+      xmlns: S3_XMLNS,
+      rules,
+    },
+  ))
+}
+
+// PUT /{bucket}?cors
+pub async fn put_bucket_cors(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+  S3Body(body): S3Body,
+) -> Result<StatusCode, S3Error> {
+  let cors = parse_cors(&body)?;
+  state.storage.buckets.put_bucket_cors(&bucket, Some(cors))?;
+  Ok(StatusCode::OK)
+}
+
+// DELETE /{bucket}?cors
+pub async fn delete_bucket_cors(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+) -> Result<StatusCode, S3Error> {
+  state.storage.buckets.put_bucket_cors(&bucket, None)?;
+  Ok(StatusCode::NO_CONTENT)
+}
+
+/// 路径中的 bucket 名
+fn path_bucket(req: &Request) -> Option<String> {
+  let bucket = req.uri().path().trim_start_matches('/').split('/').next()?;
+  let bucket = percent_decode_str(bucket).decode_utf8().ok()?;
+  (!bucket.is_empty()).then(|| bucket.into_owned())
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+  headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// 按匹配的规则写入 CORS 响应头；规则允许任意来源时返回 `*`，否则回显 Origin 并允许携带凭证
+fn apply_rule(headers: &mut HeaderMap, rule: &CorsRule, origin: &str) {
+  let any_origin = rule.allowed_origins.iter().any(|allowed| allowed == "*");
+  let values = [
+    (
+      header::ACCESS_CONTROL_ALLOW_ORIGIN,
+      Some(if any_origin {
+        "*".to_string()
+      } else {
+        origin.to_string()
+      }),
+    ),
+    (
+      header::ACCESS_CONTROL_ALLOW_METHODS,
+      Some(rule.allowed_methods.join(", ")),
+    ),
+    (
+      header::ACCESS_CONTROL_EXPOSE_HEADERS,
+      Some(rule.expose_headers.join(", ")).filter(|value| !value.is_empty()),
+    ),
+    (
+      header::ACCESS_CONTROL_MAX_AGE,
+      rule.max_age_seconds.map(|seconds| seconds.to_string()),
+    ),
+    (
+      header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+      (!any_origin).then(|| "true".to_string()),
+    ),
+  ];
+  for (name, value) in values {
+    if let Some(Ok(value)) = value.map(|value| HeaderValue::from_str(&value)) {
+      headers.insert(name, value);
+    }
+  }
+  headers.append(
+    header::VARY,
+    HeaderValue::from_static(
+      "Origin, Access-Control-Request-Headers, Access-Control-Request-Method",
+    ),
+  );
+}
+
+/// OPTIONS 预检请求，不需要签名；没有匹配的规则时返回 403
+fn preflight(state: &AppState, req: &Request) -> Result<Response, S3Error> {
+  let headers = req.headers();
+  let origin = header_str(headers, header::ORIGIN).ok_or_else(|| {
+    S3Error::new(S3ErrorCode::InvalidRequest)
+      .with_message("Insufficient information. Origin request header needed.")
+  })?;
+  let method = header_str(headers, header::ACCESS_CONTROL_REQUEST_METHOD).ok_or_else(|| {
+    S3Error::new(S3ErrorCode::InvalidRequest)
+      .with_message("Invalid Access-Control-Request-Method: null")
+  })?;
+  let requested: Vec<&str> = header_str(headers, header::ACCESS_CONTROL_REQUEST_HEADERS)
+    .unwrap_or_default()
+    .split(',')
+    .map(str::trim)
+    .filter(|name| !name.is_empty())
+    .collect();
+  let bucket = path_bucket(req).ok_or(S3ErrorCode::InvalidRequest)?;
+  let meta = state.storage.buckets.get_bucket(&bucket)?;
+  let rule = meta
+    .cors
+    .as_ref()
+    .and_then(|cors| cors.find_rule(origin, method, &requested))
+    .ok_or(S3ErrorCode::AccessForbidden)?;
+  let mut response = StatusCode::OK.into_response();
+  apply_rule(response.headers_mut(), rule, origin);
+  if !requested.is_empty()
+    && let Ok(value) = HeaderValue::from_str(&requested.join(", "))
+  {
+    response
+      .headers_mut()
+      .insert(header::ACCESS_CONTROL_ALLOW_HEADERS, value);
+  }
+  Ok(response)
+}
+
+/// CORS 层：应答预检请求，并为带 Origin 的实际请求（包括错误响应）附加 CORS 响应头
+pub async fn cors(State(state): State<AppState>, req: Request, next: Next) -> Response {
+  if req.method() == Method::OPTIONS {
+    return preflight(&state, &req).unwrap_or_else(IntoResponse::into_response);
+  }
+  let origin = header_str(req.headers(), header::ORIGIN).map(str::to_string);
+  let (Some(origin), Some(bucket)) = (origin, path_bucket(&req)) else {
+    return next.run(req).await;
+  };
+  let method = req.method().clone();
+  let mut response = next.run(req).await;
+  if let Ok(meta) = state.storage.buckets.get_bucket(&bucket)
+    && let Some(rule) = meta
+      .cors
+      .as_ref()
+      .and_then(|cors| cors.find_rule(&origin, method.as_str(), &[]))
+  {
+    apply_rule(response.headers_mut(), rule, &origin);
+  }
+  response
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn cors_xml() {
+    let xml = r#"<CORSConfiguration><CORSRule><AllowedOrigin>https://a.com</AllowedOrigin><AllowedMethod>PUT</AllowedMethod><AllowedMethod>GET</AllowedMethod><AllowedHeader>*</AllowedHeader><ExposeHeader>ETag</ExposeHeader><MaxAgeSeconds>300</MaxAgeSeconds></CORSRule></CORSConfiguration>"#;
+    let cors = parse_cors(xml.as_bytes()).unwrap();
+    assert_eq!(cors.rules[0].allowed_methods, ["PUT", "GET"]);
+    assert_eq!(cors.rules[0].max_age_seconds, Some(300));
+
+    let mut headers = HeaderMap::new();
+    apply_rule(&mut headers, &cors.rules[0], "https://a.com");
+    assert_eq!(
+      headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+      "https://a.com"
+    );
+    assert_eq!(headers[header::ACCESS_CONTROL_EXPOSE_HEADERS], "ETag");
+    assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "300");
+    assert!(parse_cors(b"<CORSConfiguration>").is_err());
+  }
+}
@@ -0,0 +1,392 @@
+//! S3 在同一路径上通过查询子资源（如 `?uploads`、`?uploadId=`）区分操作，
+//! axum 只能按路径路由，这里按方法和查询参数解析出 [`Endpoint`] 再分发到具体的 handler。
+//! 授权层使用同一份解析结果确定权限，不支持的子资源和方法组合直接返回错误，不会落到其他操作上。
+use crate::acl_handler::{get_bucket_acl, get_object_acl, put_bucket_acl, put_object_acl};
+use crate::bucket_handler::{
+  create_bucket, delete_bucket, delete_bucket_policy, get_bucket_location, get_bucket_policy,
+  head_bucket, list_buckets, put_bucket_policy,
+};
+use crate::copy_source::X_AMZ_COPY_SOURCE;
+use crate::cors_handler::{delete_bucket_cors, get_bucket_cors, put_bucket_cors};
+use crate::encryption_handler::{
+  delete_bucket_encryption, get_bucket_encryption, put_bucket_encryption,
+};
+use crate::error::{S3Error, S3ErrorCode};
+use crate::lifecycle_handler::{
+  delete_bucket_lifecycle, get_bucket_lifecycle, put_bucket_lifecycle,
+};
+use crate::multipart_handler::{
+  abort_multipart_upload, complete_multipart_upload, create_multipart_upload,
+  list_multipart_uploads, list_parts, upload_part, upload_part_copy,
+};
+use crate::object_handler::{
+  copy_object, delete_object, delete_objects, get_object, head_object, list_objects, put_object,
+};
+use crate::object_lock_handler::{
+  get_object_legal_hold, get_object_lock_configuration, get_object_retention,
+  put_object_legal_hold, put_object_lock_configuration, put_object_retention,
+};
+use crate::state::AppState;
+use crate::tagging_handler::{
+  delete_bucket_tagging, delete_object_tagging, get_bucket_tagging, get_object_tagging,
+  put_bucket_tagging, put_object_tagging,
+};
+use crate::versioning_handler::{
+  get_bucket_versioning, list_object_versions, put_bucket_versioning,
+};
+use axum::extract::{Request, State};
+use axum::handler::Handler;
+use axum::http::Method;
+use axum::response::{IntoResponse, Response};
+use server::metadata::constant::Action;
+
+/// 请求中是否带有指定的查询参数（可以没有值，如 `?uploads`）
+pub fn has_param(req: &Request, name: &str) -> bool {
+  req.uri().query().is_some_and(|query| {
+    query
+      .split('&')
+      .any(|pair| pair.split('=').next() == Some(name))
+  })
+}
+
+/// 已实现的 S3 操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+  ListBuckets,
+  CreateBucket,
+  HeadBucket,
+  DeleteBucket,
+  ListObjects,
+  ListObjectVersions,
+  ListMultipartUploads,
+  DeleteObjects,
+  GetBucketLocation,
+  GetBucketTagging,
+  PutBucketTagging,
+  DeleteBucketTagging,
+  GetBucketPolicy,
+  PutBucketPolicy,
+  DeleteBucketPolicy,
+  GetBucketAcl,
+  PutBucketAcl,
+  GetBucketCors,
+  PutBucketCors,
+  DeleteBucketCors,
+  GetBucketLifecycle,
+  PutBucketLifecycle,
+  DeleteBucketLifecycle,
+  GetBucketEncryption,
+  PutBucketEncryption,
+  DeleteBucketEncryption,
+  GetObjectLockConfiguration,
+  PutObjectLockConfiguration,
+  GetBucketVersioning,
+  PutBucketVersioning,
+  GetObject,
+  HeadObject,
+  PutObject,
+  CopyObject,
+  DeleteObject,
+  CreateMultipartUpload,
+  UploadPart,
+  UploadPartCopy,
+  CompleteMultipartUpload,
+  AbortMultipartUpload,
+  ListParts,
+  GetObjectTagging,
+  PutObjectTagging,
+  DeleteObjectTagging,
+  GetObjectAcl,
+  PutObjectAcl,
+  GetObjectRetention,
+  PutObjectRetention,
+  GetObjectLegalHold,
+  PutObjectLegalHold,
+}
+
+/// 子资源在 GET / PUT / DELETE 下对应的操作，None 表示该方法不支持
+type SubResource = (
+  &'static str,
+  Option<Endpoint>,
+  Option<Endpoint>,
+  Option<Endpoint>,
+);
+
+const BUCKET_SUB_RESOURCES: &[SubResource] = {
+  use Endpoint::*;
+  &[
+    (
+      "tagging",
+      Some(GetBucketTagging),
+      Some(PutBucketTagging),
+      Some(DeleteBucketTagging),
+    ),
+    (
+      "policy",
+      Some(GetBucketPolicy),
+      Some(PutBucketPolicy),
+      Some(DeleteBucketPolicy),
+    ),
+    ("acl", Some(GetBucketAcl), Some(PutBucketAcl), None),
+    (
+      "cors",
+      Some(GetBucketCors),
+      Some(PutBucketCors),
+      Some(DeleteBucketCors),
+    ),
+    (
+      "lifecycle",
+      Some(GetBucketLifecycle),
+      Some(PutBucketLifecycle),
+      Some(DeleteBucketLifecycle),
+    ),
+    (
+      "encryption",
+      Some(GetBucketEncryption),
+      Some(PutBucketEncryption),
+      Some(DeleteBucketEncryption),
+    ),
+    (
+      "object-lock",
+      Some(GetObjectLockConfiguration),
+      Some(PutObjectLockConfiguration),
+      None,
+    ),
+    (
+      "versioning",
+      Some(GetBucketVersioning),
+      Some(PutBucketVersioning),
+      None,
+    ),
+    ("location", Some(GetBucketLocation), None, None),
+    ("versions", Some(ListObjectVersions), None, None),
+    ("uploads", Some(ListMultipartUploads), None, None),
+  ]
+};
+
+const OBJECT_SUB_RESOURCES: &[SubResource] = {
+  use Endpoint::*;
+  &[
+    (
+      "tagging",
+      Some(GetObjectTagging),
+      Some(PutObjectTagging),
+      Some(DeleteObjectTagging),
+    ),
+    ("acl", Some(GetObjectAcl), Some(PutObjectAcl), None),
+    (
+      "retention",
+      Some(GetObjectRetention),
+      Some(PutObjectRetention),
+      None,
+    ),
+    (
+      "legal-hold",
+      Some(GetObjectLegalHold),
+      Some(PutObjectLegalHold),
+      None,
+    ),
+  ]
+};
+
+/// 尚未实现的 S3 子资源，返回 NotImplemented 而不是按无子资源的请求处理
+const UNSUPPORTED_SUB_RESOURCES: &[&str] = &[
+  "accelerate",
+  "analytics",
+  "attributes",
+  "intelligent-tiering",
+  "inventory",
+  "logging",
+  "metrics",
+  "notification",
+  "ownershipControls",
+  "policyStatus",
+  "publicAccessBlock",
+  "replication",
+  "requestPayment",
+  "restore",
+  "select",
+  "torrent",
+  "website",
+];
+
+impl Endpoint {
+  /// 按方法、路径和查询子资源解析请求
+  pub fn resolve(req: &Request, bucket: Option<&str>, key: Option<&str>) -> Result<Self, S3Error> {
+    let param = |name: &str| has_param(req, name);
+    let method = req.method();
+    if bucket.is_none() {
+      return match *method {
+        Method::GET | Method::HEAD => Ok(Self::ListBuckets),
+        _ => Err(S3ErrorCode::MethodNotAllowed.into()),
+      };
+    }
+    if let Some(name) = UNSUPPORTED_SUB_RESOURCES.iter().find(|name| param(name)) {
+      return Err(
+        S3Error::new(S3ErrorCode::NotImplemented)
+          .with_message(format!("The {name} subresource is not implemented")),
+      );
+    }
+    let sub_resources = match key {
+      None => BUCKET_SUB_RESOURCES,
+      Some(_) => OBJECT_SUB_RESOURCES,
+    };
+    if let Some((_, get, put, delete)) = sub_resources.iter().find(|(name, ..)| param(name)) {
+      let endpoint = match *method {
+        Method::GET => *get,
+        Method::PUT => *put,
+        Method::DELETE => *delete,
+        _ => None,
+      };
+      return endpoint.ok_or_else(|| S3ErrorCode::MethodNotAllowed.into());
+    }
+
+    let endpoint = match (key, method.clone()) {
+      (None, Method::GET) => Self::ListObjects,
+      (None, Method::HEAD) => Self::HeadBucket,
+      (None, Method::PUT) => Self::CreateBucket,
+      (None, Method::DELETE) => Self::DeleteBucket,
+      (None, Method::POST) if param("delete") => Self::DeleteObjects,
+      (Some(_), Method::GET) if param("uploadId") => Self::ListParts,
+      (Some(_), Method::GET) => Self::GetObject,
+      (Some(_), Method::HEAD) => Self::HeadObject,
+      (Some(_), Method::PUT) => {
+        let copy = req.headers().contains_key(X_AMZ_COPY_SOURCE);
+        match (param("uploadId") && param("partNumber"), copy) {
+          (true, true) => Self::UploadPartCopy,
+          (true, false) => Self::UploadPart,
+          (false, true) => Self::CopyObject,
+          (false, false) => Self::PutObject,
+        }
+      }
+      (Some(_), Method::POST) if param("uploads") => Self::CreateMultipartUpload,
+      (Some(_), Method::POST) if param("uploadId") => Self::CompleteMultipartUpload,
+      (Some(_), Method::DELETE) if param("uploadId") => Self::AbortMultipartUpload,
+      (Some(_), Method::DELETE) => Self::DeleteObject,
+      (_, Method::POST) => {
+        return Err(
+          S3Error::new(S3ErrorCode::InvalidRequest).with_message(match key {
+            None => "Unsupported POST request on a bucket",
+            Some(_) => "Unsupported POST request on an object",
+          }),
+        );
+      }
+      _ => return Err(S3ErrorCode::MethodNotAllowed.into()),
+    };
+    Ok(endpoint)
+  }
+
+  /// 授权使用的权限；S3 中删除 bucket 配置使用对应的 Put 权限，指定 versionId 时使用版本权限
+  pub fn action(self, versioned: bool) -> Action {
+    match self {
+      Self::ListBuckets => Action::ListAllMyBuckets,
+      Self::CreateBucket => Action::CreateBucket,
+      Self::DeleteBucket => Action::DeleteBucket,
+      Self::HeadBucket | Self::ListObjects => Action::ListBucket,
+      Self::ListObjectVersions => Action::ListBucketVersions,
+      Self::ListMultipartUploads => Action::ListBucketMultipartUploads,
+      Self::DeleteObjects => Action::DeleteObject,
+      Self::GetBucketLocation => Action::GetBucketLocation,
+      Self::GetBucketTagging => Action::GetBucketTagging,
+      Self::PutBucketTagging | Self::DeleteBucketTagging => Action::PutBucketTagging,
+      Self::GetBucketPolicy => Action::GetBucketPolicy,
+      Self::PutBucketPolicy => Action::PutBucketPolicy,
+      Self::DeleteBucketPolicy => Action::DeleteBucketPolicy,
+      Self::GetBucketAcl => Action::GetBucketAcl,
+      Self::PutBucketAcl => Action::PutBucketAcl,
+      Self::GetBucketCors => Action::GetBucketCORS,
+      Self::PutBucketCors | Self::DeleteBucketCors => Action::PutBucketCORS,
+      Self::GetBucketLifecycle => Action::GetLifecycleConfiguration,
+      Self::PutBucketLifecycle | Self::DeleteBucketLifecycle => Action::PutLifecycleConfiguration,
+      Self::GetBucketEncryption => Action::GetEncryptionConfiguration,
+      Self::PutBucketEncryption | Self::DeleteBucketEncryption => {
+        Action::PutEncryptionConfiguration
+      }
+      Self::GetObjectLockConfiguration => Action::GetBucketObjectLockConfiguration,
+      Self::PutObjectLockConfiguration => Action::PutBucketObjectLockConfiguration,
+      Self::GetBucketVersioning => Action::GetBucketVersioning,
+      Self::PutBucketVersioning => Action::PutBucketVersioning,
+      Self::GetObject | Self::HeadObject if versioned => Action::GetObjectVersion,
+      Self::GetObject | Self::HeadObject => Action::GetObject,
+      // 上传分片、创建和完成分片上传都按 PutObject 授权
+      Self::PutObject
+      | Self::CopyObject
+      | Self::CreateMultipartUpload
+      | Self::UploadPart
+      | Self::UploadPartCopy
+      | Self::CompleteMultipartUpload => Action::PutObject,
+      Self::DeleteObject if versioned => Action::DeleteObjectVersion,
+      Self::DeleteObject => Action::DeleteObject,
+      Self::AbortMultipartUpload => Action::AbortMultipartUpload,
+      Self::ListParts => Action::ListMultipartUploadParts,
+      Self::GetObjectTagging => Action::GetObjectTagging,
+      Self::PutObjectTagging => Action::PutObjectTagging,
+      Self::DeleteObjectTagging => Action::DeleteObjectTagging,
+      Self::GetObjectAcl => Action::GetObjectAcl,
+      Self::PutObjectAcl => Action::PutObjectAcl,
+      Self::GetObjectRetention => Action::GetObjectRetention,
+      Self::PutObjectRetention => Action::PutObjectRetention,
+      Self::GetObjectLegalHold => Action::GetObjectLegalHold,
+      Self::PutObjectLegalHold => Action::PutObjectLegalHold,
+    }
+  }
+}
+
+/// 所有 S3 路由的入口；授权层已把解析出的 [`Endpoint`] 写入请求扩展
+pub async fn dispatch(State(state): State<AppState>, req: Request) -> Response {
+  let Some(&endpoint) = req.extensions().get::<Endpoint>() else {
+    return S3Error::new(S3ErrorCode::InternalError).into_response();
+  };
+  match endpoint {
+    Endpoint::ListBuckets => list_buckets.call(req, state).await,
+    Endpoint::CreateBucket => create_bucket.call(req, state).await,
+    Endpoint::HeadBucket => head_bucket.call(req, state).await,
+    Endpoint::DeleteBucket => delete_bucket.call(req, state).await,
+    Endpoint::ListObjects => list_objects.call(req, state).await,
+    Endpoint::ListObjectVersions => list_object_versions.call(req, state).await,
+    Endpoint::ListMultipartUploads => list_multipart_uploads.call(req, state).await,
+    Endpoint::DeleteObjects => delete_objects.call(req, state).await,
+    Endpoint::GetBucketLocation => get_bucket_location.call(req, state).await,
+    Endpoint::GetBucketTagging => get_bucket_tagging.call(req, state).await,
+    Endpoint::PutBucketTagging => put_bucket_tagging.call(req, state).await,
+    Endpoint::DeleteBucketTagging => delete_bucket_tagging.call(req, state).await,
+    Endpoint::GetBucketPolicy => get_bucket_policy.call(req, state).await,
+    Endpoint::PutBucketPolicy => put_bucket_policy.call(req, state).await,
+    Endpoint::DeleteBucketPolicy => delete_bucket_policy.call(req, state).await,
+    Endpoint::GetBucketAcl => get_bucket_acl.call(req, state).await,
+    Endpoint::PutBucketAcl => put_bucket_acl.call(req, state).await,
+    Endpoint::GetBucketCors => get_bucket_cors.call(req, state).await,
+    Endpoint::PutBucketCors => put_bucket_cors.call(req, state).await,
+    Endpoint::DeleteBucketCors => delete_bucket_cors.call(req, state).await,
+    Endpoint::GetBucketLifecycle => get_bucket_lifecycle.call(req, state).await,
+    Endpoint::PutBucketLifecycle => put_bucket_lifecycle.call(req, state).await,
+    Endpoint::DeleteBucketLifecycle => delete_bucket_lifecycle.call(req, state).await,
+    Endpoint::GetBucketEncryption => get_bucket_encryption.call(req, state).await,
+    Endpoint::PutBucketEncryption => put_bucket_encryption.call(req, state).await,
+    Endpoint::DeleteBucketEncryption => delete_bucket_encryption.call(req, state).await,
+    Endpoint::GetObjectLockConfiguration => get_object_lock_configuration.call(req, state).await,
+    Endpoint::PutObjectLockConfiguration => put_object_lock_configuration.call(req, state).await,
+    Endpoint::GetBucketVersioning => get_bucket_versioning.call(req, state).await,
+    Endpoint::PutBucketVersioning => put_bucket_versioning.call(req, state).await,
+    Endpoint::GetObject => get_object.call(req, state).await,
+    Endpoint::HeadObject => head_object.call(req, state).await,
+    Endpoint::PutObject => put_object.call(req, state).await,
+    Endpoint::CopyObject => copy_object.call(req, state).await,
+    Endpoint::DeleteObject => delete_object.call(req, state).await,
+    Endpoint::CreateMultipartUpload => create_multipart_upload.call(req, state).await,
+    Endpoint::UploadPart => upload_part.call(req, state).await,
+    Endpoint::UploadPartCopy => upload_part_copy.call(req, state).await,
+    Endpoint::CompleteMultipartUpload => complete_multipart_upload.call(req, state).await,
+    Endpoint::AbortMultipartUpload => abort_multipart_upload.call(req, state).await,
+    Endpoint::ListParts => list_parts.call(req, state).await,
+    Endpoint::GetObjectTagging => get_object_tagging.call(req, state).await,
+    Endpoint::PutObjectTagging => put_object_tagging.call(req, state).await,
+    Endpoint::DeleteObjectTagging => delete_object_tagging.call(req, state).await,
+    Endpoint::GetObjectAcl => get_object_acl.call(req, state).await,
+    Endpoint::PutObjectAcl => put_object_acl.call(req, state).await,
+    Endpoint::GetObjectRetention => get_object_retention.call(req, state).await,
+    Endpoint::PutObjectRetention => put_object_retention.call(req, state).await,
+    Endpoint::GetObjectLegalHold => get_object_legal_hold.call(req, state).await,
+    Endpoint::PutObjectLegalHold => put_object_legal_hold.call(req, state).await,
+  }
+}
@@ -0,0 +1,269 @@
+//! 服务端加密：SSE-S3 / SSE-C 请求头的解析、加密对象的响应头，以及 `?encryption` 子资源（bucket 默认加密）。
+use crate::chunked::S3Body;
+use crate::error::{S3Error, S3ErrorCode};
+use crate::response::{S3_XMLNS, xml_response};
+use crate::state::AppState;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use server::encryption::{Encryption, WrappingKey};
+use server::metadata::encryption::{EncryptionKind, ObjectEncryption, SseAlgorithm};
+
+pub const X_AMZ_SSE: &str = "x-amz-server-side-encryption";
+pub const X_AMZ_SSE_CUSTOMER_ALGORITHM: &str = "x-amz-server-side-encryption-customer-algorithm";
+pub const X_AMZ_SSE_CUSTOMER_KEY: &str = "x-amz-server-side-encryption-customer-key";
+pub const X_AMZ_SSE_CUSTOMER_KEY_MD5: &str = "x-amz-server-side-encryption-customer-key-md5";
+/// CopyObject / UploadPartCopy 中复制源的 SSE-C 请求头以此替换 `x-amz-` 前缀
+const COPY_SOURCE_PREFIX: &str = "x-amz-copy-source-";
+const AES256: &str = "AES256";
+
+fn invalid_argument(message: &'static str) -> S3Error {
+  S3Error::new(S3ErrorCode::InvalidArgument).with_message(message)
+}
+
+/// 解析 SSE-C 的算法、密钥和密钥 MD5 三个请求头，都没有时返回 None
+fn parse_customer_key(headers: &HeaderMap, prefix: &str) -> Result<Option<WrappingKey>, S3Error> {
+  let get = |name: &str| {
+    headers
+      .get(name.replacen("x-amz-", prefix, 1))
+      .and_then(|v| v.to_str().ok())
+  };
+  let algorithm = get(X_AMZ_SSE_CUSTOMER_ALGORITHM);
+  let key = get(X_AMZ_SSE_CUSTOMER_KEY);
+  let key_md5 = get(X_AMZ_SSE_CUSTOMER_KEY_MD5);
+  if algorithm.is_none() && key.is_none() && key_md5.is_none() {
+    return Ok(None);
+  }
+  if algorithm != Some(AES256) {
+    return Err(invalid_argument(
+      "Requests specifying Server Side Encryption with Customer provided keys must provide a valid encryption algorithm.",
+    ));
+  }
+  let Some(key) = key else {
+    return Err(invalid_argument(
+      "Requests specifying Server Side Encryption with Customer provided keys must provide an appropriate secret key.",
+    ));
+  };
+  let Some(key_md5) = key_md5 else {
+    return Err(invalid_argument(
+      "Requests specifying Server Side Encryption with Customer provided keys must provide the client calculated MD5 of the secret key.",
+    ));
+  };
+  let key = WrappingKey::from_base64(key)
+    .map_err(|_| invalid_argument("The secret key was invalid for the specified algorithm."))?;
+  if key.key_md5() != key_md5 {
+    return Err(invalid_argument(
+      "The calculated MD5 hash of the key did not match the hash that was provided.",
+    ));
+  }
+  Ok(Some(key))
+}
+
+/// 请求中的 SSE-C 密钥（`x-amz-server-side-encryption-customer-*`）
+pub fn customer_key(headers: &HeaderMap) -> Result<Option<WrappingKey>, S3Error> {
+  parse_customer_key(headers, "x-amz-")
+}
+
+/// 复制源的 SSE-C 密钥（`x-amz-copy-source-server-side-encryption-customer-*`）
+pub fn copy_source_customer_key(headers: &HeaderMap) -> Result<Option<WrappingKey>, S3Error> {
+  parse_customer_key(headers, COPY_SOURCE_PREFIX)
+}
+
+/// 写入请求指定的加密方式：`x-amz-server-side-encryption: AES256` 或 SSE-C 请求头
+pub fn request_encryption(headers: &HeaderMap) -> Result<Option<Encryption>, S3Error> {
+  let customer_key = customer_key(headers)?;
+  let sse = headers
+    .get(X_AMZ_SSE)
+    .map(|v| v.to_str().unwrap_or_default());
+  match (sse, customer_key) {
+    (None, None) => Ok(None),
+    (None, Some(key)) => Ok(Some(Encryption::Customer(key))),
+    (Some(AES256), None) => Ok(Some(Encryption::S3)),
+    (Some("aws:kms" | "aws:kms:dsse"), None) => Err(
+      S3Error::new(S3ErrorCode::NotImplemented)
+        .with_message("Server-side encryption with KMS keys is not supported"),
+    ),
+    (Some(_), None) => Err(invalid_argument(
+      "The encryption method specified is not supported",
+    )),
+    (Some(_), Some(_)) => Err(invalid_argument(
+      "Server Side Encryption with Customer provided key is incompatible with the encryption method specified",
+    )),
+  }
+}
+
+/// 加密方式对应的响应头
+pub fn kind_headers(kind: Option<&EncryptionKind>) -> HeaderMap {
+  let mut headers = HeaderMap::new();
+  match kind {
+    Some(EncryptionKind::S3) => {
+      headers.insert(X_AMZ_SSE, HeaderValue::from_static(AES256));
+    }
+    Some(EncryptionKind::Customer { key_md5 }) => {
+      headers.insert(
+        X_AMZ_SSE_CUSTOMER_ALGORITHM,
+        HeaderValue::from_static(AES256),
+      );
+      if let Ok(value) = key_md5.parse() {
+        headers.insert(X_AMZ_SSE_CUSTOMER_KEY_MD5, value);
+      }
+    }
+    None => {}
+  }
+  headers
+}
+
+/// 加密对象的响应头
+pub fn encryption_headers(encryption: Option<&ObjectEncryption>) -> HeaderMap {
+  kind_headers(encryption.map(|encryption| &encryption.kind))
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct ApplyByDefault {
+  #[serde(rename = "SSEAlgorithm")]
+  sse_algorithm: String,
+  #[serde(rename = "KMSMasterKeyID", skip_serializing_if = "Option::is_none")]
+  kms_master_key_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct RuleEntry {
+  #[serde(rename = "ApplyServerSideEncryptionByDefault")]
+  apply: Option<ApplyByDefault>,
+  #[serde(rename = "BucketKeyEnabled", skip_serializing_if = "Option::is_none")]
+  bucket_key_enabled: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct ServerSideEncryptionConfiguration {
+  #[serde(rename = "Rule", default)]
+  rules: Vec<RuleEntry>,
+}
+
+#[derive(Serialize)]
+struct ServerSideEncryptionConfigurationResult {
+  #[serde(rename = "@xmlns")]
+  xmlns: &'static str,
+  #[serde(rename = "Rule")]
+  rule: RuleEntry,
+}
+
+fn parse_encryption(body: &[u8]) -> Result<SseAlgorithm, S3Error> {
+  let config: ServerSideEncryptionConfiguration = std::str::from_utf8(body)
+    .ok()
+    .and_then(|xml| quick_xml::de::from_str(xml).ok())
+    .ok_or(S3ErrorCode::MalformedXML)?;
+  let [rule] = &config.rules[..] else {
+    return Err(S3ErrorCode::MalformedXML.into());
+  };
+  let Some(apply) = &rule.apply else {
+    return Err(S3ErrorCode::MalformedXML.into());
+  };
+  match apply.sse_algorithm.as_str() {
+    AES256 if apply.kms_master_key_id.is_none() => Ok(SseAlgorithm::Aes256),
+    AES256 => Err(invalid_argument(
+      "KMSMasterKeyID is not applicable if the default sse algorithm is not aws:kms",
+    )),
+    "aws:kms" | "aws:kms:dsse" => Err(
+      S3Error::new(S3ErrorCode::NotImplemented)
+        .with_message("Server-side encryption with KMS keys is not supported"),
+    ),
+    _ => Err(S3ErrorCode::MalformedXML.into()),
+  }
+}
+
+// GET /{bucket}?encryption
+pub async fn get_bucket_encryption(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+) -> Result<Response, S3Error> {
+  let meta = state.storage.buckets.get_bucket(&bucket)?;
+  let Some(SseAlgorithm::Aes256) = meta.config.encryption else {
+    return Err(
+      S3Error::new(S3ErrorCode::ServerSideEncryptionConfigurationNotFoundError)
+        .with_resource(bucket),
+    );
+  };
+  Ok(xml_response(
+    "ServerSideEncryptionConfiguration",
+    &ServerSideEncryptionConfigurationResult {
+      xmlns: S3_XMLNS,
+      rule: RuleEntry {
+        apply: Some(ApplyByDefault {
+          sse_algorithm: AES256.to_string(),
+          kms_master_key_id: None,
+        }),
+        bucket_key_enabled: Some(false),
+      },
+    },
+  ))
+}
+
+// PUT /{bucket}?encryption
+pub async fn put_bucket_encryption(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+  S3Body(body): S3Body,
+) -> Result<StatusCode, S3Error> {
+  let algorithm = parse_encryption(&body)?;
+  // 没有主密钥时默认加密会让之后的每次写入都失败
+  if !state.storage.objects.sse_s3_enabled() {
+    return Err(
+      S3Error::new(S3ErrorCode::InvalidRequest)
+        .with_message("Server-side encryption is not configured on this server"),
+    );
+  }
+  state
+    .storage
+    .buckets
+    .put_bucket_encryption(&bucket, Some(algorithm))?;
+  Ok(StatusCode::OK)
+}
+
+// DELETE /{bucket}?encryption
+pub async fn delete_bucket_encryption(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+) -> Result<StatusCode, S3Error> {
+  state.storage.buckets.put_bucket_encryption(&bucket, None)?;
+  Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use base64::Engine;
+  use base64::engine::general_purpose::STANDARD;
+
+  #[test]
+  fn encryption_config_and_headers() {
+    let xml = r#"<ServerSideEncryptionConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+      <Rule><ApplyServerSideEncryptionByDefault><SSEAlgorithm>AES256</SSEAlgorithm></ApplyServerSideEncryptionByDefault>
+        <BucketKeyEnabled>true</BucketKeyEnabled></Rule>
+    </ServerSideEncryptionConfiguration>"#;
+    assert_eq!(
+      parse_encryption(xml.as_bytes()).unwrap(),
+      SseAlgorithm::Aes256
+    );
+    let kms = xml.replace("AES256", "aws:kms");
+    assert!(parse_encryption(kms.as_bytes()).is_err());
+    assert!(parse_encryption(b"<ServerSideEncryptionConfiguration/>").is_err());
+
+    let key = STANDARD.encode([1u8; 32]);
+    let key_md5 = WrappingKey::from_base64(&key).unwrap().key_md5();
+    let mut headers = HeaderMap::new();
+    headers.insert(X_AMZ_SSE_CUSTOMER_ALGORITHM, AES256.parse().unwrap());
+    headers.insert(X_AMZ_SSE_CUSTOMER_KEY, key.parse().unwrap());
+    headers.insert(X_AMZ_SSE_CUSTOMER_KEY_MD5, key_md5.parse().unwrap());
+    assert!(matches!(
+      request_encryption(&headers),
+      Ok(Some(Encryption::Customer(_)))
+    ));
+    headers.insert(X_AMZ_SSE, AES256.parse().unwrap());
+    assert!(request_encryption(&headers).is_err());
+    headers.remove(X_AMZ_SSE);
+    headers.insert(X_AMZ_SSE_CUSTOMER_KEY_MD5, "AAAA".parse().unwrap());
+    assert!(customer_key(&headers).is_err());
+  }
+}
@@ -0,0 +1,299 @@
+//! S3 错误模型：错误码到 HTTP 状态码的映射，以及带 RequestId 的 XML 错误响应。
+use crate::chunked::{ChunkedError, find_chunked_error};
+use crate::response::xml_response;
+use axum::extract::Request;
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use server::error::StorageError;
+use std::borrow::Cow;
+use tracing::error;
+use uuid::Uuid;
+
+pub const X_AMZ_REQUEST_ID: &str = "x-amz-request-id";
+
+macro_rules! s3_error_codes {
+  ($($code:ident => ($status:ident, $message:literal),)*) => {
+    /// S3 标准错误码
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum S3ErrorCode {
+      $($code,)*
+    }
+
+    impl S3ErrorCode {
+      pub fn as_str(&self) -> &'static str {
+        match self {
+          $(Self::$code => stringify!($code),)*
+        }
+      }
+
+      pub fn status(&self) -> StatusCode {
+        match self {
+          $(Self::$code => StatusCode::$status,)*
+        }
+      }
+
+      /// 未指定时使用的默认错误信息
+      pub fn message(&self) -> &'static str {
+        match self {
+          $(Self::$code => $message,)*
+        }
+      }
+    }
+  };
+}
+
+s3_error_codes! {
+  AccessDenied => (FORBIDDEN, "Access Denied"),
+  AccessForbidden => (FORBIDDEN, "CORSResponse: This CORS request is not allowed. This is usually because the evalution of Origin, request method / Access-Control-Request-Method or Access-Control-Request-Headers are not whitelisted by the resource's CORS spec."),
+  AuthorizationHeaderMalformed => (BAD_REQUEST, "The authorization header is malformed."),
+  AuthorizationQueryParametersError => (BAD_REQUEST, "Error parsing the X-Amz-Credential parameter."),
+  BadDigest => (BAD_REQUEST, "The Content-MD5 or checksum value that you specified did not match what the server received."),
+  BucketAlreadyExists => (CONFLICT, "The requested bucket name is not available."),
+  BucketAlreadyOwnedByYou => (CONFLICT, "Your previous request to create the named bucket succeeded and you already own it."),
+  BucketNotEmpty => (CONFLICT, "The bucket you tried to delete is not empty."),
+  EntityTooLarge => (BAD_REQUEST, "Your proposed upload exceeds the maximum allowed object size."),
+  EntityTooSmall => (BAD_REQUEST, "Your proposed upload is smaller than the minimum allowed object size."),
+  IncompleteBody => (BAD_REQUEST, "You did not provide the number of bytes specified by the Content-Length HTTP header."),
+  InternalError => (INTERNAL_SERVER_ERROR, "We encountered an internal error. Please try again."),
+  InvalidAccessKeyId => (FORBIDDEN, "The AWS Access Key Id you provided does not exist in our records."),
+  InvalidArgument => (BAD_REQUEST, "Invalid Argument"),
+  InvalidBucketState => (CONFLICT, "The request is not valid with the current state of the bucket."),
+  InvalidBucketName => (BAD_REQUEST, "The specified bucket is not valid."),
+  InvalidDigest => (BAD_REQUEST, "The Content-MD5 or checksum value that you specified is not valid."),
+  InvalidPart => (BAD_REQUEST, "One or more of the specified parts could not be found."),
+  InvalidPartNumber => (RANGE_NOT_SATISFIABLE, "The requested partnumber is not satisfiable"),
+  InvalidPartOrder => (BAD_REQUEST, "The list of parts was not in ascending order."),
+  InvalidRange => (RANGE_NOT_SATISFIABLE, "The requested range is not satisfiable"),
+  InvalidTag => (BAD_REQUEST, "The tag provided was not a valid tag."),
+  InvalidRequest => (BAD_REQUEST, "Invalid Request"),
+  KeyTooLongError => (BAD_REQUEST, "Your key is too long."),
+  MalformedACLError => (BAD_REQUEST, "The XML you provided was not well-formed or did not validate against our published schema."),
+  MalformedPolicy => (BAD_REQUEST, "Policies must be valid JSON and the first byte must be '{'"),
+  MalformedXML => (BAD_REQUEST, "The XML you provided was not well-formed or did not validate against our published schema."),
+  MetadataTooLarge => (BAD_REQUEST, "Your metadata headers exceed the maximum allowed metadata size."),
+  MethodNotAllowed => (METHOD_NOT_ALLOWED, "The specified method is not allowed against this resource."),
+  MissingContentLength => (LENGTH_REQUIRED, "You must provide the Content-Length HTTP header."),
+  NoSuchBucket => (NOT_FOUND, "The specified bucket does not exist."),
+  NoSuchBucketPolicy => (NOT_FOUND, "The bucket policy does not exist"),
+  NoSuchCORSConfiguration => (NOT_FOUND, "The CORS configuration does not exist"),
+  NoSuchKey => (NOT_FOUND, "The specified key does not exist."),
+  NoSuchLifecycleConfiguration => (NOT_FOUND, "The lifecycle configuration does not exist."),
+  NoSuchObjectLockConfiguration => (NOT_FOUND, "The specified object does not have a ObjectLock configuration"),
+  NoSuchTagSet => (NOT_FOUND, "The TagSet does not exist."),
+  NoSuchUpload => (NOT_FOUND, "The specified multipart upload does not exist."),
+  NoSuchVersion => (NOT_FOUND, "The specified version does not exist."),
+  NotImplemented => (NOT_IMPLEMENTED, "A header you provided implies functionality that is not implemented."),
+  NotModified => (NOT_MODIFIED, "Not Modified"),
+  ObjectLockConfigurationNotFoundError => (NOT_FOUND, "Object Lock configuration does not exist for this bucket"),
+  PreconditionFailed => (PRECONDITION_FAILED, "At least one of the pre-conditions you specified did not hold"),
+  RequestTimeTooSkewed => (FORBIDDEN, "The difference between the request time and the server's time is too large."),
+  ServerSideEncryptionConfigurationNotFoundError => (NOT_FOUND, "The server side encryption configuration was not found"),
+  SignatureDoesNotMatch => (FORBIDDEN, "The request signature we calculated does not match the signature you provided. Check your key and signing method."),
+  SlowDown => (SERVICE_UNAVAILABLE, "Please reduce your request rate."),
+  UnexpectedContent => (BAD_REQUEST, "This request does not support content."),
+  XAmzContentSHA256Mismatch => (BAD_REQUEST, "The provided 'x-amz-content-sha256' header does not match what was computed."),
+}
+
+/// handler 和中间件返回的 S3 错误，转换为 `<Error>` XML 响应
+#[derive(Debug)]
+pub struct S3Error {
+  code: S3ErrorCode,
+  message: Cow<'static, str>,
+  resource: Option<String>,
+  headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl S3Error {
+  pub fn new(code: S3ErrorCode) -> Self {
+    Self {
+      code,
+      message: Cow::Borrowed(code.message()),
+      resource: None,
+      headers: Vec::new(),
+    }
+  }
+
+  pub fn with_message(mut self, message: impl Into<Cow<'static, str>>) -> Self {
+    self.message = message.into();
+    self
+  }
+
+  pub fn with_resource(mut self, resource: impl Into<String>) -> Self {
+    self.resource = Some(resource.into());
+    self
+  }
+
+  /// 附加响应头，如 416 时的 `Content-Range`
+  pub fn with_header(mut self, name: impl Into<HeaderName>, value: HeaderValue) -> Self {
+    self.headers.push((name.into(), value));
+    self
+  }
+
+  pub fn code(&self) -> S3ErrorCode {
+    self.code
+  }
+
+  pub fn message(&self) -> &str {
+    &self.message
+  }
+}
+
+impl From<S3ErrorCode> for S3Error {
+  fn from(code: S3ErrorCode) -> Self {
+    Self::new(code)
+  }
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+  #[serde(rename = "Code")]
+  code: &'a str,
+  #[serde(rename = "Message")]
+  message: &'a str,
+  #[serde(rename = "Resource", skip_serializing_if = "Option::is_none")]
+  resource: Option<&'a str>,
+  #[serde(rename = "RequestId")]
+  request_id: &'a str,
+}
+
+impl IntoResponse for S3Error {
+  fn into_response(self) -> Response {
+    // 304 不带响应体
+    let mut response = if self.code == S3ErrorCode::NotModified {
+      Response::default()
+    } else {
+      let request_id = current_request_id().unwrap_or_default();
+      xml_response(
+        "Error",
+        &ErrorBody {
+          code: self.code.as_str(),
+          message: &self.message,
+          resource: self.resource.as_deref(),
+          request_id: &request_id,
+        },
+      )
+    };
+    *response.status_mut() = self.code.status();
+    for (name, value) in self.headers {
+      response.headers_mut().insert(name, value);
+    }
+    response
+  }
+}
+
+/// 存储层错误到 S3 错误码的映射；未识别的错误记录日志后返回 InternalError
+impl From<anyhow::Error> for S3Error {
+  fn from(err: anyhow::Error) -> Self {
+    if let Some(chunked_err) = find_chunked_error(&err) {
+      return chunked_err.into();
+    }
+    let Some(storage_err) = err.downcast_ref::<StorageError>() else {
+      error!("storage error: {:?}", err);
+      return S3Error::new(S3ErrorCode::InternalError);
+    };
+    let (code, resource) = match storage_err {
+      StorageError::NoSuchBucket { bucket } => (S3ErrorCode::NoSuchBucket, Some(bucket)),
+      StorageError::NoSuchKey { key, .. } => (S3ErrorCode::NoSuchKey, Some(key)),
+      StorageError::InvalidBucketName { bucket, .. } => {
+        (S3ErrorCode::InvalidBucketName, Some(bucket))
+      }
+      StorageError::BucketAlreadyExists { bucket } => {
+        (S3ErrorCode::BucketAlreadyExists, Some(bucket))
+      }
+      StorageError::BucketAlreadyOwnedByYou { bucket } => {
+        (S3ErrorCode::BucketAlreadyOwnedByYou, Some(bucket))
+      }
+      StorageError::BucketNotEmpty { bucket } => (S3ErrorCode::BucketNotEmpty, Some(bucket)),
+      StorageError::NoSuchVersion { key, .. } => (S3ErrorCode::NoSuchVersion, Some(key)),
+      StorageError::NoSuchUpload { upload_id } => (S3ErrorCode::NoSuchUpload, Some(upload_id)),
+      StorageError::InvalidPart { .. } => (S3ErrorCode::InvalidPart, None),
+      StorageError::InvalidPartOrder => (S3ErrorCode::InvalidPartOrder, None),
+      StorageError::EntityTooSmall { .. } => (S3ErrorCode::EntityTooSmall, None),
+      StorageError::PreconditionFailed => (S3ErrorCode::PreconditionFailed, None),
+      StorageError::MetadataTooLarge { .. } => (S3ErrorCode::MetadataTooLarge, None),
+      StorageError::InvalidTag { .. } => (S3ErrorCode::InvalidTag, None),
+      StorageError::MalformedPolicy { .. } => (S3ErrorCode::MalformedPolicy, None),
+      StorageError::InvalidCors { .. } => (S3ErrorCode::InvalidRequest, None),
+      StorageError::InvalidLifecycle { .. } => (S3ErrorCode::InvalidArgument, None),
+      StorageError::InvalidEncryption { .. } => (S3ErrorCode::InvalidRequest, None),
+      StorageError::EncryptionKeyMismatch => (S3ErrorCode::AccessDenied, None),
+      StorageError::BadDigest { .. } => (S3ErrorCode::BadDigest, None),
+      StorageError::InvalidChecksum { .. } => (S3ErrorCode::InvalidRequest, None),
+      StorageError::ObjectLocked { key } => (S3ErrorCode::AccessDenied, Some(key)),
+      StorageError::InvalidRetention { .. } => (S3ErrorCode::InvalidArgument, None),
+      StorageError::MissingObjectLockConfiguration => (S3ErrorCode::InvalidRequest, None),
+      StorageError::InvalidBucketState { .. } => (S3ErrorCode::InvalidBucketState, None),
+    };
+    let mut s3_err = S3Error::new(code).with_message(storage_err.to_string());
+    s3_err.resource = resource.cloned();
+    s3_err
+  }
+}
+
+impl From<&ChunkedError> for S3Error {
+  fn from(err: &ChunkedError) -> Self {
+    match err {
+      ChunkedError::SignatureDoesNotMatch => S3Error::new(S3ErrorCode::SignatureDoesNotMatch),
+      ChunkedError::IncompleteBody | ChunkedError::Body(_) => {
+        S3Error::new(S3ErrorCode::IncompleteBody)
+      }
+      ChunkedError::Malformed(message) => {
+        S3Error::new(S3ErrorCode::InvalidRequest).with_message(*message)
+      }
+      ChunkedError::BadDigest(algorithm) => {
+        S3Error::new(S3ErrorCode::BadDigest).with_message(format!(
+          "The {} you specified did not match the calculated checksum.",
+          algorithm.name()
+        ))
+      }
+      ChunkedError::ContentSha256Mismatch => S3Error::new(S3ErrorCode::XAmzContentSHA256Mismatch),
+    }
+  }
+}
+
+tokio::task_local! {
+  static REQUEST_ID: String;
+}
+
+/// 当前请求的 RequestId，只在 `request_id` 中间件内可用
+pub fn current_request_id() -> Option<String> {
+  REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// 为每个请求生成 RequestId，错误响应体中的 RequestId 与 `x-amz-request-id` 头一致
+pub async fn request_id(req: Request, next: Next) -> Response {
+  let id = Uuid::new_v4().simple().to_string().to_uppercase();
+  let mut response = REQUEST_ID.scope(id.clone(), next.run(req)).await;
+  if let Ok(value) = HeaderValue::from_str(&id) {
+    response.headers_mut().insert(X_AMZ_REQUEST_ID, value);
+  }
+  response
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use axum::body::to_bytes;
+
+  #[tokio::test]
+  async fn error_response_has_request_id() {
+    let err = S3Error::new(S3ErrorCode::NoSuchKey).with_resource("a.txt");
+    let response = REQUEST_ID
+      .scope("REQ1".into(), async { err.into_response() })
+      .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.contains("<Code>NoSuchKey</Code>"));
+    assert!(body.contains("<Resource>a.txt</Resource>"));
+    assert!(body.contains("<RequestId>REQ1</RequestId>"));
+
+    let err = S3Error::from(anyhow::Error::new(StorageError::PreconditionFailed));
+    assert_eq!(err.code(), S3ErrorCode::PreconditionFailed);
+    let response = S3Error::new(S3ErrorCode::NotModified).into_response();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert!(to_bytes(response.into_body(), 1).await.unwrap().is_empty());
+  }
+}
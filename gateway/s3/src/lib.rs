@@ -0,0 +1,26 @@
+pub mod acl_handler;
+pub mod auth;
+pub mod authz;
+pub mod bucket_handler;
+pub mod checksum;
+pub mod chunked;
+pub mod conditional;
+pub mod config;
+pub mod copy_source;
+pub mod cors_handler;
+pub mod dispatch;
+pub mod encryption_handler;
+pub mod error;
+pub mod lifecycle_handler;
+pub mod multipart_handler;
+pub mod object_handler;
+pub mod object_lock_handler;
+pub mod openapi;
+pub mod presign;
+pub mod range;
+pub mod response;
+pub mod server;
+pub mod state;
+pub mod tagging_handler;
+pub mod versioning_handler;
+pub mod vhost;
@@ -0,0 +1,376 @@
+//! `?lifecycle` 子资源：bucket 生命周期规则的读写，规则由后台任务执行。
+use crate::chunked::S3Body;
+use crate::error::{S3Error, S3ErrorCode};
+use crate::response::{S3_XMLNS, format_timestamp, xml_response};
+use crate::state::AppState;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use server::metadata::lifecycle::{
+  Expiration, LifecycleConfiguration, LifecycleFilter, LifecycleRule,
+};
+use server::metadata::tagging::Tag;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+#[derive(Serialize, Deserialize, Default)]
+struct TagEntry {
+  #[serde(rename = "Key")]
+  key: String,
+  #[serde(rename = "Value", default)]
+  value: String,
+}
+
+/// 单个条件直接写在 Filter 中，多个条件放在 And 中
+#[derive(Serialize, Deserialize, Default)]
+struct FilterEntry {
+  #[serde(rename = "Prefix", skip_serializing_if = "Option::is_none")]
+  prefix: Option<String>,
+  #[serde(rename = "Tag", default, skip_serializing_if = "Vec::is_empty")]
+  tags: Vec<TagEntry>,
+  #[serde(
+    rename = "ObjectSizeGreaterThan",
+    skip_serializing_if = "Option::is_none"
+  )]
+  object_size_greater_than: Option<u64>,
+  #[serde(rename = "ObjectSizeLessThan", skip_serializing_if = "Option::is_none")]
+  object_size_less_than: Option<u64>,
+  #[serde(rename = "And", skip_serializing_if = "Option::is_none")]
+  and: Option<Box<FilterEntry>>,
+}
+
+impl FilterEntry {
+  fn conditions(&self) -> usize {
+    self.prefix.is_some() as usize
+      + self.tags.len()
+      + self.object_size_greater_than.is_some() as usize
+      + self.object_size_less_than.is_some() as usize
+  }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExpirationEntry {
+  #[serde(rename = "Days", skip_serializing_if = "Option::is_none")]
+  days: Option<u32>,
+  #[serde(rename = "Date", skip_serializing_if = "Option::is_none")]
+  date: Option<String>,
+  #[serde(
+    rename = "ExpiredObjectDeleteMarker",
+    skip_serializing_if = "Option::is_none"
+  )]
+  expired_object_delete_marker: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NoncurrentVersionExpirationEntry {
+  #[serde(rename = "NoncurrentDays")]
+  noncurrent_days: u32,
+  #[serde(
+    rename = "NewerNoncurrentVersions",
+    skip_serializing_if = "Option::is_none"
+  )]
+  newer_noncurrent_versions: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AbortIncompleteMultipartUploadEntry {
+  #[serde(rename = "DaysAfterInitiation")]
+  days_after_initiation: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RuleEntry {
+  #[serde(rename = "ID", skip_serializing_if = "Option::is_none")]
+  id: Option<String>,
+  #[serde(rename = "Filter", skip_serializing_if = "Option::is_none")]
+  filter: Option<FilterEntry>,
+  /// 旧版本 API 把前缀直接写在 Rule 中
+  #[serde(rename = "Prefix", skip_serializing)]
+  prefix: Option<String>,
+  #[serde(rename = "Status")]
+  status: String,
+  #[serde(rename = "Expiration", skip_serializing_if = "Option::is_none")]
+  expiration: Option<ExpirationEntry>,
+  #[serde(
+    rename = "NoncurrentVersionExpiration",
+    skip_serializing_if = "Option::is_none"
+  )]
+  noncurrent_version_expiration: Option<NoncurrentVersionExpirationEntry>,
+  #[serde(
+    rename = "AbortIncompleteMultipartUpload",
+    skip_serializing_if = "Option::is_none"
+  )]
+  abort_incomplete_multipart_upload: Option<AbortIncompleteMultipartUploadEntry>,
+}
+
+#[derive(Deserialize)]
+struct LifecycleConfigurationRequest {
+  #[serde(rename = "Rule", default)]
+  rules: Vec<RuleEntry>,
+}
+
+#[derive(Serialize)]
+struct LifecycleConfigurationResult {
+  #[serde(rename = "@xmlns")]
+  xmlns: &'static str,
+  #[serde(rename = "Rule")]
+  rules: Vec<RuleEntry>,
+}
+
+fn invalid_argument(message: &'static str) -> S3Error {
+  S3Error::new(S3ErrorCode::InvalidArgument).with_message(message)
+}
+
+/// Date 必须是 UTC 零点，如 `2030-01-01T00:00:00Z`
+fn parse_date(value: &str) -> Result<i64, S3Error> {
+  let date = OffsetDateTime::parse(value.trim(), &Rfc3339)
+    .map_err(|_| invalid_argument("Invalid date in the lifecycle Expiration action"))?;
+  let timestamp = date.unix_timestamp();
+  if timestamp % (24 * 3600) != 0 {
+    return Err(invalid_argument("'Date' must be at midnight GMT"));
+  }
+  Ok(timestamp)
+}
+
+fn parse_filter(rule: &RuleEntry) -> Result<LifecycleFilter, S3Error> {
+  let conditions = match &rule.filter {
+    Some(_) if rule.prefix.is_some() => {
+      return Err(invalid_argument(
+        "Rule cannot have both a Prefix and a Filter element",
+      ));
+    }
+    // And 不能再嵌套，也不能与其它条件并列；多个条件必须放在 And 中
+    Some(filter) => match &filter.and {
+      Some(and) if filter.conditions() == 0 && and.and.is_none() => and.as_ref(),
+      None if filter.conditions() <= 1 => filter,
+      _ => return Err(S3ErrorCode::MalformedXML.into()),
+    },
+    None => {
+      return Ok(LifecycleFilter {
+        prefix: rule.prefix.clone().unwrap_or_default(),
+        ..Default::default()
+      });
+    }
+  };
+  Ok(LifecycleFilter {
+    prefix: conditions.prefix.clone().unwrap_or_default(),
+    tags: conditions
+      .tags
+      .iter()
+      .map(|tag| Tag::new(&tag.key, &tag.value))
+      .collect(),
+    object_size_greater_than: conditions.object_size_greater_than,
+    object_size_less_than: conditions.object_size_less_than,
+  })
+}
+
+fn parse_rule(rule: RuleEntry) -> Result<LifecycleRule, S3Error> {
+  let enabled = match rule.status.as_str() {
+    "Enabled" => true,
+    "Disabled" => false,
+    _ => return Err(S3ErrorCode::MalformedXML.into()),
+  };
+  let filter = parse_filter(&rule)?;
+  let (expiration, expired_object_delete_marker) = match &rule.expiration {
+    None => (None, false),
+    Some(expiration) => {
+      let marker = expiration.expired_object_delete_marker.unwrap_or(false);
+      match (expiration.days, &expiration.date) {
+        (Some(_), Some(_)) => {
+          return Err(invalid_argument(
+            "Expiration cannot have both Days and Date",
+          ));
+        }
+        (Some(days), None) => (Some(Expiration::Days(days)), marker),
+        (None, Some(date)) => (Some(Expiration::Date(parse_date(date)?)), marker),
+        (None, None) if expiration.expired_object_delete_marker.is_some() => (None, marker),
+        (None, None) => return Err(S3ErrorCode::MalformedXML.into()),
+      }
+    }
+  };
+  Ok(LifecycleRule {
+    id: rule.id,
+    enabled,
+    filter,
+    expiration,
+    expired_object_delete_marker,
+    noncurrent_days: rule
+      .noncurrent_version_expiration
+      .as_ref()
+      .map(|entry| entry.noncurrent_days),
+    newer_noncurrent_versions: rule
+      .noncurrent_version_expiration
+      .as_ref()
+      .and_then(|entry| entry.newer_noncurrent_versions),
+    abort_incomplete_upload_days: rule
+      .abort_incomplete_multipart_upload
+      .map(|entry| entry.days_after_initiation),
+  })
+}
+
+fn parse_lifecycle(body: &[u8]) -> Result<LifecycleConfiguration, S3Error> {
+  let request: LifecycleConfigurationRequest = std::str::from_utf8(body)
+    .ok()
+    .and_then(|xml| quick_xml::de::from_str(xml).ok())
+    .ok_or(S3ErrorCode::MalformedXML)?;
+  let rules = request
+    .rules
+    .into_iter()
+    .map(parse_rule)
+    .collect::<Result<_, _>>()?;
+  Ok(LifecycleConfiguration { rules })
+}
+
+fn rule_entry(rule: LifecycleRule) -> RuleEntry {
+  let filter = rule.filter;
+  let conditions = FilterEntry {
+    prefix: Some(filter.prefix).filter(|prefix| !prefix.is_empty()),
+    tags: filter
+      .tags
+      .into_iter()
+      .map(|tag| TagEntry {
+        key: tag.key,
+        value: tag.value,
+      })
+      .collect(),
+    object_size_greater_than: filter.object_size_greater_than,
+    object_size_less_than: filter.object_size_less_than,
+    and: None,
+  };
+  let filter = if conditions.conditions() > 1 {
+    FilterEntry {
+      and: Some(Box::new(conditions)),
+      ..Default::default()
+    }
+  } else {
+    conditions
+  };
+  let expiration = match rule.expiration {
+    Some(Expiration::Days(days)) => Some(ExpirationEntry {
+      days: Some(days),
+      date: None,
+      expired_object_delete_marker: None,
+    }),
+    Some(Expiration::Date(date)) => Some(ExpirationEntry {
+      days: None,
+      date: Some(format_timestamp(date)),
+      expired_object_delete_marker: None,
+    }),
+    None if rule.expired_object_delete_marker => Some(ExpirationEntry {
+      days: None,
+      date: None,
+      expired_object_delete_marker: Some(true),
+    }),
+    None => None,
+  };
+  RuleEntry {
+    id: rule.id,
+    filter: Some(filter),
+    prefix: None,
+    status: if rule.enabled { "Enabled" } else { "Disabled" }.to_string(),
+    expiration,
+    noncurrent_version_expiration: rule.noncurrent_days.map(|noncurrent_days| {
+      NoncurrentVersionExpirationEntry {
+        noncurrent_days,
+        newer_noncurrent_versions: rule.newer_noncurrent_versions,
+      }
+    }),
+    abort_incomplete_multipart_upload: rule.abort_incomplete_upload_days.map(
+      |days_after_initiation| AbortIncompleteMultipartUploadEntry {
+        days_after_initiation,
+      },
+    ),
+  }
+}
+
+// GET /{bucket}?lifecycle
+pub async fn get_bucket_lifecycle(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+) -> Result<Response, S3Error> {
+  let meta = state.storage.buckets.get_bucket(&bucket)?;
+  let Some(lifecycle) = meta.config.lifecycle else {
+    return Err(S3Error::new(S3ErrorCode::NoSuchLifecycleConfiguration).with_resource(bucket));
+  };
+  Ok(xml_response(
+    "LifecycleConfiguration",
+    &LifecycleConfigurationResult {
+      xmlns: S3_XMLNS,
+      rules: lifecycle.rules.into_iter().map(rule_entry).collect(),
+    },
+  ))
+}
+
+// PUT /{bucket}?lifecycle
+pub async fn put_bucket_lifecycle(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+  S3Body(body): S3Body,
+) -> Result<StatusCode, S3Error> {
+  let lifecycle = parse_lifecycle(&body)?;
+  state
+    .storage
+    .buckets
+    .put_bucket_lifecycle(&bucket, Some(lifecycle))?;
+  Ok(StatusCode::OK)
+}
+
+// DELETE /{bucket}?lifecycle
+pub async fn delete_bucket_lifecycle(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+) -> Result<StatusCode, S3Error> {
+  state.storage.buckets.put_bucket_lifecycle(&bucket, None)?;
+  Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn lifecycle_xml() {
+    let xml = r#"<LifecycleConfiguration>
+      <Rule><ID>logs</ID><Filter><And><Prefix>logs/</Prefix><Tag><Key>tier</Key><Value>cold</Value></Tag></And></Filter>
+        <Status>Enabled</Status><Expiration><Days>30</Days></Expiration>
+        <NoncurrentVersionExpiration><NoncurrentDays>7</NoncurrentDays><NewerNoncurrentVersions>2</NewerNoncurrentVersions></NoncurrentVersionExpiration></Rule>
+      <Rule><Filter><Prefix>tmp/</Prefix></Filter><Status>Disabled</Status>
+        <Expiration><ExpiredObjectDeleteMarker>true</ExpiredObjectDeleteMarker></Expiration>
+        <AbortIncompleteMultipartUpload><DaysAfterInitiation>3</DaysAfterInitiation></AbortIncompleteMultipartUpload></Rule>
+      <Rule><Prefix>old/</Prefix><Status>Enabled</Status><Expiration><Date>2030-01-01T00:00:00Z</Date></Expiration></Rule>
+    </LifecycleConfiguration>"#;
+    let config = parse_lifecycle(xml.as_bytes()).unwrap();
+    assert!(config.validate().is_ok());
+    let [logs, tmp, old] = &config.rules[..] else {
+      panic!("{config:?}");
+    };
+    assert_eq!(logs.filter.prefix, "logs/");
+    assert_eq!(logs.filter.tags, [Tag::new("tier", "cold")]);
+    assert_eq!(logs.expiration, Some(Expiration::Days(30)));
+    assert_eq!(logs.newer_noncurrent_versions, Some(2));
+    assert!(!tmp.enabled && tmp.expired_object_delete_marker);
+    assert_eq!(tmp.abort_incomplete_upload_days, Some(3));
+    assert_eq!(old.filter.prefix, "old/");
+    assert_eq!(old.expiration, Some(Expiration::Date(1893456000)));
+
+    let result = LifecycleConfigurationResult {
+      xmlns: S3_XMLNS,
+      rules: config.rules.into_iter().map(rule_entry).collect(),
+    };
+    let xml = quick_xml::se::to_string_with_root("LifecycleConfiguration", &result).unwrap();
+    assert!(
+      xml.contains("<Filter><And><Prefix>logs/</Prefix><Tag>"),
+      "{xml}"
+    );
+    assert!(
+      xml.contains("<Date>2030-01-01T00:00:00.000Z</Date>"),
+      "{xml}"
+    );
+    let reparsed = parse_lifecycle(xml.as_bytes()).unwrap();
+    assert_eq!(reparsed.rules.len(), 3);
+
+    let invalid = "<LifecycleConfiguration><Rule><Status>Enabled</Status><Expiration><Date>2030-01-01T12:00:00Z</Date></Expiration></Rule></LifecycleConfiguration>";
+    assert!(parse_lifecycle(invalid.as_bytes()).is_err());
+  }
+}
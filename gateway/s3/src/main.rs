@@ -1,16 +1,14 @@
-use crate::server::S3Server;
+use s3::config::load_config;
+use s3::multipart_handler::spawn_multipart_cleanup;
+use s3::server::S3Server;
+use s3::state::AppState;
+use server::object::dedup::spawn_chunk_sweeper;
+use server::object::lifecycle::spawn_lifecycle_worker;
+use std::time::Duration;
 use tracing_subscriber::EnvFilter;
 
-mod auth;
-mod bucket_handler;
-mod config;
-mod object_handler;
-mod openapi;
-pub mod server;
-mod state;
-
 #[tokio::main]
-async fn main() {
+async fn main() -> anyhow::Result<()> {
   tracing_subscriber::fmt()
     .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
     .with_env_filter(EnvFilter::from_default_env().add_directive("debug".parse().unwrap()))
@@ -21,5 +19,26 @@ async fn main() {
     .with_thread_names(true)
     .with_filter_reloading()
     .init();
-  S3Server::new("0.0.0.0:3000".to_string()).start().await
+  let config = load_config()?;
+  let state = AppState::new(config.open_storage()?, config.authenticator())
+    .with_trusted_proxy(config.trusted_proxy);
+  spawn_multipart_cleanup(
+    state.storage.clone(),
+    Duration::from_secs(config.multipart_cleanup_interval_secs),
+    Duration::from_secs(config.multipart_expiry_secs),
+  );
+  spawn_lifecycle_worker(
+    state.storage.clone(),
+    Duration::from_secs(config.lifecycle_interval_secs),
+    config.lifecycle_batch_size,
+  );
+  spawn_chunk_sweeper(
+    state.storage.clone(),
+    Duration::from_secs(config.chunk_sweep_interval_secs),
+  );
+  S3Server::new(config.listen_address().to_string(), state)
+    .with_virtual_hosts(config.virtual_hosts())
+    .start()
+    .await;
+  Ok(())
 }
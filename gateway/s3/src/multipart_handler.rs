@@ -0,0 +1,552 @@
+use crate::acl_handler::new_object_acl;
+use crate::auth::Principal;
+use crate::bucket_handler::Owner;
+use crate::checksum::{
+  ChecksumElements, checksum_headers, request_integrity, upload_checksum, upload_checksum_headers,
+  value_headers,
+};
+use crate::chunked::S3Body;
+use crate::copy_source::{MAX_COPY_SIZE, X_AMZ_COPY_SOURCE_RANGE, parse_copy_source_range};
+use crate::encryption_handler::{
+  copy_source_customer_key, customer_key, encryption_headers, kind_headers, request_encryption,
+};
+use crate::error::{S3Error, S3ErrorCode};
+use crate::object_handler::{
+  body_reader, copy_source_meta, copy_source_version_headers, request_object_headers,
+  version_headers,
+};
+use crate::object_lock_handler::request_lock;
+use crate::response::{S3_XMLNS, format_timestamp, xml_response};
+use crate::state::AppState;
+use crate::tagging_handler::header_tags;
+use axum::body::Body;
+use axum::extract::{Extension, Path, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use server::encryption::WrappingKey;
+use server::metadata::encryption::EncryptionKind;
+use server::object::multipart::{
+  CompletedPart, ListUploadsOptions, MAX_PART_NUMBER, PartOptions, UploadOptions,
+};
+use server::storage::Storage;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+const MAX_PARTS: usize = 1000;
+const MAX_UPLOADS: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct UploadQuery {
+  #[serde(rename = "uploadId")]
+  pub upload_id: String,
+  #[serde(rename = "partNumber")]
+  pub part_number: Option<u32>,
+  #[serde(rename = "max-parts")]
+  pub max_parts: Option<usize>,
+  #[serde(rename = "part-number-marker")]
+  pub part_number_marker: Option<u32>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ListUploadsQuery {
+  pub prefix: Option<String>,
+  pub delimiter: Option<String>,
+  #[serde(rename = "key-marker")]
+  pub key_marker: Option<String>,
+  #[serde(rename = "upload-id-marker")]
+  pub upload_id_marker: Option<String>,
+  #[serde(rename = "max-uploads")]
+  pub max_uploads: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct InitiateMultipartUploadResult {
+  #[serde(rename = "@xmlns")]
+  xmlns: &'static str,
+  #[serde(rename = "Bucket")]
+  bucket: String,
+  #[serde(rename = "Key")]
+  key: String,
+  #[serde(rename = "UploadId")]
+  upload_id: String,
+}
+
+#[derive(Deserialize)]
+struct CompleteMultipartUpload {
+  #[serde(rename = "Part", default)]
+  parts: Vec<CompletedPartEntry>,
+}
+
+#[derive(Deserialize)]
+struct CompletedPartEntry {
+  #[serde(rename = "PartNumber")]
+  part_number: u32,
+  #[serde(rename = "ETag")]
+  etag: String,
+  #[serde(rename = "ChecksumCRC32")]
+  checksum_crc32: Option<String>,
+  #[serde(rename = "ChecksumCRC32C")]
+  checksum_crc32c: Option<String>,
+  #[serde(rename = "ChecksumSHA1")]
+  checksum_sha1: Option<String>,
+  #[serde(rename = "ChecksumSHA256")]
+  checksum_sha256: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CompleteMultipartUploadResult {
+  #[serde(rename = "@xmlns")]
+  xmlns: &'static str,
+  #[serde(rename = "Location")]
+  location: String,
+  #[serde(rename = "Bucket")]
+  bucket: String,
+  #[serde(rename = "Key")]
+  key: String,
+  #[serde(rename = "ETag")]
+  etag: String,
+  #[serde(flatten)]
+  checksum: ChecksumElements,
+  #[serde(rename = "ChecksumType", skip_serializing_if = "Option::is_none")]
+  checksum_type: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct PartEntry {
+  #[serde(rename = "PartNumber")]
+  part_number: u32,
+  #[serde(rename = "LastModified")]
+  last_modified: String,
+  #[serde(rename = "ETag")]
+  etag: String,
+  #[serde(rename = "Size")]
+  size: u64,
+  #[serde(flatten)]
+  checksum: ChecksumElements,
+}
+
+#[derive(Serialize)]
+struct ListPartsResult {
+  #[serde(rename = "@xmlns")]
+  xmlns: &'static str,
+  #[serde(rename = "Bucket")]
+  bucket: String,
+  #[serde(rename = "Key")]
+  key: String,
+  #[serde(rename = "UploadId")]
+  upload_id: String,
+  #[serde(rename = "Initiator")]
+  initiator: Owner,
+  #[serde(rename = "Owner")]
+  owner: Owner,
+  #[serde(rename = "StorageClass")]
+  storage_class: &'static str,
+  #[serde(rename = "PartNumberMarker")]
+  part_number_marker: u32,
+  #[serde(rename = "NextPartNumberMarker")]
+  next_part_number_marker: u32,
+  #[serde(rename = "MaxParts")]
+  max_parts: usize,
+  #[serde(rename = "IsTruncated")]
+  is_truncated: bool,
+  #[serde(rename = "Part")]
+  parts: Vec<PartEntry>,
+}
+
+#[derive(Serialize)]
+struct UploadEntry {
+  #[serde(rename = "Key")]
+  key: String,
+  #[serde(rename = "UploadId")]
+  upload_id: String,
+  #[serde(rename = "Initiator")]
+  initiator: Owner,
+  #[serde(rename = "Owner")]
+  owner: Owner,
+  #[serde(rename = "StorageClass")]
+  storage_class: &'static str,
+  #[serde(rename = "Initiated")]
+  initiated: String,
+}
+
+#[derive(Serialize)]
+struct CommonPrefix {
+  #[serde(rename = "Prefix")]
+  prefix: String,
+}
+
+#[derive(Serialize)]
+struct ListMultipartUploadsResult {
+  #[serde(rename = "@xmlns")]
+  xmlns: &'static str,
+  #[serde(rename = "Bucket")]
+  bucket: String,
+  #[serde(rename = "KeyMarker")]
+  key_marker: String,
+  #[serde(rename = "UploadIdMarker")]
+  upload_id_marker: String,
+  #[serde(rename = "NextKeyMarker")]
+  next_key_marker: String,
+  #[serde(rename = "NextUploadIdMarker")]
+  next_upload_id_marker: String,
+  #[serde(rename = "Prefix")]
+  prefix: String,
+  #[serde(rename = "Delimiter", skip_serializing_if = "Option::is_none")]
+  delimiter: Option<String>,
+  #[serde(rename = "MaxUploads")]
+  max_uploads: usize,
+  #[serde(rename = "IsTruncated")]
+  is_truncated: bool,
+  #[serde(rename = "Upload")]
+  uploads: Vec<UploadEntry>,
+  #[serde(rename = "CommonPrefixes")]
+  common_prefixes: Vec<CommonPrefix>,
+}
+
+// POST /{bucket}/{key}?uploads
+pub async fn create_multipart_upload(
+  State(state): State<AppState>,
+  Extension(principal): Extension<Principal>,
+  Path((bucket, key)): Path<(String, String)>,
+  headers: HeaderMap,
+) -> Result<Response, S3Error> {
+  let options = UploadOptions {
+    headers: request_object_headers(&headers),
+    tags: header_tags(&headers)?,
+    acl: new_object_acl(&state, &bucket, &principal, &headers)?,
+    encryption: request_encryption(&headers)?,
+    checksum: upload_checksum(&headers)?,
+    lock: request_lock(&headers)?,
+  };
+  let upload = state
+    .storage
+    .objects
+    .create_multipart_upload(&bucket, &key, options)?;
+  debug!(
+    "create multipart upload {} for {}/{}",
+    upload.upload_id, bucket, key
+  );
+  let mut response = xml_response(
+    "InitiateMultipartUploadResult",
+    &InitiateMultipartUploadResult {
+      xmlns: S3_XMLNS,
+      bucket,
+      key,
+      upload_id: upload.upload_id,
+    },
+  );
+  response
+    .headers_mut()
+    .extend(kind_headers(upload.encryption.as_ref()));
+  response
+    .headers_mut()
+    .extend(upload_checksum_headers(upload.checksum));
+  Ok(response)
+}
+
+fn part_number(query: &UploadQuery) -> Result<u32, S3Error> {
+  query
+    .part_number
+    .filter(|n| (1..=MAX_PART_NUMBER).contains(n))
+    .ok_or_else(|| {
+      S3Error::new(S3ErrorCode::InvalidArgument)
+        .with_message("Part number must be an integer between 1 and 10000, inclusive")
+    })
+}
+
+/// SSE-C 分片的响应头：回显请求中密钥的 MD5
+fn customer_key_headers(customer_key: Option<&WrappingKey>) -> HeaderMap {
+  let kind = customer_key.map(|key| EncryptionKind::Customer {
+    key_md5: key.key_md5(),
+  });
+  kind_headers(kind.as_ref())
+}
+
+// PUT /{bucket}/{key}?partNumber=N&uploadId=X
+pub async fn upload_part(
+  State(state): State<AppState>,
+  Path((bucket, key)): Path<(String, String)>,
+  Query(query): Query<UploadQuery>,
+  headers: HeaderMap,
+  body: Body,
+) -> Result<Response, S3Error> {
+  let part_number = part_number(&query)?;
+  let options = PartOptions {
+    customer_key: customer_key(&headers)?,
+    integrity: request_integrity(&headers)?,
+  };
+  let mut response_headers = customer_key_headers(options.customer_key.as_ref());
+  let algorithm = options.integrity.algorithm;
+  let part = state
+    .storage
+    .objects
+    .upload_part(
+      &bucket,
+      &key,
+      &query.upload_id,
+      part_number,
+      options,
+      body_reader(body),
+    )
+    .await?;
+  if let (Some(algorithm), Some(checksum)) = (algorithm, &part.checksum) {
+    response_headers.extend(value_headers(algorithm, checksum));
+  }
+  Ok(
+    (
+      response_headers,
+      [(header::ETAG, format!("\"{}\"", part.etag))],
+    )
+      .into_response(),
+  )
+}
+
+#[derive(Serialize)]
+struct CopyPartResult {
+  #[serde(rename = "@xmlns")]
+  xmlns: &'static str,
+  #[serde(rename = "ETag")]
+  etag: String,
+  #[serde(rename = "LastModified")]
+  last_modified: String,
+}
+
+// PUT /{bucket}/{key}?partNumber=N&uploadId=X + x-amz-copy-source
+pub async fn upload_part_copy(
+  State(state): State<AppState>,
+  Path((bucket, key)): Path<(String, String)>,
+  Query(query): Query<UploadQuery>,
+  headers: HeaderMap,
+) -> Result<Response, S3Error> {
+  let part_number = part_number(&query)?;
+  let source = copy_source_meta(&state, &headers)?;
+  let range = match headers
+    .get(X_AMZ_COPY_SOURCE_RANGE)
+    .and_then(|v| v.to_str().ok())
+  {
+    Some(value) => parse_copy_source_range(value, source.size)?,
+    None => 0..source.size,
+  };
+  if range.end - range.start > MAX_COPY_SIZE {
+    return Err(S3Error::new(S3ErrorCode::InvalidRequest).with_message(format!(
+      "The specified copy range is larger than the maximum allowable size for a part: {MAX_COPY_SIZE}"
+    )));
+  }
+  let options = PartOptions {
+    customer_key: customer_key(&headers)?,
+    ..Default::default()
+  };
+  let response_headers = customer_key_headers(options.customer_key.as_ref());
+  let objects = &state.storage.objects;
+  let reader = objects
+    .open_copy_source(&source, range, copy_source_customer_key(&headers)?.as_ref())
+    .await?;
+  let part = objects
+    .upload_part(
+      &bucket,
+      &key,
+      &query.upload_id,
+      part_number,
+      options,
+      reader,
+    )
+    .await?;
+  let mut response = xml_response(
+    "CopyPartResult",
+    &CopyPartResult {
+      xmlns: S3_XMLNS,
+      etag: format!("\"{}\"", part.etag),
+      last_modified: format_timestamp(part.last_modified),
+    },
+  );
+  response.headers_mut().extend(response_headers);
+  response
+    .headers_mut()
+    .extend(copy_source_version_headers(&source));
+  Ok(response)
+}
+
+// POST /{bucket}/{key}?uploadId=X
+pub async fn complete_multipart_upload(
+  State(state): State<AppState>,
+  Path((bucket, key)): Path<(String, String)>,
+  Query(query): Query<UploadQuery>,
+  S3Body(body): S3Body,
+) -> Result<Response, S3Error> {
+  let request: CompleteMultipartUpload = std::str::from_utf8(&body)
+    .ok()
+    .and_then(|xml| quick_xml::de::from_str(xml).ok())
+    .ok_or(S3ErrorCode::MalformedXML)?;
+  let parts: Vec<CompletedPart> = request
+    .parts
+    .into_iter()
+    .map(|part| CompletedPart {
+      part_number: part.part_number,
+      etag: part.etag,
+      checksum: part
+        .checksum_crc32
+        .or(part.checksum_crc32c)
+        .or(part.checksum_sha1)
+        .or(part.checksum_sha256),
+    })
+    .collect();
+  let meta = state
+    .storage
+    .objects
+    .complete_multipart_upload(&bucket, &key, &query.upload_id, &parts)
+    .await?;
+  let mut response = xml_response(
+    "CompleteMultipartUploadResult",
+    &CompleteMultipartUploadResult {
+      xmlns: S3_XMLNS,
+      location: format!("/{}/{}", bucket, key),
+      bucket,
+      key,
+      etag: format!("\"{}\"", meta.etag),
+      checksum: ChecksumElements::new(
+        meta.checksum.as_ref().map(|checksum| checksum.algorithm),
+        meta
+          .checksum
+          .as_ref()
+          .map(|checksum| checksum.value.clone()),
+      ),
+      checksum_type: meta
+        .checksum
+        .as_ref()
+        .map(|checksum| checksum.checksum_type.name()),
+    },
+  );
+  response.headers_mut().extend(version_headers(&meta));
+  response
+    .headers_mut()
+    .extend(encryption_headers(meta.encryption.as_ref()));
+  response
+    .headers_mut()
+    .extend(checksum_headers(meta.checksum.as_ref()));
+  Ok(response)
+}
+
+// DELETE /{bucket}/{key}?uploadId=X
+pub async fn abort_multipart_upload(
+  State(state): State<AppState>,
+  Path((bucket, key)): Path<(String, String)>,
+  Query(query): Query<UploadQuery>,
+) -> Result<Response, S3Error> {
+  state
+    .storage
+    .objects
+    .abort_multipart_upload(&bucket, &key, &query.upload_id)
+    .await?;
+  Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// GET /{bucket}/{key}?uploadId=X
+pub async fn list_parts(
+  State(state): State<AppState>,
+  Path((bucket, key)): Path<(String, String)>,
+  Query(query): Query<UploadQuery>,
+) -> Result<Response, S3Error> {
+  let max_parts = query.max_parts.unwrap_or(MAX_PARTS).min(MAX_PARTS);
+  let part_number_marker = query.part_number_marker.unwrap_or(0);
+  let page = state.storage.objects.list_parts(
+    &bucket,
+    &key,
+    &query.upload_id,
+    part_number_marker,
+    max_parts,
+  )?;
+  // 分片上传没有单独记录发起者，使用 bucket owner
+  let owner = state.storage.buckets.get_bucket(&bucket)?.owner;
+  let algorithm = page.upload.checksum.map(|(algorithm, _)| algorithm);
+  let result = ListPartsResult {
+    xmlns: S3_XMLNS,
+    bucket,
+    key,
+    upload_id: page.upload.upload_id,
+    initiator: Owner::new(&owner),
+    owner: Owner::new(&owner),
+    storage_class: "STANDARD",
+    part_number_marker,
+    next_part_number_marker: page.parts.last().map_or(0, |part| part.part_number),
+    max_parts,
+    is_truncated: page.is_truncated,
+    parts: page
+      .parts
+      .into_iter()
+      .map(|part| PartEntry {
+        part_number: part.part_number,
+        last_modified: format_timestamp(part.last_modified),
+        etag: format!("\"{}\"", part.etag),
+        size: part.size,
+        checksum: ChecksumElements::new(algorithm, part.checksum),
+      })
+      .collect(),
+  };
+  Ok(xml_response("ListPartsResult", &result))
+}
+
+// GET /{bucket}?uploads
+pub async fn list_multipart_uploads(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+  Query(query): Query<ListUploadsQuery>,
+) -> Result<Response, S3Error> {
+  let options = ListUploadsOptions {
+    prefix: query.prefix.unwrap_or_default(),
+    delimiter: query.delimiter,
+    key_marker: query.key_marker,
+    upload_id_marker: query.upload_id_marker,
+    max_uploads: query.max_uploads.unwrap_or(MAX_UPLOADS).min(MAX_UPLOADS),
+  };
+  let page = state
+    .storage
+    .objects
+    .list_multipart_uploads(&bucket, &options)?;
+  let owner = state.storage.buckets.get_bucket(&bucket)?.owner;
+  let last = page.uploads.last();
+  let result = ListMultipartUploadsResult {
+    xmlns: S3_XMLNS,
+    bucket,
+    key_marker: options.key_marker.clone().unwrap_or_default(),
+    upload_id_marker: options.upload_id_marker.clone().unwrap_or_default(),
+    next_key_marker: last.map(|u| u.key.clone()).unwrap_or_default(),
+    next_upload_id_marker: last.map(|u| u.upload_id.clone()).unwrap_or_default(),
+    prefix: options.prefix.clone(),
+    delimiter: options.delimiter.clone(),
+    max_uploads: options.max_uploads,
+    is_truncated: page.is_truncated,
+    uploads: page
+      .uploads
+      .iter()
+      .map(|upload| UploadEntry {
+        key: upload.key.clone(),
+        upload_id: upload.upload_id.clone(),
+        initiator: Owner::new(&owner),
+        owner: Owner::new(&owner),
+        storage_class: "STANDARD",
+        initiated: format_timestamp(upload.initiated),
+      })
+      .collect(),
+    common_prefixes: page
+      .common_prefixes
+      .into_iter()
+      .map(|prefix| CommonPrefix { prefix })
+      .collect(),
+  };
+  Ok(xml_response("ListMultipartUploadsResult", &result))
+}
+
+/// 周期性中止超过 max_age 仍未完成的分片上传
+pub fn spawn_multipart_cleanup(storage: Arc<Storage>, interval: Duration, max_age: Duration) {
+  tokio::spawn(async move {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+      ticker.tick().await;
+      if let Err(err) = storage.objects.abort_expired_uploads(max_age).await {
+        warn!("multipart cleanup failed: {:?}", err);
+      }
+    }
+  });
+}
@@ -1,13 +1,248 @@
-use axum::extract::{Path, State};
-use axum::http::{HeaderMap, StatusCode};
-use axum::Json;
+use crate::acl_handler::new_object_acl;
+use crate::auth::Principal;
+use crate::authz::{AccessContext, authorize};
+use crate::bucket_handler::Owner;
+use crate::checksum::{checksum_headers, checksum_mode_enabled, request_integrity};
+use crate::chunked::S3Body;
+use crate::conditional::{check_copy_source_preconditions, check_preconditions, put_condition};
+use crate::copy_source::{
+  MAX_COPY_SIZE, X_AMZ_COPY_SOURCE, X_AMZ_METADATA_DIRECTIVE, parse_copy_source,
+};
+use crate::encryption_handler::{
+  copy_source_customer_key, customer_key, encryption_headers, request_encryption,
+};
+use crate::error::{S3Error, S3ErrorCode};
+use crate::object_lock_handler::{bypass_governance, lock_headers, request_lock};
+use crate::range::parse_range;
+use crate::response::{S3_XMLNS, format_http_date, format_timestamp, xml_response};
+use crate::state::AppState;
+use crate::tagging_handler::{X_AMZ_TAGGING_COUNT, X_AMZ_TAGGING_DIRECTIVE, header_tags};
+use axum::body::Body;
+use axum::extract::{Extension, Path, Query, State};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header};
 use axum::response::{IntoResponse, Response};
-use bytes::Bytes;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use futures_util::TryStreamExt;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::{Deserialize, Serialize};
+use server::metadata::constant::Action;
+use server::metadata::object_headers::ObjectHeaders;
+use server::metadata::object_meta::ObjectMeta;
+use server::object::list::ListOptions;
+use server::object::sse::check_customer_key;
+use server::object::{DeleteTarget, PutOptions};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use tokio::io::AsyncRead;
+use tokio_util::io::{ReaderStream, StreamReader};
 use tracing::debug;
-use utoipa::ToSchema;
-use crate::state::AppState;
+use utoipa::IntoParams;
 
 pub const OBJECT_TAG: &str = "object";
+
+/// 用户自定义元数据的请求头前缀
+const USER_METADATA_PREFIX: &str = "x-amz-meta-";
+pub const X_AMZ_VERSION_ID: &str = "x-amz-version-id";
+pub const X_AMZ_COPY_SOURCE_VERSION_ID: &str = "x-amz-copy-source-version-id";
+const X_AMZ_DELETE_MARKER: &str = "x-amz-delete-marker";
+
+/// `x-amz-version-id` 响应头，null 版本不返回
+pub(crate) fn version_headers(meta: &ObjectMeta) -> HeaderMap {
+  let mut headers = HeaderMap::new();
+  if !meta.null_version
+    && let Ok(value) = meta.version_id.parse()
+  {
+    headers.insert(X_AMZ_VERSION_ID, value);
+  }
+  headers
+}
+
+fn object_headers(meta: &ObjectMeta) -> HeaderMap {
+  let mut headers = HeaderMap::new();
+  headers.insert(header::CONTENT_LENGTH, meta.size.into());
+  let stored = &meta.headers;
+  let content_type = stored
+    .content_type
+    .as_deref()
+    .unwrap_or("application/octet-stream");
+  for (name, value) in [
+    (header::CONTENT_TYPE, Some(content_type)),
+    (
+      header::CONTENT_DISPOSITION,
+      stored.content_disposition.as_deref(),
+    ),
+    (header::CONTENT_ENCODING, stored.content_encoding.as_deref()),
+    (header::CONTENT_LANGUAGE, stored.content_language.as_deref()),
+    (header::CACHE_CONTROL, stored.cache_control.as_deref()),
+    (header::EXPIRES, stored.expires.as_deref()),
+  ] {
+    if let Some(Ok(value)) = value.map(HeaderValue::from_str) {
+      headers.insert(name, value);
+    }
+  }
+  for (key, value) in &stored.user {
+    if let (Ok(name), Ok(value)) = (
+      HeaderName::from_bytes(format!("{USER_METADATA_PREFIX}{key}").as_bytes()),
+      HeaderValue::from_bytes(value.as_bytes()),
+    ) {
+      headers.insert(name, value);
+    }
+  }
+  if !meta.tags.is_empty() {
+    headers.insert(X_AMZ_TAGGING_COUNT, meta.tags.len().into());
+  }
+  if let Ok(value) = format!("\"{}\"", meta.etag).parse() {
+    headers.insert(header::ETAG, value);
+  }
+  if let Ok(value) = format_http_date(meta.last_modified).parse() {
+    headers.insert(header::LAST_MODIFIED, value);
+  }
+  headers.extend(version_headers(meta));
+  headers.extend(encryption_headers(meta.encryption.as_ref()));
+  headers.extend(lock_headers(&meta.lock));
+  headers
+}
+
+#[derive(Deserialize, IntoParams, Default)]
+#[into_params(parameter_in = Query)]
+pub struct GetObjectQuery {
+  /// 按分片读取对象，从 1 开始；非分片上传的对象只有分片 1
+  #[serde(rename = "partNumber")]
+  pub part_number: Option<u32>,
+  /// 读取指定版本，缺省为当前版本
+  #[serde(rename = "versionId")]
+  pub version_id: Option<String>,
+}
+
+/// 读取当前版本或 `versionId` 指定的版本；指定的版本是删除标记时返回 405
+pub(crate) fn requested_meta(
+  state: &AppState,
+  bucket: &str,
+  key: &str,
+  version_id: Option<&str>,
+) -> Result<ObjectMeta, S3Error> {
+  let objects = &state.storage.objects;
+  let Some(version_id) = version_id else {
+    return Ok(objects.head_object(bucket, key)?);
+  };
+  let meta = objects.get_object_version(bucket, key, version_id)?;
+  if meta.is_delete_marker() {
+    let mut err = S3Error::new(S3ErrorCode::MethodNotAllowed).with_header(
+      HeaderName::from_static(X_AMZ_DELETE_MARKER),
+      HeaderValue::from_static("true"),
+    );
+    if let Ok(value) = meta.s3_version_id().parse() {
+      err = err.with_header(HeaderName::from_static(X_AMZ_VERSION_ID), value);
+    }
+    return Err(err);
+  }
+  Ok(meta)
+}
+
+/// 根据 Range 头或 partNumber 计算要读取的区间，并生成对应的状态码和响应头
+fn read_range(
+  meta: &ObjectMeta,
+  headers: &HeaderMap,
+  part_number: Option<u32>,
+) -> Result<(StatusCode, Range<u64>, HeaderMap), S3Error> {
+  let mut response_headers = object_headers(meta);
+  response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+  let range_header = headers.get(header::RANGE).and_then(|v| v.to_str().ok());
+
+  let range = match (range_header, part_number) {
+    (Some(_), Some(_)) => {
+      return Err(
+        S3Error::new(S3ErrorCode::InvalidRequest)
+          .with_message("Cannot specify both Range header and partNumber query parameter"),
+      );
+    }
+    (None, Some(part_number)) => {
+      let parts: &[u64] = if meta.parts.is_empty() {
+        &[meta.size]
+      } else {
+        &meta.parts
+      };
+      let index = (part_number as usize).wrapping_sub(1);
+      if index >= parts.len() {
+        return Err(S3Error::new(S3ErrorCode::InvalidPartNumber).with_header(
+          HeaderName::from_static("x-amz-mp-parts-count"),
+          parts.len().into(),
+        ));
+      }
+      if !meta.parts.is_empty() {
+        response_headers.insert("x-amz-mp-parts-count", parts.len().into());
+      }
+      let start: u64 = parts[..index].iter().sum();
+      start..start + parts[index]
+    }
+    (Some(value), None) => match parse_range(value) {
+      // 无法解析的 Range 头按规范忽略
+      None => 0..meta.size,
+      Some(range) => match range.resolve(meta.size) {
+        Some(range) => range,
+        None => {
+          let mut err = S3Error::new(S3ErrorCode::InvalidRange);
+          if let Ok(value) = format!("bytes */{}", meta.size).parse() {
+            err = err.with_header(header::CONTENT_RANGE, value);
+          }
+          return Err(err);
+        }
+      },
+    },
+    (None, None) => return Ok((StatusCode::OK, 0..meta.size, response_headers)),
+  };
+
+  // 读取整个非分片对象时仍然返回 200
+  if range == (0..meta.size) && meta.parts.is_empty() && range_header.is_none() {
+    return Ok((StatusCode::OK, range, response_headers));
+  }
+  response_headers.insert(header::CONTENT_LENGTH, (range.end - range.start).into());
+  if !range.is_empty()
+    && let Ok(value) = format!("bytes {}-{}/{}", range.start, range.end - 1, meta.size).parse()
+  {
+    response_headers.insert(header::CONTENT_RANGE, value);
+  }
+  Ok((StatusCode::PARTIAL_CONTENT, range, response_headers))
+}
+
+/// 以流的方式读取请求体，避免把整个对象缓存在内存中
+pub fn body_reader(body: Body) -> impl AsyncRead + Unpin {
+  StreamReader::new(body.into_data_stream().map_err(std::io::Error::other))
+}
+
+/// 从请求头中提取随对象保存的元数据；同名的 x-amz-meta-* 头按逗号合并
+pub(crate) fn request_object_headers(headers: &HeaderMap) -> ObjectHeaders {
+  let get = |name: header::HeaderName| {
+    headers
+      .get(name)
+      .and_then(|v| v.to_str().ok())
+      .map(str::to_string)
+  };
+  let mut user = BTreeMap::<String, String>::new();
+  for (name, value) in headers {
+    if let Some(key) = name.as_str().strip_prefix(USER_METADATA_PREFIX) {
+      let value = String::from_utf8_lossy(value.as_bytes());
+      user
+        .entry(key.to_string())
+        .and_modify(|existing| {
+          existing.push(',');
+          existing.push_str(&value);
+        })
+        .or_insert_with(|| value.into_owned());
+    }
+  }
+  ObjectHeaders {
+    content_type: get(header::CONTENT_TYPE),
+    content_disposition: get(header::CONTENT_DISPOSITION),
+    content_encoding: get(header::CONTENT_ENCODING),
+    content_language: get(header::CONTENT_LANGUAGE),
+    cache_control: get(header::CACHE_CONTROL),
+    expires: get(header::EXPIRES),
+    user,
+  }
+}
+
 // PUT /{bucket}/{key} 上传对象
 #[utoipa::path(
     put,
@@ -20,41 +255,213 @@ pub const OBJECT_TAG: &str = "object";
     ),
     responses(
         (status = 200, description = "Object uploaded successfully"),
+        (status = 412, description = "If-Match or If-None-Match precondition failed"),
         (status = 500, description = "Internal server error")
     )
 )]
 pub async fn put_object(
-    State(_state): State<AppState>,
-    Path((_bucket, _key)): Path<(String, String)>,
-    _body: Bytes,
-) -> impl IntoResponse {
-    StatusCode::OK
+  State(state): State<AppState>,
+  Extension(principal): Extension<Principal>,
+  Path((bucket, key)): Path<(String, String)>,
+  headers: HeaderMap,
+  body: Body,
+) -> Result<impl IntoResponse, S3Error> {
+  let options = PutOptions {
+    headers: request_object_headers(&headers),
+    tags: header_tags(&headers)?,
+    acl: new_object_acl(&state, &bucket, &principal, &headers)?,
+    condition: put_condition(&headers)?,
+    encryption: request_encryption(&headers)?,
+    integrity: request_integrity(&headers)?,
+    lock: request_lock(&headers)?,
+  };
+  let meta = state
+    .storage
+    .objects
+    .put_object(&bucket, &key, options, body_reader(body))
+    .await?;
+  debug!("put_object {}/{} ({} bytes)", bucket, key, meta.size);
+  Ok((
+    version_headers(&meta),
+    encryption_headers(meta.encryption.as_ref()),
+    checksum_headers(meta.checksum.as_ref()),
+    [(header::ETAG, format!("\"{}\"", meta.etag))],
+  ))
+}
+
+#[derive(Serialize)]
+struct CopyObjectResult {
+  #[serde(rename = "@xmlns")]
+  xmlns: &'static str,
+  #[serde(rename = "ETag")]
+  etag: String,
+  #[serde(rename = "LastModified")]
+  last_modified: String,
+}
+
+/// 读取复制源的元数据并检查 `x-amz-copy-source-if-*`
+pub(crate) fn copy_source_meta(
+  state: &AppState,
+  headers: &HeaderMap,
+) -> Result<ObjectMeta, S3Error> {
+  let value = headers
+    .get(X_AMZ_COPY_SOURCE)
+    .and_then(|v| v.to_str().ok())
+    .ok_or(S3ErrorCode::InvalidArgument)?;
+  let source = parse_copy_source(value)?;
+  let objects = &state.storage.objects;
+  let meta = match &source.version_id {
+    Some(version_id) => objects.get_object_version(&source.bucket, &source.key, version_id)?,
+    None => objects.head_object(&source.bucket, &source.key)?,
+  };
+  // 复制源不能是删除标记
+  if meta.is_delete_marker() {
+    return Err(S3Error::new(S3ErrorCode::InvalidRequest).with_message(
+      "The source of a copy request may not specifically refer to a delete marker by version id.",
+    ));
+  }
+  check_copy_source_preconditions(headers, &meta)?;
+  Ok(meta)
+}
+
+/// `x-amz-copy-source-version-id` 响应头，null 版本不返回
+pub(crate) fn copy_source_version_headers(source: &ObjectMeta) -> HeaderMap {
+  let mut headers = HeaderMap::new();
+  if let Some(value) = version_headers(source).remove(X_AMZ_VERSION_ID) {
+    headers.insert(X_AMZ_COPY_SOURCE_VERSION_ID, value);
+  }
+  headers
+}
+
+// PUT /{bucket}/{key} + x-amz-copy-source 服务端复制对象
+pub async fn copy_object(
+  State(state): State<AppState>,
+  Extension(principal): Extension<Principal>,
+  Path((bucket, key)): Path<(String, String)>,
+  headers: HeaderMap,
+) -> Result<Response, S3Error> {
+  let source = copy_source_meta(&state, &headers)?;
+  if source.size > MAX_COPY_SIZE {
+    return Err(S3Error::new(S3ErrorCode::InvalidRequest).with_message(format!(
+      "The specified copy source is larger than the maximum allowable size for a copy source: {MAX_COPY_SIZE}"
+    )));
+  }
+  let replace = match headers
+    .get(X_AMZ_METADATA_DIRECTIVE)
+    .and_then(|v| v.to_str().ok())
+  {
+    None | Some("COPY") => false,
+    Some("REPLACE") => true,
+    Some(_) => {
+      return Err(
+        S3Error::new(S3ErrorCode::InvalidArgument).with_message("Unknown metadata directive."),
+      );
+    }
+  };
+  let encryption = request_encryption(&headers)?;
+  if !replace && encryption.is_none() && source.bucket == bucket && source.key == key {
+    return Err(S3Error::new(S3ErrorCode::InvalidRequest).with_message(
+      "This copy request is illegal because it is trying to copy an object to itself without changing the object's metadata, storage class, website redirect location or encryption attributes.",
+    ));
+  }
+  let tags = match headers
+    .get(X_AMZ_TAGGING_DIRECTIVE)
+    .and_then(|v| v.to_str().ok())
+  {
+    None | Some("COPY") => source.tags.clone(),
+    Some("REPLACE") => header_tags(&headers)?,
+    Some(_) => {
+      return Err(
+        S3Error::new(S3ErrorCode::InvalidArgument).with_message("Unknown tagging directive."),
+      );
+    }
+  };
+  let options = PutOptions {
+    headers: if replace {
+      request_object_headers(&headers)
+    } else {
+      source.headers.clone()
+    },
+    tags,
+    // ACL 不随复制继承
+    acl: new_object_acl(&state, &bucket, &principal, &headers)?,
+    condition: put_condition(&headers)?,
+    encryption,
+    integrity: Default::default(),
+    // 保留设置不随复制继承
+    lock: request_lock(&headers)?,
+  };
+  let source_key = copy_source_customer_key(&headers)?;
+  let meta = state
+    .storage
+    .objects
+    .copy_object(&source, source_key.as_ref(), &bucket, &key, options)
+    .await?;
+  debug!(
+    "copy_object {}/{} -> {}/{}",
+    source.bucket, source.key, bucket, key
+  );
+  let mut response = xml_response(
+    "CopyObjectResult",
+    &CopyObjectResult {
+      xmlns: S3_XMLNS,
+      etag: format!("\"{}\"", meta.etag),
+      last_modified: format_timestamp(meta.last_modified),
+    },
+  );
+  response.headers_mut().extend(version_headers(&meta));
+  response
+    .headers_mut()
+    .extend(encryption_headers(meta.encryption.as_ref()));
+  response
+    .headers_mut()
+    .extend(copy_source_version_headers(&source));
+  Ok(response)
 }
 
 // GET /{bucket}/{key} 下载对象
 #[utoipa::path(
     get,
     path = "/{bucket}/{key}",
-tag = OBJECT_TAG,
+    tag = OBJECT_TAG,
     params(
         ("bucket" = String, Path, description = "Bucket name"),
-        ("key" = String, Path, description = "Object key")
+        ("key" = String, Path, description = "Object key"),
+        ("Range" = Option<String>, Header, description = "Single byte range, e.g. bytes=0-99"),
+        GetObjectQuery
     ),
     responses(
         (status = 200, description = "Object retrieved successfully", content_type = "application/octet-stream"),
-        (status = 404, description = "Object not found")
+        (status = 206, description = "Partial content", content_type = "application/octet-stream"),
+        (status = 304, description = "Not modified"),
+        (status = 404, description = "Object not found"),
+        (status = 412, description = "Precondition failed"),
+        (status = 416, description = "Requested range not satisfiable")
     )
 )]
 pub async fn get_object(
-    State(_state): State<AppState>,
-    Path((_bucket, _key)): Path<(String, String)>,
-) -> impl IntoResponse {
-    debug!("get_object called for bucket {:?}", _bucket);
-    Response::builder()
-        .status(StatusCode::OK)
-        .header("Content-Type", "application/octet-stream")
-        .body(axum::body::Body::from("fake file content"))
-        .unwrap()
+  State(state): State<AppState>,
+  Path((bucket, key)): Path<(String, String)>,
+  Query(query): Query<GetObjectQuery>,
+  headers: HeaderMap,
+) -> Result<Response, S3Error> {
+  debug!("get_object called for {}/{}", bucket, key);
+  let meta = requested_meta(&state, &bucket, &key, query.version_id.as_deref())?;
+  let customer_key = customer_key(&headers)?;
+  check_customer_key(&meta, customer_key.as_ref())?;
+  check_preconditions(&headers, &meta)?;
+  let (status, range, mut response_headers) = read_range(&meta, &headers, query.part_number)?;
+  // 校验值只对应整个对象
+  if status == StatusCode::OK && checksum_mode_enabled(&headers) {
+    response_headers.extend(checksum_headers(meta.checksum.as_ref()));
+  }
+  let reader = state
+    .storage
+    .objects
+    .open_object(&meta, range, customer_key.as_ref())
+    .await?;
+  let body = Body::from_stream(ReaderStream::new(reader));
+  Ok((status, response_headers, body).into_response())
 }
 
 // HEAD /{bucket}/{key} 获取元数据
@@ -62,7 +469,7 @@ pub async fn get_object(
 #[utoipa::path(
     head,
     path = "/{bucket}/{key}",
-tag = OBJECT_TAG,
+    tag = OBJECT_TAG,
     params(
         ("bucket" = String, Path, description = "Bucket name"),
         ("key" = String, Path, description = "Object key")
@@ -70,20 +477,28 @@ tag = OBJECT_TAG,
     responses(
         (status = 200, description = "Metadata retrieved successfully", headers(
             ("Content-Length" = String, description = "Length of the object"),
-            ("Content-Type" = String, description = "Content type of the object")
+            ("Content-Type" = String, description = "Content type of the object"),
+            ("ETag" = String, description = "Entity tag of the object"),
+            ("Last-Modified" = String, description = "Last modification time of the object")
         )),
+        (status = 206, description = "Metadata of the requested range or part"),
         (status = 404, description = "Object not found")
     )
 )]
 pub async fn head_object(
-    State(_state): State<AppState>,
-    Path((_bucket, _key)): Path<(String, String)>,
-) -> impl IntoResponse {
-    let mut headers = HeaderMap::new();
-    headers.insert("Content-Length", "123".parse().unwrap());
-    headers.insert("Content-Type", "application/octet-stream".parse().unwrap());
-
-    (StatusCode::OK, headers)
+  State(state): State<AppState>,
+  Path((bucket, key)): Path<(String, String)>,
+  Query(query): Query<GetObjectQuery>,
+  headers: HeaderMap,
+) -> Result<impl IntoResponse, S3Error> {
+  let meta = requested_meta(&state, &bucket, &key, query.version_id.as_deref())?;
+  check_customer_key(&meta, customer_key(&headers)?.as_ref())?;
+  check_preconditions(&headers, &meta)?;
+  let (status, _, mut response_headers) = read_range(&meta, &headers, query.part_number)?;
+  if status == StatusCode::OK && checksum_mode_enabled(&headers) {
+    response_headers.extend(checksum_headers(meta.checksum.as_ref()));
+  }
+  Ok((status, response_headers))
 }
 
 // DELETE /{bucket}/{key} 删除对象
@@ -91,10 +506,11 @@ pub async fn head_object(
 #[utoipa::path(
     delete,
     path = "/{bucket}/{key}",
-tag = OBJECT_TAG,
+    tag = OBJECT_TAG,
     params(
         ("bucket" = String, Path, description = "Bucket name"),
-        ("key" = String, Path, description = "Object key")
+        ("key" = String, Path, description = "Object key"),
+        DeleteObjectQuery
     ),
     responses(
         (status = 204, description = "Object deleted successfully"),
@@ -102,16 +518,273 @@ tag = OBJECT_TAG,
     )
 )]
 pub async fn delete_object(
-    State(_state): State<AppState>,
-    Path((_bucket, _key)): Path<(String, String)>,
-) -> impl IntoResponse {
-    StatusCode::NO_CONTENT
+  State(state): State<AppState>,
+  Extension(access): Extension<AccessContext>,
+  Path((bucket, key)): Path<(String, String)>,
+  Query(query): Query<DeleteObjectQuery>,
+  headers: HeaderMap,
+) -> Result<impl IntoResponse, S3Error> {
+  let bypass = bypass_governance(&state, &access, &headers, &bucket, &key);
+  // 与 S3 一致：删除不存在的对象或版本同样返回 204
+  let result = state
+    .storage
+    .objects
+    .delete_object(&bucket, &key, query.version_id.as_deref(), bypass)
+    .await?;
+  let mut headers = HeaderMap::new();
+  if let Some(meta) = result {
+    if meta.is_delete_marker() {
+      headers.insert(X_AMZ_DELETE_MARKER, HeaderValue::from_static("true"));
+    }
+    if (meta.is_delete_marker() || query.version_id.is_some())
+      && let Ok(value) = meta.s3_version_id().parse()
+    {
+      headers.insert(X_AMZ_VERSION_ID, value);
+    }
+  }
+  Ok((StatusCode::NO_CONTENT, headers))
+}
+
+#[derive(Deserialize, IntoParams, Default)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteObjectQuery {
+  /// 永久删除指定版本；缺省时在开启版本控制的 bucket 中创建删除标记
+  #[serde(rename = "versionId")]
+  pub version_id: Option<String>,
+}
+
+/// 单次 DeleteObjects 请求最多包含的 key 数
+const MAX_DELETE_OBJECTS: usize = 1000;
+
+#[derive(Deserialize)]
+struct Delete {
+  #[serde(rename = "Quiet", default)]
+  quiet: bool,
+  #[serde(rename = "Object", default)]
+  objects: Vec<ObjectIdentifier>,
+}
+
+#[derive(Deserialize)]
+struct ObjectIdentifier {
+  #[serde(rename = "Key")]
+  key: String,
+  #[serde(rename = "VersionId")]
+  version_id: Option<String>,
 }
 
-#[derive(serde::Serialize, ToSchema)]
-struct ObjectInfo {
-    key: String,
-    size: u64,
+#[derive(Serialize)]
+struct DeletedEntry {
+  #[serde(rename = "Key")]
+  key: String,
+  #[serde(rename = "VersionId", skip_serializing_if = "Option::is_none")]
+  version_id: Option<String>,
+  #[serde(rename = "DeleteMarker", skip_serializing_if = "Option::is_none")]
+  delete_marker: Option<bool>,
+  #[serde(
+    rename = "DeleteMarkerVersionId",
+    skip_serializing_if = "Option::is_none"
+  )]
+  delete_marker_version_id: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DeleteErrorEntry {
+  #[serde(rename = "Key")]
+  key: String,
+  #[serde(rename = "VersionId", skip_serializing_if = "Option::is_none")]
+  version_id: Option<String>,
+  #[serde(rename = "Code")]
+  code: &'static str,
+  #[serde(rename = "Message")]
+  message: String,
+}
+
+#[derive(Serialize)]
+struct DeleteResult {
+  #[serde(rename = "@xmlns")]
+  xmlns: &'static str,
+  #[serde(rename = "Deleted")]
+  deleted: Vec<DeletedEntry>,
+  #[serde(rename = "Error")]
+  errors: Vec<DeleteErrorEntry>,
+}
+
+// POST /{bucket}?delete 批量删除对象
+pub async fn delete_objects(
+  State(state): State<AppState>,
+  Extension(access): Extension<AccessContext>,
+  Path(bucket): Path<String>,
+  headers: HeaderMap,
+  S3Body(body): S3Body,
+) -> Result<Response, S3Error> {
+  let request: Delete = std::str::from_utf8(&body)
+    .ok()
+    .and_then(|xml| quick_xml::de::from_str(xml).ok())
+    .filter(|request: &Delete| (1..=MAX_DELETE_OBJECTS).contains(&request.objects.len()))
+    .ok_or(S3ErrorCode::MalformedXML)?;
+
+  // 与 S3 一致，逐个 key 授权，无权删除的 key 在结果中返回 AccessDenied
+  let (targets, denied): (Vec<_>, Vec<_>) = request.objects.into_iter().partition(|object| {
+    let action = match object.version_id {
+      Some(_) => Action::DeleteObjectVersion,
+      None => Action::DeleteObject,
+    };
+    authorize(&state, &access, action, &bucket, Some(&object.key)).is_ok()
+  });
+  let keys: Vec<DeleteTarget> = targets
+    .iter()
+    .map(|object| DeleteTarget {
+      key: &object.key,
+      version_id: object.version_id.as_deref(),
+      bypass_governance: bypass_governance(&state, &access, &headers, &bucket, &object.key),
+    })
+    .collect();
+  let results = state.storage.objects.delete_objects(&bucket, &keys).await?;
+  debug!("delete_objects {}: {} keys", bucket, keys.len());
+
+  let mut deleted = Vec::new();
+  let mut errors: Vec<_> = denied
+    .into_iter()
+    .map(|object| {
+      let code = S3ErrorCode::AccessDenied;
+      DeleteErrorEntry {
+        key: object.key,
+        version_id: object.version_id,
+        code: code.as_str(),
+        message: code.message().to_string(),
+      }
+    })
+    .collect();
+  for (object, result) in targets.into_iter().zip(results) {
+    match result {
+      // 受 Object Lock 保护的版本
+      Err(err) => {
+        let err = S3Error::from(err);
+        errors.push(DeleteErrorEntry {
+          key: object.key,
+          version_id: object.version_id,
+          code: err.code().as_str(),
+          message: err.message().to_string(),
+        });
+      }
+      // 与单个删除一致，不存在的 key 同样视为删除成功
+      Ok(_) if request.quiet => {}
+      Ok(result) => {
+        let marker = result.filter(|meta| meta.is_delete_marker());
+        deleted.push(DeletedEntry {
+          key: object.key,
+          version_id: object.version_id,
+          delete_marker: marker.as_ref().map(|_| true),
+          delete_marker_version_id: marker.map(|meta| meta.s3_version_id().to_string()),
+        });
+      }
+    }
+  }
+  let result = DeleteResult {
+    xmlns: S3_XMLNS,
+    deleted,
+    errors,
+  };
+  Ok(xml_response("DeleteResult", &result))
+}
+
+const MAX_KEYS: usize = 1000;
+// encoding-type=url 时对 key 做百分号编码，保留 `/` 等非保留字符
+pub(crate) const KEY_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+  .remove(b'-')
+  .remove(b'_')
+  .remove(b'.')
+  .remove(b'~')
+  .remove(b'/');
+
+#[derive(Deserialize, IntoParams, Default)]
+#[into_params(parameter_in = Query)]
+pub struct ListObjectsQuery {
+  /// `2` 表示 ListObjectsV2，缺省为 V1
+  #[serde(rename = "list-type")]
+  pub list_type: Option<u8>,
+  pub prefix: Option<String>,
+  pub delimiter: Option<String>,
+  #[serde(rename = "max-keys")]
+  pub max_keys: Option<usize>,
+  #[serde(rename = "encoding-type")]
+  pub encoding_type: Option<String>,
+  /// V1 分页标记
+  pub marker: Option<String>,
+  #[serde(rename = "start-after")]
+  pub start_after: Option<String>,
+  #[serde(rename = "continuation-token")]
+  pub continuation_token: Option<String>,
+  #[serde(rename = "fetch-owner")]
+  pub fetch_owner: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct ListEntry {
+  #[serde(rename = "Key")]
+  key: String,
+  #[serde(rename = "LastModified")]
+  last_modified: String,
+  #[serde(rename = "ETag")]
+  etag: String,
+  #[serde(rename = "Size")]
+  size: u64,
+  #[serde(rename = "Owner", skip_serializing_if = "Option::is_none")]
+  owner: Option<Owner>,
+  #[serde(rename = "StorageClass")]
+  storage_class: &'static str,
+}
+
+#[derive(Serialize)]
+struct CommonPrefix {
+  #[serde(rename = "Prefix")]
+  prefix: String,
+}
+
+#[derive(Serialize)]
+struct ListBucketResult {
+  #[serde(rename = "@xmlns")]
+  xmlns: &'static str,
+  #[serde(rename = "Name")]
+  name: String,
+  #[serde(rename = "Prefix")]
+  prefix: String,
+  #[serde(rename = "Delimiter", skip_serializing_if = "Option::is_none")]
+  delimiter: Option<String>,
+  #[serde(rename = "MaxKeys")]
+  max_keys: usize,
+  #[serde(rename = "EncodingType", skip_serializing_if = "Option::is_none")]
+  encoding_type: Option<String>,
+  #[serde(rename = "IsTruncated")]
+  is_truncated: bool,
+  // V1
+  #[serde(rename = "Marker", skip_serializing_if = "Option::is_none")]
+  marker: Option<String>,
+  #[serde(rename = "NextMarker", skip_serializing_if = "Option::is_none")]
+  next_marker: Option<String>,
+  // V2
+  #[serde(rename = "KeyCount", skip_serializing_if = "Option::is_none")]
+  key_count: Option<usize>,
+  #[serde(rename = "ContinuationToken", skip_serializing_if = "Option::is_none")]
+  continuation_token: Option<String>,
+  #[serde(
+    rename = "NextContinuationToken",
+    skip_serializing_if = "Option::is_none"
+  )]
+  next_continuation_token: Option<String>,
+  #[serde(rename = "StartAfter", skip_serializing_if = "Option::is_none")]
+  start_after: Option<String>,
+  #[serde(rename = "Contents")]
+  contents: Vec<ListEntry>,
+  #[serde(rename = "CommonPrefixes")]
+  common_prefixes: Vec<CommonPrefix>,
+}
+
+fn decode_continuation_token(token: &str) -> Option<String> {
+  URL_SAFE_NO_PAD
+    .decode(token)
+    .ok()
+    .and_then(|raw| String::from_utf8(raw).ok())
 }
 
 // GET /{bucket} 列出对象
@@ -120,27 +793,94 @@ struct ObjectInfo {
     path = "/{bucket}",
     tag = OBJECT_TAG,
     params(
-        ("bucket" = String, Path, description = "Bucket name")
+        ("bucket" = String, Path, description = "Bucket name"),
+        ListObjectsQuery
     ),
     responses(
-        (status = 200, description = "List objects in bucket", body = [ObjectInfo]),
+        (status = 200, description = "ListBucketResult XML", content_type = "application/xml"),
         (status = 404, description = "Bucket not found")
     )
 )]
 pub async fn list_objects(
-    State(_state): State<AppState>,
-    Path(_bucket): Path<String>,
-) -> impl IntoResponse {
-    let mock_objects = vec![
-        ObjectInfo {
-            key: "file1.txt".into(),
-            size: 1024,
-        },
-        ObjectInfo {
-            key: "image.jpg".into(),
-            size: 2048,
-        },
-    ];
-
-    Json(mock_objects)
-}
\ No newline at end of file
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+  Query(query): Query<ListObjectsQuery>,
+) -> Result<Response, S3Error> {
+  let v2 = query.list_type == Some(2);
+  let start_after = if v2 {
+    match &query.continuation_token {
+      Some(token) => Some(decode_continuation_token(token).ok_or_else(|| {
+        S3Error::new(S3ErrorCode::InvalidArgument)
+          .with_message("The continuation token provided is incorrect")
+          .with_resource(&bucket)
+      })?),
+      None => query.start_after.clone(),
+    }
+  } else {
+    query.marker.clone()
+  };
+  let options = ListOptions {
+    prefix: query.prefix.clone().unwrap_or_default(),
+    delimiter: query.delimiter.clone(),
+    start_after,
+    max_keys: query.max_keys.unwrap_or(MAX_KEYS).min(MAX_KEYS),
+  };
+
+  let bucket_meta = state.storage.buckets.get_bucket(&bucket)?;
+  let page = state.storage.objects.list_objects(&bucket, &options)?;
+
+  let url_encode = query.encoding_type.as_deref() == Some("url");
+  let encode = |value: &str| -> String {
+    if url_encode {
+      utf8_percent_encode(value, KEY_ENCODE_SET).to_string()
+    } else {
+      value.to_string()
+    }
+  };
+  let with_owner = !v2 || query.fetch_owner == Some(true);
+  let key_count = page.objects.len() + page.common_prefixes.len();
+
+  let result = ListBucketResult {
+    xmlns: S3_XMLNS,
+    name: bucket,
+    prefix: encode(&options.prefix),
+    delimiter: options.delimiter.as_deref().map(&encode),
+    max_keys: options.max_keys,
+    encoding_type: query.encoding_type.clone().filter(|_| url_encode),
+    is_truncated: page.is_truncated,
+    marker: (!v2).then(|| encode(query.marker.as_deref().unwrap_or_default())),
+    next_marker: page
+      .next_marker
+      .as_deref()
+      .filter(|_| !v2 && page.is_truncated)
+      .map(&encode),
+    key_count: v2.then_some(key_count),
+    continuation_token: query.continuation_token.clone().filter(|_| v2),
+    next_continuation_token: page
+      .next_marker
+      .as_deref()
+      .filter(|_| v2 && page.is_truncated)
+      .map(|marker| URL_SAFE_NO_PAD.encode(marker)),
+    start_after: query.start_after.as_deref().filter(|_| v2).map(&encode),
+    contents: page
+      .objects
+      .iter()
+      .map(|meta| ListEntry {
+        key: encode(&meta.key),
+        last_modified: format_timestamp(meta.last_modified),
+        etag: format!("\"{}\"", meta.etag),
+        size: meta.size,
+        owner: with_owner.then(|| Owner::new(&bucket_meta.owner)),
+        storage_class: "STANDARD",
+      })
+      .collect(),
+    common_prefixes: page
+      .common_prefixes
+      .iter()
+      .map(|prefix| CommonPrefix {
+        prefix: encode(prefix),
+      })
+      .collect(),
+  };
+  Ok(xml_response("ListBucketResult", &result))
+}
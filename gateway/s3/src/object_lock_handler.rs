@@ -0,0 +1,420 @@
+//! Object Lock：写入时的保留请求头、读取时的响应头，`?object-lock`（bucket 默认保留）、
+//! `?retention` 和 `?legal-hold` 子资源，以及治理模式的绕过。
+use crate::authz::{AccessContext, authorize};
+use crate::chunked::S3Body;
+use crate::error::{S3Error, S3ErrorCode};
+use crate::object_handler::requested_meta;
+use crate::response::{S3_XMLNS, format_timestamp, xml_response};
+use crate::state::AppState;
+use axum::extract::{Extension, Path, Query, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use server::metadata::constant::Action;
+use server::metadata::object_lock::{
+  DefaultRetention, ObjectLock, ObjectLockConfiguration, Retention, RetentionMode, RetentionPeriod,
+};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// 创建 bucket 时开启 Object Lock
+pub const X_AMZ_BUCKET_OBJECT_LOCK_ENABLED: &str = "x-amz-bucket-object-lock-enabled";
+pub const X_AMZ_OBJECT_LOCK_MODE: &str = "x-amz-object-lock-mode";
+pub const X_AMZ_OBJECT_LOCK_RETAIN_UNTIL_DATE: &str = "x-amz-object-lock-retain-until-date";
+pub const X_AMZ_OBJECT_LOCK_LEGAL_HOLD: &str = "x-amz-object-lock-legal-hold";
+pub const X_AMZ_BYPASS_GOVERNANCE_RETENTION: &str = "x-amz-bypass-governance-retention";
+const ENABLED: &str = "Enabled";
+
+fn invalid_argument(message: &'static str) -> S3Error {
+  S3Error::new(S3ErrorCode::InvalidArgument).with_message(message)
+}
+
+fn parse_mode(mode: &str) -> Result<RetentionMode, S3Error> {
+  RetentionMode::from_name(mode).ok_or_else(|| invalid_argument("Unknown wormMode directive."))
+}
+
+fn parse_retain_until(value: &str) -> Result<i64, S3Error> {
+  OffsetDateTime::parse(value.trim(), &Rfc3339)
+    .map(|date| date.unix_timestamp())
+    .map_err(|_| invalid_argument("The retain until date must be provided in ISO 8601 format"))
+}
+
+fn parse_legal_hold(status: &str) -> Result<bool, S3Error> {
+  match status {
+    "ON" => Ok(true),
+    "OFF" => Ok(false),
+    _ => Err(invalid_argument(
+      "Legal Hold must be either of 'ON' or 'OFF'",
+    )),
+  }
+}
+
+/// 写入请求（PutObject / CopyObject / CreateMultipartUpload）指定的保留设置和法律保留
+pub fn request_lock(headers: &HeaderMap) -> Result<ObjectLock, S3Error> {
+  let get = |name: &str| headers.get(name).map(|v| v.to_str().unwrap_or_default());
+  let retention = match (
+    get(X_AMZ_OBJECT_LOCK_MODE),
+    get(X_AMZ_OBJECT_LOCK_RETAIN_UNTIL_DATE),
+  ) {
+    (None, None) => None,
+    (Some(mode), Some(date)) => {
+      let retention = Retention {
+        mode: parse_mode(mode)?,
+        retain_until: parse_retain_until(date)?,
+      };
+      if !retention.is_active(OffsetDateTime::now_utc().unix_timestamp()) {
+        return Err(invalid_argument(
+          "The retain until date must be in the future!",
+        ));
+      }
+      Some(retention)
+    }
+    _ => {
+      return Err(invalid_argument(
+        "x-amz-object-lock-retain-until-date and x-amz-object-lock-mode must both be supplied",
+      ));
+    }
+  };
+  let legal_hold = match get(X_AMZ_OBJECT_LOCK_LEGAL_HOLD) {
+    None => false,
+    Some(status) => parse_legal_hold(status)?,
+  };
+  Ok(ObjectLock {
+    retention,
+    legal_hold,
+  })
+}
+
+/// 对象版本的 Object Lock 响应头
+pub fn lock_headers(lock: &ObjectLock) -> HeaderMap {
+  let mut headers = HeaderMap::new();
+  if let Some(retention) = &lock.retention {
+    headers.insert(
+      X_AMZ_OBJECT_LOCK_MODE,
+      HeaderValue::from_static(retention.mode.name()),
+    );
+    if let Ok(value) = format_timestamp(retention.retain_until).parse() {
+      headers.insert(X_AMZ_OBJECT_LOCK_RETAIN_UNTIL_DATE, value);
+    }
+  }
+  if lock.legal_hold {
+    headers.insert(X_AMZ_OBJECT_LOCK_LEGAL_HOLD, HeaderValue::from_static("ON"));
+  }
+  headers
+}
+
+/// 请求带有 `x-amz-bypass-governance-retention: true` 且请求方有 `s3:BypassGovernanceRetention` 权限；
+/// 没有权限时忽略该请求头，受保护的版本仍然拒绝删除
+pub fn bypass_governance(
+  state: &AppState,
+  access: &AccessContext,
+  headers: &HeaderMap,
+  bucket: &str,
+  key: &str,
+) -> bool {
+  headers
+    .get(X_AMZ_BYPASS_GOVERNANCE_RETENTION)
+    .is_some_and(|v| v.as_bytes().eq_ignore_ascii_case(b"true"))
+    && authorize(
+      state,
+      access,
+      Action::BypassGovernanceRetention,
+      bucket,
+      Some(key),
+    )
+    .is_ok()
+}
+
+#[derive(Serialize, Deserialize)]
+struct DefaultRetentionEntry {
+  #[serde(rename = "Mode")]
+  mode: String,
+  #[serde(rename = "Days", skip_serializing_if = "Option::is_none")]
+  days: Option<u32>,
+  #[serde(rename = "Years", skip_serializing_if = "Option::is_none")]
+  years: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RuleEntry {
+  #[serde(rename = "DefaultRetention")]
+  default_retention: DefaultRetentionEntry,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ObjectLockConfigurationXml {
+  #[serde(rename = "@xmlns", skip_deserializing)]
+  xmlns: &'static str,
+  #[serde(rename = "ObjectLockEnabled")]
+  object_lock_enabled: Option<String>,
+  #[serde(rename = "Rule", skip_serializing_if = "Option::is_none")]
+  rule: Option<RuleEntry>,
+}
+
+fn parse_object_lock(body: &[u8]) -> Result<ObjectLockConfiguration, S3Error> {
+  let config: ObjectLockConfigurationXml = std::str::from_utf8(body)
+    .ok()
+    .and_then(|xml| quick_xml::de::from_str(xml).ok())
+    .ok_or(S3ErrorCode::MalformedXML)?;
+  if config.object_lock_enabled.as_deref() != Some(ENABLED) {
+    return Err(S3ErrorCode::MalformedXML.into());
+  }
+  let default_retention = match config.rule {
+    None => None,
+    Some(RuleEntry { default_retention }) => {
+      let period = match (default_retention.days, default_retention.years) {
+        (Some(days), None) => RetentionPeriod::Days(days),
+        (None, Some(years)) => RetentionPeriod::Years(years),
+        _ => return Err(S3ErrorCode::MalformedXML.into()),
+      };
+      Some(DefaultRetention {
+        mode: parse_mode(&default_retention.mode)?,
+        period,
+      })
+    }
+  };
+  Ok(ObjectLockConfiguration { default_retention })
+}
+
+// GET /{bucket}?object-lock
+pub async fn get_object_lock_configuration(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+) -> Result<Response, S3Error> {
+  let meta = state.storage.buckets.get_bucket(&bucket)?;
+  let Some(config) = meta.config.object_lock else {
+    return Err(
+      S3Error::new(S3ErrorCode::ObjectLockConfigurationNotFoundError).with_resource(bucket),
+    );
+  };
+  let rule = config.default_retention.map(|default| {
+    let (days, years) = match default.period {
+      RetentionPeriod::Days(days) => (Some(days), None),
+      RetentionPeriod::Years(years) => (None, Some(years)),
+    };
+    RuleEntry {
+      default_retention: DefaultRetentionEntry {
+        mode: default.mode.name().to_string(),
+        days,
+        years,
+      },
+    }
+  });
+  Ok(xml_response(
+    "ObjectLockConfiguration",
+    &ObjectLockConfigurationXml {
+      xmlns: S3_XMLNS,
+      object_lock_enabled: Some(ENABLED.to_string()),
+      rule,
+    },
+  ))
+}
+
+// PUT /{bucket}?object-lock
+pub async fn put_object_lock_configuration(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+  S3Body(body): S3Body,
+) -> Result<StatusCode, S3Error> {
+  let config = parse_object_lock(&body)?;
+  state
+    .storage
+    .buckets
+    .put_object_lock_configuration(&bucket, config)?;
+  Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct LockQuery {
+  #[serde(rename = "versionId")]
+  pub version_id: Option<String>,
+}
+
+/// `?retention` 的请求和响应体；请求中两个元素都为空表示移除保留设置
+#[derive(Serialize, Deserialize)]
+struct RetentionXml {
+  #[serde(rename = "@xmlns", skip_deserializing)]
+  xmlns: &'static str,
+  #[serde(rename = "Mode")]
+  mode: Option<String>,
+  #[serde(rename = "RetainUntilDate")]
+  retain_until_date: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LegalHoldXml {
+  #[serde(rename = "@xmlns", skip_deserializing)]
+  xmlns: &'static str,
+  #[serde(rename = "Status")]
+  status: String,
+}
+
+fn parse_retention(body: &[u8]) -> Result<Option<Retention>, S3Error> {
+  let retention: RetentionXml = std::str::from_utf8(body)
+    .ok()
+    .and_then(|xml| quick_xml::de::from_str(xml).ok())
+    .ok_or(S3ErrorCode::MalformedXML)?;
+  match (retention.mode, retention.retain_until_date) {
+    (None, None) => Ok(None),
+    (Some(mode), Some(date)) => Ok(Some(Retention {
+      mode: parse_mode(&mode)?,
+      retain_until: parse_retain_until(&date)?,
+    })),
+    _ => Err(S3ErrorCode::MalformedXML.into()),
+  }
+}
+
+/// 读取 `?retention` / `?legal-hold` 的目标版本；bucket 没有开启 Object Lock 时返回 InvalidRequest
+fn lock_target(
+  state: &AppState,
+  bucket: &str,
+  key: &str,
+  version_id: Option<&str>,
+) -> Result<ObjectLock, S3Error> {
+  let meta = requested_meta(state, bucket, key, version_id)?;
+  if state
+    .storage
+    .buckets
+    .get_bucket(bucket)?
+    .config
+    .object_lock
+    .is_none()
+  {
+    return Err(
+      S3Error::new(S3ErrorCode::InvalidRequest)
+        .with_message("Bucket is missing Object Lock Configuration"),
+    );
+  }
+  Ok(meta.lock)
+}
+
+// GET /{bucket}/{key}?retention
+pub async fn get_object_retention(
+  State(state): State<AppState>,
+  Path((bucket, key)): Path<(String, String)>,
+  Query(query): Query<LockQuery>,
+) -> Result<Response, S3Error> {
+  let lock = lock_target(&state, &bucket, &key, query.version_id.as_deref())?;
+  let Some(retention) = lock.retention else {
+    return Err(S3Error::new(S3ErrorCode::NoSuchObjectLockConfiguration).with_resource(key));
+  };
+  Ok(xml_response(
+    "Retention",
+    &RetentionXml {
+      xmlns: S3_XMLNS,
+      mode: Some(retention.mode.name().to_string()),
+      retain_until_date: Some(format_timestamp(retention.retain_until)),
+    },
+  ))
+}
+
+// PUT /{bucket}/{key}?retention
+pub async fn put_object_retention(
+  State(state): State<AppState>,
+  Extension(access): Extension<AccessContext>,
+  Path((bucket, key)): Path<(String, String)>,
+  Query(query): Query<LockQuery>,
+  headers: HeaderMap,
+  S3Body(body): S3Body,
+) -> Result<StatusCode, S3Error> {
+  let retention = parse_retention(&body)?;
+  // 指定的版本是删除标记时返回 405
+  requested_meta(&state, &bucket, &key, query.version_id.as_deref())?;
+  let bypass = bypass_governance(&state, &access, &headers, &bucket, &key);
+  state.storage.objects.put_object_retention(
+    &bucket,
+    &key,
+    query.version_id.as_deref(),
+    retention,
+    bypass,
+  )?;
+  Ok(StatusCode::OK)
+}
+
+// GET /{bucket}/{key}?legal-hold
+pub async fn get_object_legal_hold(
+  State(state): State<AppState>,
+  Path((bucket, key)): Path<(String, String)>,
+  Query(query): Query<LockQuery>,
+) -> Result<Response, S3Error> {
+  let lock = lock_target(&state, &bucket, &key, query.version_id.as_deref())?;
+  let status = if lock.legal_hold { "ON" } else { "OFF" };
+  Ok(xml_response(
+    "LegalHold",
+    &LegalHoldXml {
+      xmlns: S3_XMLNS,
+      status: status.to_string(),
+    },
+  ))
+}
+
+// PUT /{bucket}/{key}?legal-hold
+pub async fn put_object_legal_hold(
+  State(state): State<AppState>,
+  Path((bucket, key)): Path<(String, String)>,
+  Query(query): Query<LockQuery>,
+  S3Body(body): S3Body,
+) -> Result<StatusCode, S3Error> {
+  let legal_hold: LegalHoldXml = std::str::from_utf8(&body)
+    .ok()
+    .and_then(|xml| quick_xml::de::from_str(xml).ok())
+    .ok_or(S3ErrorCode::MalformedXML)?;
+  let legal_hold = parse_legal_hold(&legal_hold.status)?;
+  requested_meta(&state, &bucket, &key, query.version_id.as_deref())?;
+  state.storage.objects.put_object_legal_hold(
+    &bucket,
+    &key,
+    query.version_id.as_deref(),
+    legal_hold,
+  )?;
+  Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn object_lock_xml_and_headers() {
+    let xml = r#"<ObjectLockConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+      <ObjectLockEnabled>Enabled</ObjectLockEnabled>
+      <Rule><DefaultRetention><Mode>COMPLIANCE</Mode><Years>1</Years></DefaultRetention></Rule>
+    </ObjectLockConfiguration>"#;
+    let config = parse_object_lock(xml.as_bytes()).unwrap();
+    let default = config.default_retention.unwrap();
+    assert_eq!(default.mode, RetentionMode::Compliance);
+    assert_eq!(default.period, RetentionPeriod::Years(1));
+    let both = xml.replace("<Years>1</Years>", "<Years>1</Years><Days>1</Days>");
+    assert!(parse_object_lock(both.as_bytes()).is_err());
+
+    let retention = parse_retention(
+      b"<Retention><Mode>GOVERNANCE</Mode><RetainUntilDate>2030-01-01T00:00:00.000Z</RetainUntilDate></Retention>",
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(retention.retain_until, 1893456000);
+    assert!(parse_retention(b"<Retention/>").unwrap().is_none());
+
+    let mut headers = HeaderMap::new();
+    headers.insert(X_AMZ_OBJECT_LOCK_MODE, "GOVERNANCE".parse().unwrap());
+    assert!(request_lock(&headers).is_err());
+    headers.insert(
+      X_AMZ_OBJECT_LOCK_RETAIN_UNTIL_DATE,
+      "2999-01-01T00:00:00Z".parse().unwrap(),
+    );
+    headers.insert(X_AMZ_OBJECT_LOCK_LEGAL_HOLD, "ON".parse().unwrap());
+    let lock = request_lock(&headers).unwrap();
+    assert!(lock.legal_hold);
+    let response = lock_headers(&lock);
+    assert_eq!(response[X_AMZ_OBJECT_LOCK_MODE], "GOVERNANCE");
+    assert_eq!(
+      response[X_AMZ_OBJECT_LOCK_RETAIN_UNTIL_DATE],
+      "2999-01-01T00:00:00.000Z"
+    );
+    headers.insert(
+      X_AMZ_OBJECT_LOCK_RETAIN_UNTIL_DATE,
+      "2000-01-01T00:00:00Z".parse().unwrap(),
+    );
+    assert!(request_lock(&headers).is_err());
+  }
+}
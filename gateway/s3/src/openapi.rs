@@ -5,6 +5,7 @@ use crate::object_handler::__path_head_object;
 use crate::object_handler::__path_get_object;
 use crate::object_handler::__path_delete_object;
 use crate::bucket_handler::__path_delete_bucket;
+use crate::bucket_handler::__path_head_bucket;
 use crate::bucket_handler::__path_create_bucket;
 use utoipa::OpenApi;
 pub const S3_TAG: &str = "s3";
@@ -20,7 +21,8 @@ pub const S3_TAG: &str = "s3";
         put_object,
         list_buckets,
         delete_bucket,
-        create_bucket
+        create_bucket,
+        head_bucket
         )
 )
 ]
@@ -0,0 +1,67 @@
+//! HTTP Range 请求头解析（RFC 9110），只支持单个字节区间。
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+  /// `bytes=start-` 或 `bytes=start-end`（end 包含在内）
+  From { start: u64, end: Option<u64> },
+  /// `bytes=-n`，最后 n 个字节
+  Suffix(u64),
+}
+
+/// 解析 Range 头；格式不合法或包含多个区间时返回 None，按规范忽略该头返回整个对象
+pub fn parse_range(value: &str) -> Option<ByteRange> {
+  let spec = value.trim().strip_prefix("bytes=")?.trim();
+  if spec.contains(',') {
+    return None;
+  }
+  let (start, end) = spec.split_once('-')?;
+  let (start, end) = (start.trim(), end.trim());
+  if start.is_empty() {
+    return end.parse().ok().map(ByteRange::Suffix);
+  }
+  let start = start.parse().ok()?;
+  let end = match end {
+    "" => None,
+    end => Some(end.parse().ok()?),
+  };
+  if end.is_some_and(|end| end < start) {
+    return None;
+  }
+  Some(ByteRange::From { start, end })
+}
+
+impl ByteRange {
+  /// 按对象大小换算成左闭右开的区间，None 表示无法满足（416）
+  pub fn resolve(self, size: u64) -> Option<Range<u64>> {
+    match self {
+      ByteRange::From { start, .. } if start >= size => None,
+      ByteRange::From { start, end } => {
+        let end = end.map_or(size, |end| (end + 1).min(size));
+        Some(start..end)
+      }
+      ByteRange::Suffix(0) => None,
+      ByteRange::Suffix(n) => Some(size.saturating_sub(n)..size),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{ByteRange, parse_range};
+
+  #[test]
+  fn parse_and_resolve() {
+    let range = parse_range("bytes=2-5").unwrap();
+    assert_eq!(range.resolve(10), Some(2..6));
+    assert_eq!(range.resolve(4), Some(2..4));
+    assert_eq!(range.resolve(2), None);
+    assert_eq!(parse_range("bytes=3-").unwrap().resolve(10), Some(3..10));
+    assert_eq!(parse_range("bytes=-4"), Some(ByteRange::Suffix(4)));
+    assert_eq!(parse_range("bytes=-40").unwrap().resolve(10), Some(0..10));
+    assert_eq!(parse_range("bytes=-0").unwrap().resolve(10), None);
+    assert_eq!(parse_range("bytes=5-2"), None);
+    assert_eq!(parse_range("bytes=0-1,4-5"), None);
+    assert_eq!(parse_range("items=0-1"), None);
+  }
+}
@@ -0,0 +1,52 @@
+use axum::body::Body;
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use time::macros::format_description;
+use time::{OffsetDateTime, PrimitiveDateTime};
+use tracing::error;
+
+pub const S3_XMLNS: &str = "http://s3.amazonaws.com/doc/2006-03-01/";
+const XML_DECLARATION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+
+/// 将响应结构序列化为带 XML 声明的 S3 响应体
+pub fn xml_response<T: Serialize>(root: &str, value: &T) -> Response {
+  match quick_xml::se::to_string_with_root(root, value) {
+    Ok(xml) => (
+      [(header::CONTENT_TYPE, "application/xml")],
+      Body::from(format!("{XML_DECLARATION}{xml}")),
+    )
+      .into_response(),
+    Err(err) => {
+      error!("failed to serialize {}: {}", root, err);
+      StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    }
+  }
+}
+
+/// S3 XML 中使用的 ISO 8601 时间格式
+pub fn format_timestamp(timestamp: i64) -> String {
+  let format = format_description!("[year]-[month]-[day]T[hour]:[minute]:[second].000Z");
+  OffsetDateTime::from_unix_timestamp(timestamp)
+    .ok()
+    .and_then(|t| t.format(format).ok())
+    .unwrap_or_default()
+}
+
+/// HTTP 头中使用的 IMF-fixdate 时间格式，如 `Sun, 06 Nov 1994 08:49:37 GMT`
+const HTTP_DATE: &[time::format_description::FormatItem<'static>] = format_description!(
+  "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT"
+);
+
+pub fn format_http_date(timestamp: i64) -> String {
+  OffsetDateTime::from_unix_timestamp(timestamp)
+    .ok()
+    .and_then(|t| t.format(HTTP_DATE).ok())
+    .unwrap_or_default()
+}
+
+pub fn parse_http_date(value: &str) -> Option<i64> {
+  PrimitiveDateTime::parse(value.trim(), HTTP_DATE)
+    .ok()
+    .map(|t| t.assume_utc().unix_timestamp())
+}
@@ -1,35 +1,48 @@
-use axum::routing::{delete, get, head, put};
-use axum::Router;
+use axum::routing::{any, get};
+use axum::{Router, ServiceExt};
+use axum::middleware::{from_fn, from_fn_with_state};
 use axum_prometheus::PrometheusMetricLayer;
+use std::net::SocketAddr;
+use tower::Layer;
 use tower_http::trace::TraceLayer;
 use tracing::debug;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
-use crate::bucket_handler::{create_bucket, delete_bucket, list_buckets};
-use crate::object_handler::{delete_object, get_object, head_object, list_objects, put_object};
+use crate::auth::sigv4_auth;
+use crate::authz::authorize_request;
+use crate::cors_handler::cors;
+use crate::error::request_id;
+use crate::dispatch::dispatch;
 use crate::openapi::ApiDoc;
 use crate::state::AppState;
+use crate::vhost::{VirtualHosts, virtual_host};
 
 pub struct S3Server {
   router: Router,
   address: String,
+  virtual_hosts: VirtualHosts,
 }
 
 impl S3Server {
-  pub fn new(address: String) -> Self {
+  pub fn new(address: String, state: AppState) -> Self {
     let (prom_layer, metric_handle) = PrometheusMetricLayer::pair();
     // build our application with a route
+    let s3_routes = Router::new()
+      // 方法和查询子资源由 dispatch 解析后分发，bucket 和对象操作共用同一入口
+      .route("/", any(dispatch))
+      .route("/{bucket}", any(dispatch))
+      // key 可以包含 `/`，使用通配段匹配
+      .route("/{bucket}/{*key}", any(dispatch))
+      // 认证之后按 bucket 策略授权
+      .route_layer(from_fn_with_state(state.clone(), authorize_request))
+      // 所有 S3 路由都需要通过 SigV4 认证
+      .route_layer(from_fn_with_state(state.clone(), sigv4_auth))
+      // CORS 在认证之外：预检请求不带签名，认证失败的响应也需要 CORS 头浏览器才能读取
+      .layer(from_fn_with_state(state.clone(), cors))
+      // 在认证之外生成 RequestId，认证失败的响应同样带 x-amz-request-id
+      .layer(from_fn(request_id));
     let app = Router::new()
-      .route("/", get(list_buckets))
-      // bucket 操作
-      .route("/{bucket}", put(create_bucket))
-      .route("/{bucket}", delete(delete_bucket))
-     
-      .route("/{bucket}", get(list_objects))
-      .route("/{bucket}/{key}", put(put_object))
-      .route("/{bucket}/{key}", get(get_object))
-      .route("/{bucket}/{key}", head(head_object))
-      .route("/{bucket}/{key}", delete(delete_object))
+      .merge(s3_routes)
       .route(
         "/metrics",
         get(move || async move { metric_handle.render() }),
@@ -37,13 +50,20 @@ impl S3Server {
       .merge(SwaggerUi::new("/swagger-ui").url("/api-doc/openapi.json", ApiDoc::openapi()))
       .layer(prom_layer)
       .layer(TraceLayer::new_for_http())
-      .with_state(AppState {});
+      .with_state(state);
     S3Server {
       router: app,
       address,
+      virtual_hosts: VirtualHosts::default(),
     }
   }
 
+  /// 启用虚拟主机风格访问
+  pub fn with_virtual_hosts(mut self, virtual_hosts: VirtualHosts) -> Self {
+    self.virtual_hosts = virtual_hosts;
+    self
+  }
+
   pub async fn start(&self) {
     // run our app with hyper, listening globally on port 3000
     debug!("Starting S3 Server:http://{}", self.address);
@@ -51,6 +71,15 @@ impl S3Server {
     let listener = tokio::net::TcpListener::bind(self.address.clone())
       .await
       .unwrap();
-    axum::serve(listener, self.router.clone()).await.unwrap();
+    // 虚拟主机风格的改写要在路由匹配之前，因此包在整个 Router 外面
+    let app = from_fn_with_state(self.virtual_hosts.clone(), virtual_host)
+      .layer(self.router.clone());
+    // 策略条件 aws:SourceIp 需要客户端地址
+    axum::serve(
+      listener,
+      app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
   }
 }
@@ -1,4 +1,27 @@
+use crate::auth::Authenticator;
+use server::storage::Storage;
+use std::sync::Arc;
+
 #[derive(Clone)]
 pub struct AppState {
-    // 可扩展你的 KV 存储、文件路径等状态
-}
\ No newline at end of file
+  pub storage: Arc<Storage>,
+  pub auth: Arc<Authenticator>,
+  /// 是否信任反向代理设置的 `X-Forwarded-Proto`
+  pub trusted_proxy: bool,
+}
+
+impl AppState {
+  pub fn new(storage: Storage, auth: Authenticator) -> Self {
+    Self {
+      storage: Arc::new(storage),
+      auth: Arc::new(auth),
+      trusted_proxy: false,
+    }
+  }
+
+  /// 网关位于终止 TLS 的反向代理之后时，按代理转发的协议判断请求是否经过 HTTPS
+  pub fn with_trusted_proxy(mut self, trusted_proxy: bool) -> Self {
+    self.trusted_proxy = trusted_proxy;
+    self
+  }
+}
@@ -0,0 +1,196 @@
+//! `?tagging` 子资源：对象和 bucket 的标签读写，以及 PUT 时的 `x-amz-tagging` 请求头。
+use crate::chunked::S3Body;
+use crate::error::{S3Error, S3ErrorCode};
+use crate::response::{S3_XMLNS, xml_response};
+use crate::state::AppState;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Response;
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use server::metadata::tagging::Tag;
+
+pub const X_AMZ_TAGGING: &str = "x-amz-tagging";
+pub const X_AMZ_TAGGING_DIRECTIVE: &str = "x-amz-tagging-directive";
+pub const X_AMZ_TAGGING_COUNT: &str = "x-amz-tagging-count";
+
+#[derive(Serialize, Deserialize)]
+struct TagEntry {
+  #[serde(rename = "Key")]
+  key: String,
+  #[serde(rename = "Value", default)]
+  value: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TagSet {
+  #[serde(rename = "Tag", default)]
+  tags: Vec<TagEntry>,
+}
+
+#[derive(Deserialize)]
+struct Tagging {
+  #[serde(rename = "TagSet")]
+  tag_set: TagSet,
+}
+
+#[derive(Serialize)]
+struct TaggingResult {
+  #[serde(rename = "@xmlns")]
+  xmlns: &'static str,
+  #[serde(rename = "TagSet")]
+  tag_set: TagSet,
+}
+
+fn parse_tagging(body: &[u8]) -> Result<Vec<Tag>, S3Error> {
+  let tagging: Tagging = std::str::from_utf8(body)
+    .ok()
+    .and_then(|xml| quick_xml::de::from_str(xml).ok())
+    .ok_or(S3ErrorCode::MalformedXML)?;
+  Ok(
+    tagging
+      .tag_set
+      .tags
+      .into_iter()
+      .map(|tag| Tag::new(tag.key, tag.value))
+      .collect(),
+  )
+}
+
+fn tagging_response(tags: Vec<Tag>) -> Response {
+  let tags = tags
+    .into_iter()
+    .map(|tag| TagEntry {
+      key: tag.key,
+      value: tag.value,
+    })
+    .collect();
+  xml_response(
+    "Tagging",
+    &TaggingResult {
+      xmlns: S3_XMLNS,
+      tag_set: TagSet { tags },
+    },
+  )
+}
+
+/// 解析 `x-amz-tagging: k1=v1&k2=v2`，键值为 URL 编码
+pub(crate) fn header_tags(headers: &HeaderMap) -> Result<Vec<Tag>, S3Error> {
+  let Some(value) = headers.get(X_AMZ_TAGGING) else {
+    return Ok(Vec::new());
+  };
+  let invalid = || {
+    S3Error::new(S3ErrorCode::InvalidArgument).with_message("The x-amz-tagging header is invalid")
+  };
+  let decode = |s: &str| {
+    percent_decode_str(&s.replace('+', " "))
+      .decode_utf8()
+      .map(|s| s.into_owned())
+      .map_err(|_| invalid())
+  };
+  let value = value.to_str().map_err(|_| invalid())?;
+  value
+    .split('&')
+    .filter(|pair| !pair.is_empty())
+    .map(|pair| {
+      let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+      Ok(Tag::new(decode(key)?, decode(value)?))
+    })
+    .collect()
+}
+
+// GET /{bucket}/{key}?tagging
+pub async fn get_object_tagging(
+  State(state): State<AppState>,
+  Path((bucket, key)): Path<(String, String)>,
+) -> Result<Response, S3Error> {
+  let meta = state.storage.objects.head_object(&bucket, &key)?;
+  Ok(tagging_response(meta.tags))
+}
+
+// PUT /{bucket}/{key}?tagging
+pub async fn put_object_tagging(
+  State(state): State<AppState>,
+  Path((bucket, key)): Path<(String, String)>,
+  S3Body(body): S3Body,
+) -> Result<StatusCode, S3Error> {
+  let tags = parse_tagging(&body)?;
+  state
+    .storage
+    .objects
+    .put_object_tagging(&bucket, &key, tags)?;
+  Ok(StatusCode::OK)
+}
+
+// DELETE /{bucket}/{key}?tagging
+pub async fn delete_object_tagging(
+  State(state): State<AppState>,
+  Path((bucket, key)): Path<(String, String)>,
+) -> Result<StatusCode, S3Error> {
+  state
+    .storage
+    .objects
+    .put_object_tagging(&bucket, &key, Vec::new())?;
+  Ok(StatusCode::NO_CONTENT)
+}
+
+// GET /{bucket}?tagging
+pub async fn get_bucket_tagging(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+) -> Result<Response, S3Error> {
+  let meta = state.storage.buckets.get_bucket(&bucket)?;
+  if meta.tags.is_empty() {
+    return Err(S3Error::new(S3ErrorCode::NoSuchTagSet).with_resource(bucket));
+  }
+  Ok(tagging_response(meta.tags))
+}
+
+// PUT /{bucket}?tagging
+pub async fn put_bucket_tagging(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+  S3Body(body): S3Body,
+) -> Result<StatusCode, S3Error> {
+  let tags = parse_tagging(&body)?;
+  state.storage.buckets.put_bucket_tagging(&bucket, tags)?;
+  Ok(StatusCode::NO_CONTENT)
+}
+
+// DELETE /{bucket}?tagging
+pub async fn delete_bucket_tagging(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+) -> Result<StatusCode, S3Error> {
+  state
+    .storage
+    .buckets
+    .put_bucket_tagging(&bucket, Vec::new())?;
+  Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn tagging_header_and_xml() {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+      X_AMZ_TAGGING,
+      "team=data%20eng&empty=&a+b=c".parse().unwrap(),
+    );
+    assert_eq!(
+      header_tags(&headers).unwrap(),
+      [
+        Tag::new("team", "data eng"),
+        Tag::new("empty", ""),
+        Tag::new("a b", "c")
+      ]
+    );
+
+    let xml = r#"<Tagging><TagSet><Tag><Key>k</Key><Value>v</Value></Tag></TagSet></Tagging>"#;
+    assert_eq!(parse_tagging(xml.as_bytes()).unwrap(), [Tag::new("k", "v")]);
+    assert!(parse_tagging(b"<Tagging>").is_err());
+  }
+}
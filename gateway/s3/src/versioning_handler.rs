@@ -0,0 +1,305 @@
+//! `?versioning` 子资源和 `?versions`（ListObjectVersions）。
+use crate::bucket_handler::Owner;
+use crate::chunked::S3Body;
+use crate::error::{S3Error, S3ErrorCode};
+use crate::object_handler::KEY_ENCODE_SET;
+use crate::response::{S3_XMLNS, format_timestamp, xml_response};
+use crate::state::AppState;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use percent_encoding::utf8_percent_encode;
+use serde::{Deserialize, Serialize};
+use server::metadata::config::VersioningStatus;
+use server::object::version::ListVersionsOptions;
+
+const MAX_KEYS: usize = 1000;
+
+#[derive(Serialize, Deserialize)]
+struct VersioningConfiguration {
+  #[serde(rename = "@xmlns", skip_deserializing)]
+  xmlns: &'static str,
+  /// 从未开启过版本控制的 bucket 不返回 Status
+  #[serde(rename = "Status", skip_serializing_if = "Option::is_none")]
+  status: Option<String>,
+}
+
+// GET /{bucket}?versioning
+pub async fn get_bucket_versioning(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+) -> Result<Response, S3Error> {
+  let meta = state.storage.buckets.get_bucket(&bucket)?;
+  let status = match meta.config.versioning {
+    VersioningStatus::Unversioned => None,
+    VersioningStatus::Enabled => Some("Enabled".to_string()),
+    VersioningStatus::Suspended => Some("Suspended".to_string()),
+  };
+  Ok(xml_response(
+    "VersioningConfiguration",
+    &VersioningConfiguration {
+      xmlns: S3_XMLNS,
+      status,
+    },
+  ))
+}
+
+// PUT /{bucket}?versioning
+pub async fn put_bucket_versioning(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+  S3Body(body): S3Body,
+) -> Result<Response, S3Error> {
+  let request: VersioningConfiguration = std::str::from_utf8(&body)
+    .ok()
+    .and_then(|xml| quick_xml::de::from_str(xml).ok())
+    .ok_or(S3ErrorCode::MalformedXML)?;
+  // 开启后只能暂停，不能回到未开启状态
+  let status = match request.status.as_deref() {
+    Some("Enabled") => VersioningStatus::Enabled,
+    Some("Suspended") => VersioningStatus::Suspended,
+    _ => return Err(S3ErrorCode::MalformedXML.into()),
+  };
+  state
+    .storage
+    .buckets
+    .put_bucket_versioning(&bucket, status)?;
+  Ok(StatusCode::OK.into_response())
+}
+
+#[derive(Deserialize)]
+pub struct ListVersionsQuery {
+  pub prefix: Option<String>,
+  pub delimiter: Option<String>,
+  #[serde(rename = "key-marker")]
+  pub key_marker: Option<String>,
+  #[serde(rename = "version-id-marker")]
+  pub version_id_marker: Option<String>,
+  #[serde(rename = "max-keys")]
+  pub max_keys: Option<usize>,
+  #[serde(rename = "encoding-type")]
+  pub encoding_type: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ObjectVersion {
+  #[serde(rename = "Key")]
+  key: String,
+  #[serde(rename = "VersionId")]
+  version_id: String,
+  #[serde(rename = "IsLatest")]
+  is_latest: bool,
+  #[serde(rename = "LastModified")]
+  last_modified: String,
+  #[serde(rename = "ETag")]
+  etag: String,
+  #[serde(rename = "Size")]
+  size: u64,
+  #[serde(rename = "Owner")]
+  owner: Owner,
+  #[serde(rename = "StorageClass")]
+  storage_class: &'static str,
+}
+
+#[derive(Serialize)]
+struct DeleteMarkerEntry {
+  #[serde(rename = "Key")]
+  key: String,
+  #[serde(rename = "VersionId")]
+  version_id: String,
+  #[serde(rename = "IsLatest")]
+  is_latest: bool,
+  #[serde(rename = "LastModified")]
+  last_modified: String,
+  #[serde(rename = "Owner")]
+  owner: Owner,
+}
+
+/// 版本和删除标记按列举顺序交错输出
+#[derive(Serialize)]
+enum VersionListEntry {
+  Version(ObjectVersion),
+  DeleteMarker(DeleteMarkerEntry),
+}
+
+#[derive(Serialize)]
+struct CommonPrefix {
+  #[serde(rename = "Prefix")]
+  prefix: String,
+}
+
+#[derive(Serialize)]
+struct ListVersionsResult {
+  #[serde(rename = "@xmlns")]
+  xmlns: &'static str,
+  #[serde(rename = "Name")]
+  name: String,
+  #[serde(rename = "Prefix")]
+  prefix: String,
+  #[serde(rename = "KeyMarker")]
+  key_marker: String,
+  #[serde(rename = "VersionIdMarker")]
+  version_id_marker: String,
+  #[serde(rename = "NextKeyMarker", skip_serializing_if = "Option::is_none")]
+  next_key_marker: Option<String>,
+  #[serde(
+    rename = "NextVersionIdMarker",
+    skip_serializing_if = "Option::is_none"
+  )]
+  next_version_id_marker: Option<String>,
+  #[serde(rename = "MaxKeys")]
+  max_keys: usize,
+  #[serde(rename = "Delimiter", skip_serializing_if = "Option::is_none")]
+  delimiter: Option<String>,
+  #[serde(rename = "EncodingType", skip_serializing_if = "Option::is_none")]
+  encoding_type: Option<String>,
+  #[serde(rename = "IsTruncated")]
+  is_truncated: bool,
+  #[serde(rename = "$value")]
+  entries: Vec<VersionListEntry>,
+  #[serde(rename = "CommonPrefixes")]
+  common_prefixes: Vec<CommonPrefix>,
+}
+
+// GET /{bucket}?versions
+pub async fn list_object_versions(
+  State(state): State<AppState>,
+  Path(bucket): Path<String>,
+  Query(query): Query<ListVersionsQuery>,
+) -> Result<Response, S3Error> {
+  if query.version_id_marker.is_some() && query.key_marker.is_none() {
+    return Err(
+      S3Error::new(S3ErrorCode::InvalidArgument)
+        .with_message("A version-id marker cannot be specified without a key marker."),
+    );
+  }
+  let options = ListVersionsOptions {
+    prefix: query.prefix.clone().unwrap_or_default(),
+    delimiter: query.delimiter.clone(),
+    key_marker: query.key_marker.clone(),
+    version_id_marker: query.version_id_marker.clone(),
+    max_keys: query.max_keys.unwrap_or(MAX_KEYS).min(MAX_KEYS),
+  };
+  let bucket_meta = state.storage.buckets.get_bucket(&bucket)?;
+  let page = state
+    .storage
+    .objects
+    .list_object_versions(&bucket, &options)?;
+
+  let url_encode = query.encoding_type.as_deref() == Some("url");
+  let encode = |value: &str| -> String {
+    if url_encode {
+      utf8_percent_encode(value, KEY_ENCODE_SET).to_string()
+    } else {
+      value.to_string()
+    }
+  };
+  let result = ListVersionsResult {
+    xmlns: S3_XMLNS,
+    name: bucket,
+    prefix: encode(&options.prefix),
+    key_marker: encode(options.key_marker.as_deref().unwrap_or_default()),
+    version_id_marker: options.version_id_marker.clone().unwrap_or_default(),
+    next_key_marker: page.next_key_marker.as_deref().map(&encode),
+    next_version_id_marker: page.next_version_id_marker.clone(),
+    max_keys: options.max_keys,
+    delimiter: options.delimiter.as_deref().map(&encode),
+    encoding_type: query.encoding_type.clone().filter(|_| url_encode),
+    is_truncated: page.is_truncated,
+    entries: page
+      .versions
+      .iter()
+      .map(|entry| {
+        let meta = &entry.meta;
+        let key = encode(&meta.key);
+        let version_id = meta.s3_version_id().to_string();
+        let last_modified = format_timestamp(meta.last_modified);
+        let owner = Owner::new(&bucket_meta.owner);
+        if meta.is_delete_marker() {
+          VersionListEntry::DeleteMarker(DeleteMarkerEntry {
+            key,
+            version_id,
+            is_latest: entry.is_latest,
+            last_modified,
+            owner,
+          })
+        } else {
+          VersionListEntry::Version(ObjectVersion {
+            key,
+            version_id,
+            is_latest: entry.is_latest,
+            last_modified,
+            etag: format!("\"{}\"", meta.etag),
+            size: meta.size,
+            owner,
+            storage_class: "STANDARD",
+          })
+        }
+      })
+      .collect(),
+    common_prefixes: page
+      .common_prefixes
+      .iter()
+      .map(|prefix| CommonPrefix {
+        prefix: encode(prefix),
+      })
+      .collect(),
+  };
+  Ok(xml_response("ListVersionsResult", &result))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn versions_xml() {
+    let request: VersioningConfiguration = quick_xml::de::from_str(
+      r#"<VersioningConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/"><Status>Suspended</Status></VersioningConfiguration>"#,
+    )
+    .unwrap();
+    assert_eq!(request.status.as_deref(), Some("Suspended"));
+
+    let result = ListVersionsResult {
+      xmlns: S3_XMLNS,
+      name: "bkt".into(),
+      prefix: String::new(),
+      key_marker: String::new(),
+      version_id_marker: String::new(),
+      next_key_marker: None,
+      next_version_id_marker: None,
+      max_keys: 1000,
+      delimiter: None,
+      encoding_type: None,
+      is_truncated: false,
+      entries: vec![
+        VersionListEntry::DeleteMarker(DeleteMarkerEntry {
+          key: "a".into(),
+          version_id: "2".into(),
+          is_latest: true,
+          last_modified: String::new(),
+          owner: Owner::new("alice"),
+        }),
+        VersionListEntry::Version(ObjectVersion {
+          key: "a".into(),
+          version_id: "1".into(),
+          is_latest: false,
+          last_modified: String::new(),
+          etag: "\"e\"".into(),
+          size: 1,
+          owner: Owner::new("alice"),
+          storage_class: "STANDARD",
+        }),
+      ],
+      common_prefixes: Vec::new(),
+    };
+    let xml = quick_xml::se::to_string_with_root("ListVersionsResult", &result).unwrap();
+    let marker = xml.find("<DeleteMarker><Key>a</Key><VersionId>2</VersionId>");
+    let version = xml.find("<Version><Key>a</Key><VersionId>1</VersionId>");
+    assert!(
+      marker.is_some() && version.is_some() && marker < version,
+      "{xml}"
+    );
+    assert!(xml.contains("<IsTruncated>false</IsTruncated>"));
+  }
+}
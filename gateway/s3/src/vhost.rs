@@ -0,0 +1,110 @@
+//! 虚拟主机风格（`bucket.s3.example.local/key`）的请求在路由前改写为路径风格（`/bucket/key`）。
+//! 改写前的 URI 保存在 `OriginalUri` 扩展中，SigV4 按客户端实际请求的路径计算签名。
+use axum::extract::{OriginalUri, Request, State};
+use axum::http::uri::PathAndQuery;
+use axum::http::{Uri, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::sync::Arc;
+
+/// 虚拟主机风格访问的基础域名
+#[derive(Clone, Debug, Default)]
+pub struct VirtualHosts {
+  domains: Arc<[String]>,
+}
+
+impl VirtualHosts {
+  pub fn new(domains: &[String]) -> Self {
+    let domains = domains
+      .iter()
+      .map(|domain| domain.trim().trim_matches('.').to_ascii_lowercase())
+      .filter(|domain| !domain.is_empty())
+      .collect();
+    Self { domains }
+  }
+
+  /// 按 Host 取 bucket 名；Host 正好是基础域名或不属于任何基础域名时为路径风格，返回 None
+  pub fn bucket<'a>(&self, host: &'a str) -> Option<&'a str> {
+    let host = host.split(':').next().unwrap_or_default();
+    if self
+      .domains
+      .iter()
+      .any(|domain| host.eq_ignore_ascii_case(domain))
+    {
+      return None;
+    }
+    self
+      .domains
+      .iter()
+      .filter_map(|domain| {
+        let bucket = host.get(..host.len().checked_sub(domain.len() + 1)?)?;
+        let suffix = &host[bucket.len()..];
+        (suffix.starts_with('.') && suffix[1..].eq_ignore_ascii_case(domain)).then_some(bucket)
+      })
+      // 基础域名互相嵌套时按最长的匹配
+      .min_by_key(|bucket| bucket.len())
+      .filter(|bucket| !bucket.is_empty())
+  }
+}
+
+fn rewrite_uri(uri: &Uri, bucket: &str) -> Option<Uri> {
+  let path = match uri.path() {
+    "" | "/" => String::new(),
+    path => path.to_string(),
+  };
+  let path_and_query = match uri.query() {
+    Some(query) => format!("/{bucket}{path}?{query}"),
+    None => format!("/{bucket}{path}"),
+  };
+  let mut parts = uri.clone().into_parts();
+  parts.path_and_query = Some(PathAndQuery::try_from(path_and_query).ok()?);
+  Uri::from_parts(parts).ok()
+}
+
+/// 改写层：包在整个 Router 外面，路由匹配之前执行
+pub async fn virtual_host(
+  State(hosts): State<VirtualHosts>,
+  mut req: Request,
+  next: Next,
+) -> Response {
+  let original = req.uri().clone();
+  let host = req
+    .headers()
+    .get(header::HOST)
+    .and_then(|value| value.to_str().ok())
+    .or_else(|| original.authority().map(|authority| authority.as_str()));
+  if let Some(bucket) = host.and_then(|host| hosts.bucket(host))
+    && let Some(uri) = rewrite_uri(&original, bucket)
+  {
+    *req.uri_mut() = uri;
+  }
+  req.extensions_mut().insert(OriginalUri(original));
+  next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bucket_from_host() {
+    let hosts = VirtualHosts::new(&["s3.example.local".into(), "example.local".into()]);
+    assert_eq!(hosts.bucket("photos.s3.example.local"), Some("photos"));
+    assert_eq!(
+      hosts.bucket("my.logs.S3.Example.local:3000"),
+      Some("my.logs")
+    );
+    assert_eq!(hosts.bucket("photos.example.local"), Some("photos"));
+    assert_eq!(hosts.bucket("s3.example.local"), None);
+    assert_eq!(hosts.bucket("127.0.0.1:3000"), None);
+    assert_eq!(hosts.bucket("photos.example.com"), None);
+
+    let uri: Uri = "/a/b.txt?tagging".parse().unwrap();
+    assert_eq!(
+      rewrite_uri(&uri, "photos").unwrap(),
+      "/photos/a/b.txt?tagging"
+    );
+    let uri: Uri = "/?list-type=2".parse().unwrap();
+    assert_eq!(rewrite_uri(&uri, "photos").unwrap(), "/photos?list-type=2");
+  }
+}
@@ -1,35 +1,384 @@
-use anyhow::{Result, anyhow};
-use redb::{Database, ReadableTable, TableDefinition};
-use std::path::Path;
-const BUCKETS_META: TableDefinition<&str, &[u8]> = TableDefinition::new("__buckets_meta__");
+use crate::error::StorageError;
+use crate::metadata::acl::AccessControlList;
+use crate::metadata::bucket_meta::BucketMeta;
+use crate::metadata::config::{BucketConfig, VersioningStatus};
+use crate::metadata::cors::CorsConfiguration;
+use crate::metadata::encryption::SseAlgorithm;
+use crate::metadata::lifecycle::LifecycleConfiguration;
+use crate::metadata::object_lock::ObjectLockConfiguration;
+use crate::metadata::policy::BucketPolicy;
+use crate::metadata::tagging::{MAX_BUCKET_TAGS, Tag, validate_tags};
+use crate::metadata::{BUCKET_TABLE, VERSION_TABLE};
+use anyhow::Result;
+use redb::{Database, ReadableTable};
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use uuid::Uuid;
 
-struct BucketManager {
-    db: Database,
+pub struct BucketManager {
+  db: Arc<Database>,
+}
+
+/// 创建 bucket 时一并写入的设置，与 BucketMeta 在同一个事务中提交
+#[derive(Debug, Clone, Default)]
+pub struct CreateBucketOptions {
+  /// None 表示 owner 私有
+  pub acl: Option<AccessControlList>,
+  pub dedup: bool,
+  /// 开启 Object Lock，同时开启版本控制；只能在创建时开启
+  pub object_lock: bool,
 }
 
 impl BucketManager {
-    pub fn open(path: &Path) -> Result<Self> {
-        let db = Database::open(path)?;
-        Ok(Self { db })
+  pub fn new(db: Arc<Database>) -> Self {
+    Self { db }
+  }
+
+  pub fn create_bucket(
+    &self,
+    bucket_name: &str,
+    owner: &str,
+    options: CreateBucketOptions,
+  ) -> Result<BucketMeta> {
+    validate_bucket_name(bucket_name)?;
+    let bucket = BucketMeta {
+      id: Uuid::now_v7().to_string(),
+      name: bucket_name.to_string(),
+      created_at: chrono::Utc::now().timestamp(),
+      owner: owner.to_string(),
+      policy: None,
+      config: BucketConfig {
+        versioning: match options.object_lock {
+          true => VersioningStatus::Enabled,
+          false => VersioningStatus::Unversioned,
+        },
+        dedup: options.dedup,
+        lifecycle: None,
+        encryption: None,
+        object_lock: options.object_lock.then(ObjectLockConfiguration::default),
+      },
+      tags: Vec::new(),
+      acl: options
+        .acl
+        .unwrap_or_else(|| AccessControlList::private(owner)),
+      cors: None,
+    };
+
+    let write_txn = self.db.begin_write()?; // mutable txn
+    {
+      let mut meta = write_txn.open_table(BUCKET_TABLE)?;
+      if let Some(existing) = meta.get(bucket_name)? {
+        let bucket = bucket_name.to_string();
+        return Err(if existing.value().owner == owner {
+          StorageError::BucketAlreadyOwnedByYou { bucket }.into()
+        } else {
+          StorageError::BucketAlreadyExists { bucket }.into()
+        });
+      }
+      meta.insert(bucket_name, &bucket)?;
+    }
+
+    write_txn.commit()?; // 这里提交
+    Ok(bucket)
+  }
+
+  pub fn bucket_exists(&self, bucket_name: &str) -> Result<bool> {
+    let read_txn = self.db.begin_read()?;
+    let meta = read_txn.open_table(BUCKET_TABLE)?;
+    Ok(meta.get(bucket_name)?.is_some())
+  }
+
+  pub fn get_bucket(&self, bucket_name: &str) -> Result<BucketMeta> {
+    let read_txn = self.db.begin_read()?;
+    let meta = read_txn.open_table(BUCKET_TABLE)?;
+    match meta.get(bucket_name)? {
+      Some(bucket) => Ok(bucket.value()),
+      None => Err(no_such_bucket(bucket_name)),
     }
+  }
 
-    pub fn create_bucket(&self, bucket_name: &str) -> Result<()> {
-        let write_txn = self.db.begin_write()?; // mutable txn
-        {
-            let mut meta = write_txn.open_table(BUCKETS_META)?;
-            if meta.get(bucket_name)?.is_some() {
-                return Err(anyhow!("Bucket already exists"));
-            }
-            meta.insert(bucket_name, &b"{}"[..])?;
-        }
+  /// 按名称排序返回全部 bucket
+  pub fn list_buckets(&self) -> Result<Vec<BucketMeta>> {
+    let read_txn = self.db.begin_read()?;
+    let meta = read_txn.open_table(BUCKET_TABLE)?;
+    let mut buckets = Vec::new();
+    for entry in meta.iter()? {
+      let (_, bucket) = entry?;
+      buckets.push(bucket.value());
+    }
+    Ok(buckets)
+  }
 
-        write_txn.commit()?; // 这里提交
-        Ok(())
+  /// 替换 bucket 的全部标签，空列表即删除标签
+  pub fn put_bucket_tagging(&self, bucket_name: &str, tags: Vec<Tag>) -> Result<()> {
+    validate_tags(&tags, MAX_BUCKET_TAGS)?;
+    let write_txn = self.db.begin_write()?;
+    {
+      let mut meta = write_txn.open_table(BUCKET_TABLE)?;
+      let mut bucket = match meta.get(bucket_name)? {
+        Some(bucket) => bucket.value(),
+        None => return Err(no_such_bucket(bucket_name)),
+      };
+      bucket.tags = tags;
+      meta.insert(bucket_name, &bucket)?;
     }
+    write_txn.commit()?;
+    Ok(())
+  }
 
-    pub fn bucket_exists(&self, bucket_name: &str) -> Result<bool> {
-        let read_txn = self.db.begin_read()?;
-        let meta = read_txn.open_table(BUCKETS_META)?;
-        Ok(meta.get(bucket_name)?.is_some())
+  /// 替换 bucket 的 ACL，owner 保持不变
+  pub fn put_bucket_acl(&self, bucket_name: &str, acl: AccessControlList) -> Result<()> {
+    let write_txn = self.db.begin_write()?;
+    {
+      let mut meta = write_txn.open_table(BUCKET_TABLE)?;
+      let mut bucket = match meta.get(bucket_name)? {
+        Some(bucket) => bucket.value(),
+        None => return Err(no_such_bucket(bucket_name)),
+      };
+      bucket.acl = acl;
+      meta.insert(bucket_name, &bucket)?;
+    }
+    write_txn.commit()?;
+    Ok(())
+  }
+
+  /// 设置或删除（`None`）CORS 配置
+  pub fn put_bucket_cors(&self, bucket_name: &str, cors: Option<CorsConfiguration>) -> Result<()> {
+    if let Some(cors) = &cors {
+      cors.validate()?;
+    }
+    let write_txn = self.db.begin_write()?;
+    {
+      let mut meta = write_txn.open_table(BUCKET_TABLE)?;
+      let mut bucket = match meta.get(bucket_name)? {
+        Some(bucket) => bucket.value(),
+        None => return Err(no_such_bucket(bucket_name)),
+      };
+      bucket.cors = cors;
+      meta.insert(bucket_name, &bucket)?;
+    }
+    write_txn.commit()?;
+    Ok(())
+  }
+
+  /// 开启或暂停版本控制；暂停后已有的版本保持不变，新写入的对象为 null 版本。开启 Object Lock 后不能暂停
+  pub fn put_bucket_versioning(&self, bucket_name: &str, status: VersioningStatus) -> Result<()> {
+    let write_txn = self.db.begin_write()?;
+    {
+      let mut meta = write_txn.open_table(BUCKET_TABLE)?;
+      let mut bucket = match meta.get(bucket_name)? {
+        Some(bucket) => bucket.value(),
+        None => return Err(no_such_bucket(bucket_name)),
+      };
+      if bucket.config.object_lock.is_some() && status != VersioningStatus::Enabled {
+        return Err(
+          StorageError::InvalidBucketState {
+            reason: "An Object Lock configuration is present on this bucket, so the versioning state cannot be changed.",
+          }
+          .into(),
+        );
+      }
+      bucket.config.versioning = status;
+      meta.insert(bucket_name, &bucket)?;
+    }
+    write_txn.commit()?;
+    Ok(())
+  }
+
+  /// 开启或关闭去重；只影响之后写入的对象，已有对象的数据保持原样
+  pub fn put_bucket_dedup(&self, bucket_name: &str, dedup: bool) -> Result<()> {
+    let write_txn = self.db.begin_write()?;
+    {
+      let mut meta = write_txn.open_table(BUCKET_TABLE)?;
+      let mut bucket = match meta.get(bucket_name)? {
+        Some(bucket) => bucket.value(),
+        None => return Err(no_such_bucket(bucket_name)),
+      };
+      bucket.config.dedup = dedup;
+      meta.insert(bucket_name, &bucket)?;
+    }
+    write_txn.commit()?;
+    Ok(())
+  }
+
+  /// 设置或删除（`None`）生命周期配置
+  pub fn put_bucket_lifecycle(
+    &self,
+    bucket_name: &str,
+    lifecycle: Option<LifecycleConfiguration>,
+  ) -> Result<()> {
+    if let Some(lifecycle) = &lifecycle {
+      lifecycle.validate()?;
+    }
+    let write_txn = self.db.begin_write()?;
+    {
+      let mut meta = write_txn.open_table(BUCKET_TABLE)?;
+      let mut bucket = match meta.get(bucket_name)? {
+        Some(bucket) => bucket.value(),
+        None => return Err(no_such_bucket(bucket_name)),
+      };
+      bucket.config.lifecycle = lifecycle;
+      meta.insert(bucket_name, &bucket)?;
+    }
+    write_txn.commit()?;
+    Ok(())
+  }
+
+  /// 设置或删除（`None`）默认加密；只影响之后写入的对象
+  pub fn put_bucket_encryption(
+    &self,
+    bucket_name: &str,
+    encryption: Option<SseAlgorithm>,
+  ) -> Result<()> {
+    let write_txn = self.db.begin_write()?;
+    {
+      let mut meta = write_txn.open_table(BUCKET_TABLE)?;
+      let mut bucket = match meta.get(bucket_name)? {
+        Some(bucket) => bucket.value(),
+        None => return Err(no_such_bucket(bucket_name)),
+      };
+      bucket.config.encryption = encryption;
+      meta.insert(bucket_name, &bucket)?;
+    }
+    write_txn.commit()?;
+    Ok(())
+  }
+
+  /// 替换 Object Lock 配置（默认保留）；只能用于创建时已开启 Object Lock 的 bucket
+  pub fn put_object_lock_configuration(
+    &self,
+    bucket_name: &str,
+    config: ObjectLockConfiguration,
+  ) -> Result<()> {
+    config.validate()?;
+    let write_txn = self.db.begin_write()?;
+    {
+      let mut meta = write_txn.open_table(BUCKET_TABLE)?;
+      let mut bucket = match meta.get(bucket_name)? {
+        Some(bucket) => bucket.value(),
+        None => return Err(no_such_bucket(bucket_name)),
+      };
+      if bucket.config.object_lock.is_none() {
+        return Err(
+          StorageError::InvalidBucketState {
+            reason: "Object Lock configuration cannot be enabled on existing buckets",
+          }
+          .into(),
+        );
+      }
+      bucket.config.object_lock = Some(config);
+      meta.insert(bucket_name, &bucket)?;
+    }
+    write_txn.commit()?;
+    Ok(())
+  }
+
+  /// 设置或删除（`None`）bucket 策略，策略需先经 `policy::parse_policy` 校验
+  pub fn put_bucket_policy(&self, bucket_name: &str, policy: Option<BucketPolicy>) -> Result<()> {
+    let write_txn = self.db.begin_write()?;
+    {
+      let mut meta = write_txn.open_table(BUCKET_TABLE)?;
+      let mut bucket = match meta.get(bucket_name)? {
+        Some(bucket) => bucket.value(),
+        None => return Err(no_such_bucket(bucket_name)),
+      };
+      bucket.policy = policy;
+      meta.insert(bucket_name, &bucket)?;
+    }
+    write_txn.commit()?;
+    Ok(())
+  }
+
+  /// 只允许删除空 bucket（包括历史版本和删除标记），检查与删除在同一个写事务中完成
+  pub fn delete_bucket(&self, bucket_name: &str) -> Result<()> {
+    let write_txn = self.db.begin_write()?;
+    {
+      let versions = write_txn.open_table(VERSION_TABLE)?;
+      let mut meta = write_txn.open_table(BUCKET_TABLE)?;
+      if meta.get(bucket_name)?.is_none() {
+        return Err(no_such_bucket(bucket_name));
+      }
+      let first = versions
+        .range((bucket_name, "", "")..)?
+        .next()
+        .transpose()?;
+      if first.is_some_and(|(key, _)| key.value().0 == bucket_name) {
+        return Err(
+          StorageError::BucketNotEmpty {
+            bucket: bucket_name.to_string(),
+          }
+          .into(),
+        );
+      }
+      meta.remove(bucket_name)?;
+    }
+    write_txn.commit()?;
+    Ok(())
+  }
+}
+
+pub(crate) fn no_such_bucket(bucket_name: &str) -> anyhow::Error {
+  StorageError::NoSuchBucket {
+    bucket: bucket_name.to_string(),
+  }
+  .into()
+}
+
+/// S3 通用 bucket 命名规则
+pub fn validate_bucket_name(name: &str) -> Result<(), StorageError> {
+  let reason = if name.len() < 3 || name.len() > 63 {
+    Some("bucket name must be between 3 and 63 characters long")
+  } else if !name
+    .bytes()
+    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'.' || b == b'-')
+  {
+    Some("bucket name can only contain lowercase letters, numbers, dots and hyphens")
+  } else if !name.starts_with(|c: char| c.is_ascii_alphanumeric())
+    || !name.ends_with(|c: char| c.is_ascii_alphanumeric())
+  {
+    Some("bucket name must begin and end with a letter or number")
+  } else if name.contains("..") {
+    Some("bucket name must not contain two adjacent periods")
+  } else if name.parse::<Ipv4Addr>().is_ok() {
+    Some("bucket name must not be formatted as an IP address")
+  } else if name.starts_with("xn--") || name.starts_with("sthree-") {
+    Some("bucket name must not start with a reserved prefix")
+  } else if name.ends_with("-s3alias") || name.ends_with("--ol-s3") {
+    Some("bucket name must not end with a reserved suffix")
+  } else {
+    None
+  };
+
+  match reason {
+    Some(reason) => Err(StorageError::InvalidBucketName {
+      bucket: name.to_string(),
+      reason,
+    }),
+    None => Ok(()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::validate_bucket_name;
+
+  #[test]
+  fn bucket_name_rules() {
+    for name in ["my-bucket", "logs.2024", "abc", "a1-b2.c3"] {
+      assert!(validate_bucket_name(name).is_ok(), "{name}");
+    }
+    for name in [
+      "ab",
+      "My-Bucket",
+      "-bucket",
+      "bucket-",
+      "a..b",
+      "192.168.1.1",
+      "xn--bucket",
+      "bucket-s3alias",
+      "under_score",
+    ] {
+      assert!(validate_bucket_name(name).is_err(), "{name}");
     }
+  }
 }
@@ -0,0 +1,310 @@
+use crate::error::StorageError;
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bincode::{Decode, Encode};
+use md5::Digest;
+use sha1::Sha1;
+use sha2::Sha256;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// S3 的附加校验算法（x-amz-checksum-*），值以 base64 编码传输
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Encode, Decode,
+)]
+pub enum ChecksumAlgorithm {
+  Crc32,
+  Crc32c,
+  Sha1,
+  Sha256,
+}
+
+impl ChecksumAlgorithm {
+  pub const ALL: [ChecksumAlgorithm; 4] = [Self::Crc32, Self::Crc32c, Self::Sha1, Self::Sha256];
+
+  /// 解析 `x-amz-sdk-checksum-algorithm` 等头中的算法名，如 `CRC32`
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL
+      .into_iter()
+      .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name.trim()))
+  }
+
+  /// 解析 `x-amz-checksum-crc32` 形式的头名
+  pub fn from_header_name(name: &str) -> Option<Self> {
+    Self::ALL
+      .into_iter()
+      .find(|algorithm| algorithm.header_name().eq_ignore_ascii_case(name.trim()))
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Crc32 => "CRC32",
+      Self::Crc32c => "CRC32C",
+      Self::Sha1 => "SHA1",
+      Self::Sha256 => "SHA256",
+    }
+  }
+
+  pub fn header_name(&self) -> &'static str {
+    match self {
+      Self::Crc32 => "x-amz-checksum-crc32",
+      Self::Crc32c => "x-amz-checksum-crc32c",
+      Self::Sha1 => "x-amz-checksum-sha1",
+      Self::Sha256 => "x-amz-checksum-sha256",
+    }
+  }
+
+  /// 摘要的字节长度
+  pub fn digest_len(&self) -> usize {
+    match self {
+      Self::Crc32 | Self::Crc32c => 4,
+      Self::Sha1 => 20,
+      Self::Sha256 => 32,
+    }
+  }
+
+  /// CRC 可以由各分片的校验值合并出整个对象的校验值（FULL_OBJECT）
+  pub fn combinable(&self) -> bool {
+    matches!(self, Self::Crc32 | Self::Crc32c)
+  }
+
+  pub fn hasher(&self) -> Checksum {
+    match self {
+      Self::Crc32 => Checksum::Crc32(crc32fast::Hasher::new()),
+      Self::Crc32c => Checksum::Crc32c(0),
+      Self::Sha1 => Checksum::Sha1(Sha1::new()),
+      Self::Sha256 => Checksum::Sha256(Sha256::new()),
+    }
+  }
+}
+
+/// 增量计算的校验值
+#[derive(Clone)]
+pub enum Checksum {
+  Crc32(crc32fast::Hasher),
+  Crc32c(u32),
+  Sha1(Sha1),
+  Sha256(Sha256),
+}
+
+impl Checksum {
+  pub fn update(&mut self, data: &[u8]) {
+    match self {
+      Checksum::Crc32(hasher) => hasher.update(data),
+      Checksum::Crc32c(crc) => *crc = crc32c::crc32c_append(*crc, data),
+      Checksum::Sha1(hasher) => hasher.update(data),
+      Checksum::Sha256(hasher) => hasher.update(data),
+    }
+  }
+
+  /// 原始摘要字节（CRC 为大端序）
+  pub fn finalize_bytes(self) -> Vec<u8> {
+    match self {
+      Checksum::Crc32(hasher) => hasher.finalize().to_be_bytes().to_vec(),
+      Checksum::Crc32c(crc) => crc.to_be_bytes().to_vec(),
+      Checksum::Sha1(hasher) => hasher.finalize().to_vec(),
+      Checksum::Sha256(hasher) => hasher.finalize().to_vec(),
+    }
+  }
+
+  /// base64 编码的摘要，即 x-amz-checksum-* 头的值
+  pub fn finalize(self) -> String {
+    STANDARD.encode(self.finalize_bytes())
+  }
+}
+
+/// 分片上传对象的校验值类型，即 `x-amz-checksum-type`
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Encode, Decode,
+)]
+pub enum ChecksumType {
+  /// 整个对象数据的校验值
+  FullObject,
+  /// 各分片摘要拼接后再计算一次，值带 `-<分片数>` 后缀
+  Composite,
+}
+
+impl ChecksumType {
+  pub fn from_name(name: &str) -> Option<Self> {
+    [Self::FullObject, Self::Composite]
+      .into_iter()
+      .find(|checksum_type| checksum_type.name().eq_ignore_ascii_case(name.trim()))
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::FullObject => "FULL_OBJECT",
+      Self::Composite => "COMPOSITE",
+    }
+  }
+}
+
+/// 随对象保存的附加校验值
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct ObjectChecksum {
+  pub algorithm: ChecksumAlgorithm,
+  pub checksum_type: ChecksumType,
+  pub value: String, // base64
+}
+
+/// 写入请求的完整性校验：Content-MD5，以及要计算并保存的附加校验算法
+#[derive(Debug, Clone, Default)]
+pub struct IntegrityCheck {
+  pub content_md5: Option<[u8; 16]>,
+  pub algorithm: Option<ChecksumAlgorithm>,
+  /// 请求头中给出的校验值；尾部校验值在解码请求体时已经验证，这里为 None
+  pub expected: Option<String>,
+}
+
+impl IntegrityCheck {
+  /// 比对写入数据的 MD5 和附加校验值
+  pub fn verify(&self, md5: &[u8; 16], checksum: Option<&str>) -> Result<(), StorageError> {
+    if self.content_md5.is_some_and(|expected| expected != *md5) {
+      return Err(StorageError::BadDigest {
+        name: "Content-MD5",
+      });
+    }
+    if let (Some(algorithm), Some(expected)) = (self.algorithm, &self.expected)
+      && checksum != Some(expected.as_str())
+    {
+      return Err(StorageError::BadDigest {
+        name: algorithm.name(),
+      });
+    }
+    Ok(())
+  }
+}
+
+/// 边读边计算附加校验值，未指定算法时直接透传
+pub struct ChecksumReader<R> {
+  inner: R,
+  checksum: Option<Checksum>,
+}
+
+impl<R: AsyncRead + Unpin> ChecksumReader<R> {
+  pub fn new(inner: R, algorithm: Option<ChecksumAlgorithm>) -> Self {
+    Self {
+      inner,
+      checksum: algorithm.map(|algorithm| algorithm.hasher()),
+    }
+  }
+
+  /// base64 编码的校验值，在读取完成后调用
+  pub fn finish(self) -> Option<String> {
+    self.checksum.map(Checksum::finalize)
+  }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ChecksumReader<R> {
+  fn poll_read(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+  ) -> Poll<io::Result<()>> {
+    let this = &mut *self;
+    let filled = buf.filled().len();
+    ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+    if let Some(checksum) = &mut this.checksum {
+      checksum.update(&buf.filled()[filled..]);
+    }
+    Poll::Ready(Ok(()))
+  }
+}
+
+/// 由各分片的 (校验值, 大小) 计算分片上传对象的校验值
+pub fn multipart_checksum(
+  algorithm: ChecksumAlgorithm,
+  checksum_type: ChecksumType,
+  parts: &[(&str, u64)],
+) -> Result<String> {
+  let mut digests = Vec::with_capacity(parts.len());
+  for (value, _) in parts {
+    let digest = STANDARD.decode(value)?;
+    if digest.len() != algorithm.digest_len() {
+      return Err(anyhow!("invalid {} part checksum", algorithm.name()));
+    }
+    digests.push(digest);
+  }
+  let crc = |digest: &[u8]| u32::from_be_bytes(digest.try_into().unwrap_or_default());
+  match (checksum_type, algorithm) {
+    (ChecksumType::Composite, _) => {
+      let mut checksum = algorithm.hasher();
+      for digest in &digests {
+        checksum.update(digest);
+      }
+      Ok(format!("{}-{}", checksum.finalize(), parts.len()))
+    }
+    (ChecksumType::FullObject, ChecksumAlgorithm::Crc32) => {
+      let mut hasher = crc32fast::Hasher::new();
+      for (digest, (_, size)) in digests.iter().zip(parts) {
+        hasher.combine(&crc32fast::Hasher::new_with_initial_len(crc(digest), *size));
+      }
+      Ok(STANDARD.encode(hasher.finalize().to_be_bytes()))
+    }
+    (ChecksumType::FullObject, ChecksumAlgorithm::Crc32c) => {
+      let combined = digests
+        .iter()
+        .zip(parts)
+        .fold(0, |acc, (digest, (_, size))| {
+          crc32c::crc32c_combine(acc, crc(digest), *size as usize)
+        });
+      Ok(STANDARD.encode(combined.to_be_bytes()))
+    }
+    (ChecksumType::FullObject, _) => Err(anyhow!(
+      "{} does not support full object checksums",
+      algorithm.name()
+    )),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{ChecksumAlgorithm, ChecksumType, multipart_checksum};
+
+  #[test]
+  fn known_values() {
+    let digest = |algorithm: ChecksumAlgorithm| {
+      let mut checksum = algorithm.hasher();
+      checksum.update(b"hello ");
+      checksum.update(b"world");
+      checksum.finalize()
+    };
+    assert_eq!(digest(ChecksumAlgorithm::Crc32), "DUoRhQ==");
+    assert_eq!(digest(ChecksumAlgorithm::Crc32c), "yZRlqg==");
+    assert_eq!(
+      digest(ChecksumAlgorithm::Sha1),
+      "Kq5sNclPz7QV2+lfQIuc6R7oRu0="
+    );
+    assert_eq!(
+      ChecksumAlgorithm::from_header_name("X-Amz-Checksum-Crc32c"),
+      Some(ChecksumAlgorithm::Crc32c)
+    );
+  }
+
+  #[test]
+  fn multipart_checksums() {
+    let digest = |algorithm: ChecksumAlgorithm, data: &[u8]| {
+      let mut checksum = algorithm.hasher();
+      checksum.update(data);
+      checksum.finalize()
+    };
+    for algorithm in [ChecksumAlgorithm::Crc32, ChecksumAlgorithm::Crc32c] {
+      let (a, b) = (digest(algorithm, b"hello "), digest(algorithm, b"world"));
+      let parts = [(a.as_str(), 6), (b.as_str(), 5)];
+      assert_eq!(
+        multipart_checksum(algorithm, ChecksumType::FullObject, &parts).unwrap(),
+        digest(algorithm, b"hello world")
+      );
+    }
+    let part = digest(ChecksumAlgorithm::Sha256, b"data");
+    let parts = [(part.as_str(), 4), (part.as_str(), 4)];
+    let composite = multipart_checksum(ChecksumAlgorithm::Sha256, ChecksumType::Composite, &parts);
+    assert!(composite.unwrap().ends_with("-2"));
+    assert!(
+      multipart_checksum(ChecksumAlgorithm::Sha256, ChecksumType::FullObject, &parts).is_err()
+    );
+  }
+}
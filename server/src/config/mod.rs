@@ -4,9 +4,10 @@ use std::path::PathBuf;
 use std::time::Duration;
 
 /// 存储引擎类型
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub enum StorageEngine {
   /// 简单文件系统存储（开发用）
+  #[default]
   SimpleFs,
   /// 日志结构合并存储（生产推荐）
   Lsm,
@@ -16,6 +17,7 @@ pub enum StorageEngine {
 
 /// 纠删码配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ErasureCodingConfig {
   /// 数据分片数量
   pub data_shards: usize,
@@ -29,6 +31,7 @@ pub struct ErasureCodingConfig {
 
 /// 事务配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TransactionConfig {
   /// 事务超时时间
   pub timeout: Duration,
@@ -43,16 +46,18 @@ pub struct TransactionConfig {
 }
 
 /// 事务隔离级别
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub enum IsolationLevel {
   ReadUncommitted,
   ReadCommitted,
   RepeatableRead,
+  #[default]
   Serializable,
 }
 
 /// 缓存配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CacheConfig {
   /// 元数据缓存大小（字节）
   pub metadata_cache_size: usize,
@@ -66,6 +71,7 @@ pub struct CacheConfig {
 
 /// 集群配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ClusterConfig {
   /// 当前节点ID
   pub node_id: String,
@@ -80,7 +86,8 @@ pub struct ClusterConfig {
 }
 
 /// 安全配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SecurityConfig {
   /// 是否启用TLS
   pub enable_tls: bool,
@@ -94,10 +101,13 @@ pub struct SecurityConfig {
   pub secret_key: Option<String>,
   /// 是否启用匿名访问
   pub allow_anonymous: bool,
+  /// SSE-S3 主密钥（base64 编码的 32 字节），用于加密每个对象的数据密钥；未配置时不支持 SSE-S3
+  pub master_key: Option<String>,
 }
 
 /// 性能调优配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PerformanceConfig {
   /// IO线程数
   pub io_threads: usize,
@@ -115,6 +125,7 @@ pub struct PerformanceConfig {
 
 /// 日志配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LogConfig {
   /// 日志级别
   pub level: String,
@@ -128,6 +139,7 @@ pub struct LogConfig {
 
 /// 监控配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MonitoringConfig {
   /// Prometheus指标端口
   pub metrics_port: u16,
@@ -139,6 +151,7 @@ pub struct MonitoringConfig {
 
 /// 搜索服务配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SearchConfig {
   /// 是否启用内容搜索
   pub enable_content_search: bool,
@@ -152,6 +165,7 @@ pub struct SearchConfig {
 
 /// 主服务配置结构体
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ServiceConfig {
   /// 服务名称
   pub service_name: String,
@@ -186,3 +200,119 @@ pub struct ServiceConfig {
   /// 搜索配置
   pub search: SearchConfig,
 }
+
+const MIB: u64 = 1024 * 1024;
+
+impl Default for ErasureCodingConfig {
+  fn default() -> Self {
+    Self {
+      data_shards: 4,
+      parity_shards: 2,
+      min_shard_size: MIB,
+      max_shard_size: 64 * MIB,
+    }
+  }
+}
+
+impl Default for TransactionConfig {
+  fn default() -> Self {
+    Self {
+      timeout: Duration::from_secs(30),
+      enable_2pc: false,
+      wal_path: PathBuf::from("data/wal"),
+      max_wal_size: 64 * MIB,
+      isolation_level: IsolationLevel::default(),
+    }
+  }
+}
+
+impl Default for CacheConfig {
+  fn default() -> Self {
+    Self {
+      metadata_cache_size: 64 * MIB as usize,
+      data_block_cache_size: 256 * MIB as usize,
+      merge_buffer_size: 4 * MIB as usize,
+      merge_timeout: Duration::from_secs(1),
+    }
+  }
+}
+
+impl Default for ClusterConfig {
+  fn default() -> Self {
+    Self {
+      node_id: "node-1".to_string(),
+      members: Vec::new(),
+      coordinator: None,
+      discovery_interval: Duration::from_secs(10),
+      heartbeat_timeout: Duration::from_secs(30),
+    }
+  }
+}
+
+impl Default for PerformanceConfig {
+  fn default() -> Self {
+    Self {
+      io_threads: 4,
+      worker_threads: 8,
+      max_connections: 1024,
+      max_body_size: 5 * 1024 * MIB as usize,
+      enable_io_uring: false,
+      numa_node: None,
+    }
+  }
+}
+
+impl Default for LogConfig {
+  fn default() -> Self {
+    Self {
+      level: "info".to_string(),
+      file_path: None,
+      max_file_size: 100 * MIB,
+      max_files: 10,
+    }
+  }
+}
+
+impl Default for MonitoringConfig {
+  fn default() -> Self {
+    Self {
+      metrics_port: 9090,
+      health_check_endpoint: "/health".to_string(),
+      profiling_interval: Duration::from_secs(60),
+    }
+  }
+}
+
+impl Default for SearchConfig {
+  fn default() -> Self {
+    Self {
+      enable_content_search: false,
+      index_path: PathBuf::from("data/index"),
+      index_refresh_interval: Duration::from_secs(60),
+      supported_extensions: Vec::new(),
+    }
+  }
+}
+
+impl Default for ServiceConfig {
+  fn default() -> Self {
+    Self {
+      service_name: "maxio".to_string(),
+      bind_address: IpAddr::from([0, 0, 0, 0]),
+      http_port: 3000,
+      rpc_port: 3001,
+      data_root: PathBuf::from("data"),
+      temp_dir: PathBuf::from("data/tmp"),
+      storage_engine: StorageEngine::default(),
+      erasure_coding: ErasureCodingConfig::default(),
+      transaction: TransactionConfig::default(),
+      cache: CacheConfig::default(),
+      cluster: ClusterConfig::default(),
+      security: SecurityConfig::default(),
+      performance: PerformanceConfig::default(),
+      log: LogConfig::default(),
+      monitoring: MonitoringConfig::default(),
+      search: SearchConfig::default(),
+    }
+  }
+}
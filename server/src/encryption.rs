@@ -0,0 +1,238 @@
+//! 服务端加密（SSE-S3 / SSE-C）。每段数据（普通对象或一个分片）使用随机生成的 AES-256 数据密钥，
+//! 数据密钥以 AES-256-GCM 用主密钥或客户密钥加密后随元数据保存。
+//! 数据按 BLOCK_SIZE 切块，每块独立以 AES-256-GCM 加密（nonce 为块序号），密文为各块密文与 tag 依次相连，
+//! range 读取时只需解密覆盖到的块。
+use aes_gcm::aead::AeadInPlace;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use md5::{Digest, Md5};
+use rand::Rng;
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// 每块明文的大小
+pub const BLOCK_SIZE: usize = 64 * 1024;
+/// 每块密文附加的 GCM tag 长度
+pub const TAG_SIZE: usize = 16;
+const KEY_SIZE: usize = 32;
+const NONCE_SIZE: usize = 12;
+
+/// 明文长度对应的密文长度
+pub fn cipher_len(plain_len: u64) -> u64 {
+  plain_len + plain_len.div_ceil(BLOCK_SIZE as u64) * TAG_SIZE as u64
+}
+
+fn block_nonce(block: u64) -> Nonce<aes_gcm::aead::consts::U12> {
+  let mut nonce = [0u8; NONCE_SIZE];
+  nonce[NONCE_SIZE - 8..].copy_from_slice(&block.to_be_bytes());
+  nonce.into()
+}
+
+/// 用于加密数据密钥的 256 位密钥：服务端主密钥或 SSE-C 的客户密钥
+#[derive(Clone)]
+pub struct WrappingKey([u8; KEY_SIZE]);
+
+impl fmt::Debug for WrappingKey {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("WrappingKey(..)")
+  }
+}
+
+/// 写入对象时请求的加密方式
+#[derive(Debug, Clone)]
+pub enum Encryption {
+  /// SSE-S3，使用服务端主密钥
+  S3,
+  /// SSE-C，使用请求中提供的客户密钥
+  Customer(WrappingKey),
+}
+
+impl WrappingKey {
+  /// 解析 base64 编码的 32 字节密钥
+  pub fn from_base64(value: &str) -> Result<Self> {
+    let bytes = STANDARD.decode(value.trim())?;
+    let key: [u8; KEY_SIZE] = bytes
+      .try_into()
+      .map_err(|_| anyhow!("encryption key must be {KEY_SIZE} bytes"))?;
+    Ok(Self(key))
+  }
+
+  /// 密钥的 MD5（base64），即 SSE-C 的 `x-amz-server-side-encryption-customer-key-MD5`
+  pub fn key_md5(&self) -> String {
+    STANDARD.encode(Md5::digest(self.0))
+  }
+
+  /// 生成新的数据密钥，返回数据密钥和加密后的数据密钥（nonce + 密文 + tag）
+  pub fn new_data_key(&self) -> Result<(DataKey, Vec<u8>)> {
+    let mut key = [0u8; KEY_SIZE];
+    let mut nonce = [0u8; NONCE_SIZE];
+    let mut rng = rand::rng();
+    rng.fill(&mut key);
+    rng.fill(&mut nonce);
+    let mut sealed = key.to_vec();
+    Aes256Gcm::new(&self.0.into())
+      .encrypt_in_place(&nonce.into(), b"", &mut sealed)
+      .map_err(|_| anyhow!("failed to seal data key"))?;
+    sealed.splice(0..0, nonce);
+    Ok((DataKey(key), sealed))
+  }
+
+  /// 解密数据密钥；密钥不匹配或数据损坏时失败
+  pub fn open_data_key(&self, sealed: &[u8]) -> Result<DataKey> {
+    if sealed.len() != NONCE_SIZE + KEY_SIZE + TAG_SIZE {
+      return Err(anyhow!("invalid sealed data key"));
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_SIZE);
+    let mut key = ciphertext.to_vec();
+    Aes256Gcm::new(&self.0.into())
+      .decrypt_in_place(Nonce::from_slice(nonce), b"", &mut key)
+      .map_err(|_| anyhow!("failed to open data key"))?;
+    let key: [u8; KEY_SIZE] = key
+      .try_into()
+      .map_err(|_| anyhow!("invalid data key length"))?;
+    Ok(DataKey(key))
+  }
+}
+
+/// 一段数据的数据密钥，只在内存中以明文存在
+#[derive(Clone)]
+pub struct DataKey([u8; KEY_SIZE]);
+
+impl DataKey {
+  pub(crate) fn cipher(&self) -> Aes256Gcm {
+    Aes256Gcm::new(&self.0.into())
+  }
+}
+
+/// 解密第 block 块的密文（含 tag），原地替换为明文
+pub(crate) fn decrypt_block(cipher: &Aes256Gcm, block: u64, data: &mut Vec<u8>) -> io::Result<()> {
+  cipher
+    .decrypt_in_place(&block_nonce(block), b"", data)
+    .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "failed to decrypt object data"))
+}
+
+/// 边读边加密：按 BLOCK_SIZE 切块加密，同时统计明文的大小和 MD5
+pub struct EncryptReader<R> {
+  inner: R,
+  cipher: Aes256Gcm,
+  block: u64,
+  buffer: Vec<u8>, // 未满一块的明文，加密后原地变为密文
+  pos: usize,      // 已输出的密文位置
+  sealed: bool,    // buffer 中是否为待输出的密文
+  eof: bool,
+  md5: Md5,
+  size: u64,
+}
+
+impl<R: AsyncRead + Unpin> EncryptReader<R> {
+  pub fn new(inner: R, key: &DataKey) -> Self {
+    Self {
+      inner,
+      cipher: key.cipher(),
+      block: 0,
+      buffer: Vec::with_capacity(BLOCK_SIZE + TAG_SIZE),
+      pos: 0,
+      sealed: false,
+      eof: false,
+      md5: Md5::new(),
+      size: 0,
+    }
+  }
+
+  /// 明文的大小和 MD5，在读取完成后调用
+  pub fn finish(self) -> (u64, [u8; 16]) {
+    (self.size, self.md5.finalize().into())
+  }
+
+  fn seal_block(&mut self) -> io::Result<()> {
+    self.md5.update(&self.buffer);
+    self.size += self.buffer.len() as u64;
+    self
+      .cipher
+      .encrypt_in_place(&block_nonce(self.block), b"", &mut self.buffer)
+      .map_err(|_| io::Error::other("failed to encrypt object data"))?;
+    self.block += 1;
+    self.pos = 0;
+    self.sealed = true;
+    Ok(())
+  }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for EncryptReader<R> {
+  fn poll_read(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+  ) -> Poll<io::Result<()>> {
+    let this = &mut *self;
+    loop {
+      if this.sealed {
+        if this.pos < this.buffer.len() {
+          let n = buf.remaining().min(this.buffer.len() - this.pos);
+          buf.put_slice(&this.buffer[this.pos..this.pos + n]);
+          this.pos += n;
+          return Poll::Ready(Ok(()));
+        }
+        this.buffer.clear();
+        this.sealed = false;
+      }
+      if this.eof {
+        return Poll::Ready(Ok(()));
+      }
+      // 读满一块明文
+      let filled = this.buffer.len();
+      this.buffer.resize(BLOCK_SIZE, 0);
+      let mut read_buf = ReadBuf::new(&mut this.buffer[filled..]);
+      let polled = Pin::new(&mut this.inner).poll_read(cx, &mut read_buf);
+      let n = read_buf.filled().len();
+      this.buffer.truncate(filled + n);
+      ready!(polled)?;
+      if n == 0 {
+        this.eof = true;
+        if this.buffer.is_empty() {
+          return Poll::Ready(Ok(()));
+        }
+        this.seal_block()?;
+      } else if this.buffer.len() == BLOCK_SIZE {
+        this.seal_block()?;
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tokio::io::AsyncReadExt;
+
+  #[tokio::test]
+  async fn encrypt_blocks_and_seal_keys() {
+    let master = WrappingKey::from_base64(&STANDARD.encode([7u8; 32])).unwrap();
+    let (key, sealed) = master.new_data_key().unwrap();
+    assert_eq!(master.open_data_key(&sealed).unwrap().0, key.0);
+    let other = WrappingKey([8u8; 32]);
+    assert!(other.open_data_key(&sealed).is_err());
+
+    let data: Vec<u8> = (0..BLOCK_SIZE * 2 + 10).map(|i| i as u8).collect();
+    let mut reader = EncryptReader::new(&data[..], &key);
+    let mut encrypted = Vec::new();
+    reader.read_to_end(&mut encrypted).await.unwrap();
+    assert_eq!(encrypted.len() as u64, cipher_len(data.len() as u64));
+    let (size, md5) = reader.finish();
+    assert_eq!(size, data.len() as u64);
+    assert_eq!(md5, <[u8; 16]>::from(Md5::digest(&data)));
+
+    // 每块可以单独解密
+    let frame = BLOCK_SIZE + TAG_SIZE;
+    let mut last = encrypted[2 * frame..].to_vec();
+    decrypt_block(&key.cipher(), 2, &mut last).unwrap();
+    assert_eq!(last, data[2 * BLOCK_SIZE..]);
+    let mut wrong_block = encrypted[..frame].to_vec();
+    assert!(decrypt_block(&key.cipher(), 1, &mut wrong_block).is_err());
+  }
+}
@@ -0,0 +1,61 @@
+use thiserror::Error;
+
+/// 存储层的业务错误，通过 anyhow 向上传递，由网关按类型映射为 S3 错误码
+#[derive(Debug, Error)]
+pub enum StorageError {
+  #[error("The specified bucket does not exist: {bucket}")]
+  NoSuchBucket { bucket: String },
+  #[error("The specified key does not exist: {bucket}/{key}")]
+  NoSuchKey { bucket: String, key: String },
+  #[error("The specified bucket is not valid: {reason}")]
+  InvalidBucketName {
+    bucket: String,
+    reason: &'static str,
+  },
+  #[error("The requested bucket name is not available: {bucket}")]
+  BucketAlreadyExists { bucket: String },
+  #[error(
+    "Your previous request to create the named bucket succeeded and you already own it: {bucket}"
+  )]
+  BucketAlreadyOwnedByYou { bucket: String },
+  #[error("The bucket you tried to delete is not empty: {bucket}")]
+  BucketNotEmpty { bucket: String },
+  #[error("The specified version does not exist: {key} {version_id}")]
+  NoSuchVersion { key: String, version_id: String },
+  #[error("The specified multipart upload does not exist: {upload_id}")]
+  NoSuchUpload { upload_id: String },
+  #[error("One or more of the specified parts could not be found: part {part_number}")]
+  InvalidPart { part_number: u32 },
+  #[error("The list of parts was not in ascending order")]
+  InvalidPartOrder,
+  #[error("Your proposed upload is smaller than the minimum allowed size: part {part_number}")]
+  EntityTooSmall { part_number: u32 },
+  #[error("At least one of the pre-conditions you specified did not hold")]
+  PreconditionFailed,
+  #[error("Your metadata headers exceed the maximum allowed metadata size: {size} bytes")]
+  MetadataTooLarge { size: usize },
+  #[error("{reason}")]
+  InvalidTag { reason: &'static str },
+  #[error("{reason}")]
+  MalformedPolicy { reason: String },
+  #[error("{reason}")]
+  InvalidCors { reason: &'static str },
+  #[error("{reason}")]
+  InvalidLifecycle { reason: &'static str },
+  #[error("{reason}")]
+  InvalidEncryption { reason: &'static str },
+  #[error("The provided encryption key does not match the key used to encrypt the object")]
+  EncryptionKeyMismatch,
+  #[error("The {name} you specified did not match the calculated checksum.")]
+  BadDigest { name: &'static str },
+  #[error("{reason}")]
+  InvalidChecksum { reason: &'static str },
+  #[error("Access Denied because object protected by object lock: {key}")]
+  ObjectLocked { key: String },
+  #[error("{reason}")]
+  InvalidRetention { reason: &'static str },
+  #[error("Bucket is missing Object Lock Configuration")]
+  MissingObjectLockConfiguration,
+  #[error("{reason}")]
+  InvalidBucketState { reason: &'static str },
+}
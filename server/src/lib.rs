@@ -0,0 +1,12 @@
+pub mod bucket;
+pub mod checksum;
+pub mod config;
+pub mod encryption;
+pub mod error;
+pub mod max;
+pub mod metadata;
+pub mod object;
+pub mod policy;
+pub mod protocol;
+pub mod storage;
+pub mod writer;
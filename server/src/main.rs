@@ -1,13 +1,6 @@
+use server::max::MaxServer;
 use tokio::signal;
 use tracing_subscriber::EnvFilter;
-use crate::max::MaxServer;
-
-mod max;
-mod bucket;
-mod metadata;
-mod config;
-mod writer;
-mod protocol;
 
 #[tokio::main]
 async fn main() {
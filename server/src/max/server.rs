@@ -22,7 +22,7 @@ impl MaxServer {
     debug!("Max Server listening on {}", self.address);
 
     loop {
-      let (stream, addr) = listener.accept().await.unwrap();
+      let (_stream, addr) = listener.accept().await.unwrap();
       println!("Accepted connection from {}", addr);
       tokio::spawn(async move {});
     }
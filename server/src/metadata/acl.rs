@@ -0,0 +1,193 @@
+use bincode::{Decode, Encode};
+
+pub const ALL_USERS_URI: &str = "http://acs.amazonaws.com/groups/global/AllUsers";
+pub const AUTHENTICATED_USERS_URI: &str =
+  "http://acs.amazonaws.com/groups/global/AuthenticatedUsers";
+
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Encode, Decode,
+)]
+pub enum Permission {
+  FullControl,
+  Read,
+  Write,
+  ReadAcp,
+  WriteAcp,
+}
+
+impl Permission {
+  pub const ALL: [Permission; 5] = [
+    Self::FullControl,
+    Self::Read,
+    Self::Write,
+    Self::ReadAcp,
+    Self::WriteAcp,
+  ];
+
+  /// XML 中的写法，如 `FULL_CONTROL`
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::FullControl => "FULL_CONTROL",
+      Self::Read => "READ",
+      Self::Write => "WRITE",
+      Self::ReadAcp => "READ_ACP",
+      Self::WriteAcp => "WRITE_ACP",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL
+      .into_iter()
+      .find(|permission| permission.as_str() == name)
+  }
+}
+
+/// 被授权方：用户（以 access key 作为 canonical ID）或预定义的用户组
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum Grantee {
+  User(String),
+  AllUsers,
+  AuthenticatedUsers,
+}
+
+impl Grantee {
+  /// 按组 URI 解析，如 `http://acs.amazonaws.com/groups/global/AllUsers`
+  pub fn from_group_uri(uri: &str) -> Option<Self> {
+    match uri {
+      ALL_USERS_URI => Some(Self::AllUsers),
+      AUTHENTICATED_USERS_URI => Some(Self::AuthenticatedUsers),
+      _ => None,
+    }
+  }
+
+  /// `principal` 为请求方的 access key，匿名请求为 None
+  pub fn matches(&self, principal: Option<&str>) -> bool {
+    match self {
+      Self::User(id) => principal == Some(id.as_str()),
+      Self::AllUsers => true,
+      Self::AuthenticatedUsers => principal.is_some(),
+    }
+  }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct Grant {
+  pub grantee: Grantee,
+  pub permission: Permission,
+}
+
+impl Grant {
+  pub fn new(grantee: Grantee, permission: Permission) -> Self {
+    Self {
+      grantee,
+      permission,
+    }
+  }
+}
+
+/// bucket 或对象的 ACL；owner 总是拥有全部权限，不依赖 grants
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq, Encode, Decode,
+)]
+pub struct AccessControlList {
+  pub owner: String,
+  pub grants: Vec<Grant>,
+}
+
+impl AccessControlList {
+  /// 只有 owner 拥有 FULL_CONTROL，即 `private`
+  pub fn private(owner: &str) -> Self {
+    CannedAcl::Private.build(owner, owner)
+  }
+
+  pub fn allows(&self, principal: Option<&str>, permission: Permission) -> bool {
+    principal == Some(self.owner.as_str())
+      || self.grants.iter().any(|grant| {
+        (grant.permission == permission || grant.permission == Permission::FullControl)
+          && grant.grantee.matches(principal)
+      })
+  }
+}
+
+/// `x-amz-acl` 预定义 ACL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CannedAcl {
+  Private,
+  PublicRead,
+  PublicReadWrite,
+  AuthenticatedRead,
+  BucketOwnerRead,
+  BucketOwnerFullControl,
+}
+
+impl CannedAcl {
+  pub fn from_name(name: &str) -> Option<Self> {
+    Some(match name {
+      "private" => Self::Private,
+      "public-read" => Self::PublicRead,
+      "public-read-write" => Self::PublicReadWrite,
+      "authenticated-read" => Self::AuthenticatedRead,
+      "bucket-owner-read" => Self::BucketOwnerRead,
+      "bucket-owner-full-control" => Self::BucketOwnerFullControl,
+      _ => return None,
+    })
+  }
+
+  /// `bucket_owner` 只对 bucket-owner-* 有意义，用于对象 ACL
+  pub fn build(&self, owner: &str, bucket_owner: &str) -> AccessControlList {
+    let mut grants = vec![Grant::new(
+      Grantee::User(owner.to_string()),
+      Permission::FullControl,
+    )];
+    let extra = match self {
+      Self::Private => vec![],
+      Self::PublicRead => vec![(Grantee::AllUsers, Permission::Read)],
+      Self::PublicReadWrite => vec![
+        (Grantee::AllUsers, Permission::Read),
+        (Grantee::AllUsers, Permission::Write),
+      ],
+      Self::AuthenticatedRead => vec![(Grantee::AuthenticatedUsers, Permission::Read)],
+      Self::BucketOwnerRead => vec![(Grantee::User(bucket_owner.to_string()), Permission::Read)],
+      Self::BucketOwnerFullControl => vec![(
+        Grantee::User(bucket_owner.to_string()),
+        Permission::FullControl,
+      )],
+    };
+    // 自己的 bucket 里 bucket-owner-* 与 private 相同
+    grants.extend(
+      extra
+        .into_iter()
+        .filter(|(grantee, _)| *grantee != Grantee::User(owner.to_string()))
+        .map(|(grantee, permission)| Grant::new(grantee, permission)),
+    );
+    AccessControlList {
+      owner: owner.to_string(),
+      grants,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn canned_acl_grants() {
+    let acl = CannedAcl::PublicRead.build("alice", "alice");
+    assert!(acl.allows(None, Permission::Read));
+    assert!(!acl.allows(None, Permission::Write));
+    assert!(acl.allows(Some("alice"), Permission::WriteAcp));
+
+    let acl = CannedAcl::AuthenticatedRead.build("alice", "alice");
+    assert!(!acl.allows(None, Permission::Read));
+    assert!(acl.allows(Some("bob"), Permission::Read));
+
+    let acl = CannedAcl::BucketOwnerFullControl.build("bob", "alice");
+    assert_eq!(acl.grants.len(), 2);
+    assert!(acl.allows(Some("alice"), Permission::ReadAcp));
+    assert_eq!(
+      CannedAcl::BucketOwnerRead.build("alice", "alice"),
+      AccessControlList::private("alice")
+    );
+  }
+}
@@ -1,21 +1,25 @@
+use crate::metadata::acl::AccessControlList;
 use crate::metadata::config::BucketConfig;
+use crate::metadata::cors::CorsConfiguration;
 use crate::metadata::policy::BucketPolicy;
-use bincode::{Decode, Encode};
-use redb::{TypeName, Value};
-use std::borrow::Cow;
+use crate::metadata::tagging::Tag;
 use crate::impl_redb_value;
+use bincode::{Decode, Encode};
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Encode, Decode)]
 pub struct BucketMeta {
-  pub id: String,                   // 内部唯一标识
-  pub name: String,                 // Bucket 名称，用户可见
-  pub created_at: i64,              // 创建时间
-  pub owner: String,                // 所有者（可选）
-  pub policy: Option<BucketPolicy>, // 权限策略
-  pub config: BucketConfig,         // 存储策略等配置
+  pub id: String,                      // 内部唯一标识
+  pub name: String,                    // Bucket 名称，用户可见
+  pub created_at: i64,                 // 创建时间
+  pub owner: String,                   // 所有者（可选）
+  pub policy: Option<BucketPolicy>,    // 权限策略
+  pub config: BucketConfig,            // 存储策略等配置
+  pub tags: Vec<Tag>,                  // bucket 标签
+  pub acl: AccessControlList,          // 访问控制列表
+  pub cors: Option<CorsConfiguration>, // 跨域规则
 }
 
-impl_redb_value!(BucketMeta, "BucketMeta");
+impl_redb_value!(BucketMeta, "BucketMeta", 1);
 
 // impl Value for BucketMeta {
 //   type SelfType<'a>
@@ -1,8 +1,24 @@
+use crate::metadata::encryption::SseAlgorithm;
+use crate::metadata::lifecycle::LifecycleConfiguration;
+use crate::metadata::object_lock::ObjectLockConfiguration;
 use bincode::{Decode, Encode};
 
+/// bucket 的版本控制状态；开启后只能暂停，不能回到未开启
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Decode, Encode,
+)]
+pub enum VersioningStatus {
+  #[default]
+  Unversioned,
+  Enabled,
+  Suspended,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Decode, Encode)]
 pub struct BucketConfig {
-  pub versioning: bool,
+  pub versioning: VersioningStatus,
   pub dedup: bool,
-  pub lifecycle_days: Option<u32>, // 自动清理时间
+  pub lifecycle: Option<LifecycleConfiguration>, // 生命周期规则，由后台任务执行
+  pub encryption: Option<SseAlgorithm>,          // 默认加密，请求未指定加密方式时使用
+  pub object_lock: Option<ObjectLockConfiguration>, // 开启 Object Lock 后不能关闭
 }
@@ -1,15 +1,85 @@
 use bincode::{Decode, Encode};
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Decode, Encode)]
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Decode, Encode,
+)]
 pub enum Effect {
   Allow,
   Deny,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Decode, Encode)]
-pub enum Action {
+macro_rules! s3_actions {
+  ($($action:ident,)*) => {
+    /// S3 操作对应的权限名，策略中写作 `s3:GetObject`
+    #[derive(
+      serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Decode, Encode,
+    )]
+    pub enum Action {
+      $($action,)*
+    }
+
+    impl Action {
+      pub const ALL: &[Action] = &[$(Action::$action,)*];
+
+      pub fn as_str(&self) -> &'static str {
+        match self {
+          $(Action::$action => concat!("s3:", stringify!($action)),)*
+        }
+      }
+    }
+  };
+}
+
+s3_actions! {
+  ListAllMyBuckets,
+  CreateBucket,
+  DeleteBucket,
+  ListBucket,
+  ListBucketVersions,
+  ListBucketMultipartUploads,
+  GetBucketLocation,
+  GetBucketTagging,
+  PutBucketTagging,
+  GetBucketPolicy,
+  PutBucketPolicy,
+  DeleteBucketPolicy,
+  GetBucketAcl,
+  PutBucketAcl,
+  GetBucketCORS,
+  PutBucketCORS,
+  GetBucketVersioning,
+  PutBucketVersioning,
+  GetLifecycleConfiguration,
+  PutLifecycleConfiguration,
+  GetEncryptionConfiguration,
+  PutEncryptionConfiguration,
+  GetBucketObjectLockConfiguration,
+  PutBucketObjectLockConfiguration,
   GetObject,
+  GetObjectVersion,
   PutObject,
   DeleteObject,
-  ListBucket,
+  DeleteObjectVersion,
+  AbortMultipartUpload,
+  ListMultipartUploadParts,
+  GetObjectTagging,
+  PutObjectTagging,
+  DeleteObjectTagging,
+  GetObjectAcl,
+  PutObjectAcl,
+  GetObjectRetention,
+  PutObjectRetention,
+  GetObjectLegalHold,
+  PutObjectLegalHold,
+  BypassGovernanceRetention,
+}
+
+impl Action {
+  /// 解析 `s3:GetObject` 形式的权限名，大小写不敏感
+  pub fn from_name(name: &str) -> Option<Action> {
+    Action::ALL
+      .iter()
+      .copied()
+      .find(|action| action.as_str().eq_ignore_ascii_case(name))
+  }
 }
@@ -0,0 +1,134 @@
+use crate::error::StorageError;
+use crate::policy::wildcard_match;
+use bincode::{Decode, Encode};
+
+/// 一个 bucket 最多 100 条 CORS 规则
+pub const MAX_CORS_RULES: usize = 100;
+const CORS_METHODS: [&str; 5] = ["GET", "PUT", "POST", "DELETE", "HEAD"];
+
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq, Encode, Decode,
+)]
+pub struct CorsRule {
+  pub id: Option<String>,
+  pub allowed_origins: Vec<String>, // 可包含一个 `*`，如 https://*.example.com
+  pub allowed_methods: Vec<String>, // GET、PUT、POST、DELETE、HEAD
+  pub allowed_headers: Vec<String>, // 预检请求 Access-Control-Request-Headers 允许的头，可包含一个 `*`
+  pub expose_headers: Vec<String>,  // 允许浏览器读取的响应头
+  pub max_age_seconds: Option<u32>, // 预检结果的缓存时间
+}
+
+impl CorsRule {
+  pub fn allows_origin(&self, origin: &str) -> bool {
+    self
+      .allowed_origins
+      .iter()
+      .any(|allowed| wildcard_match(allowed, origin))
+  }
+
+  /// 请求头名不区分大小写
+  fn allows_header(&self, header: &str) -> bool {
+    self
+      .allowed_headers
+      .iter()
+      .any(|allowed| wildcard_match(&allowed.to_ascii_lowercase(), &header.to_ascii_lowercase()))
+  }
+
+  pub fn matches(&self, origin: &str, method: &str, headers: &[&str]) -> bool {
+    self.allows_origin(origin)
+      && self.allowed_methods.iter().any(|allowed| allowed == method)
+      && headers.iter().all(|header| self.allows_header(header))
+  }
+}
+
+/// bucket 的 CORS 配置，按顺序取第一条匹配的规则
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq, Encode, Decode,
+)]
+pub struct CorsConfiguration {
+  pub rules: Vec<CorsRule>,
+}
+
+impl CorsConfiguration {
+  pub fn validate(&self) -> Result<(), StorageError> {
+    let invalid = |reason| Err(StorageError::InvalidCors { reason });
+    if self.rules.is_empty() || self.rules.len() > MAX_CORS_RULES {
+      return invalid("The CORS configuration must have between 1 and 100 rules");
+    }
+    for rule in &self.rules {
+      if rule.allowed_origins.is_empty() || rule.allowed_methods.is_empty() {
+        return invalid("Each CORSRule must specify at least one AllowedOrigin and AllowedMethod");
+      }
+      if rule
+        .allowed_methods
+        .iter()
+        .any(|method| !CORS_METHODS.contains(&method.as_str()))
+      {
+        return invalid("Found unsupported HTTP method in CORS config");
+      }
+      let too_many_wildcards =
+        |values: &[String]| values.iter().any(|v| v.matches('*').count() > 1);
+      if too_many_wildcards(&rule.allowed_origins) {
+        return invalid("AllowedOrigin can not have more than one wildcard");
+      }
+      if too_many_wildcards(&rule.allowed_headers) {
+        return invalid("AllowedHeader can not have more than one wildcard");
+      }
+    }
+    Ok(())
+  }
+
+  pub fn find_rule(&self, origin: &str, method: &str, headers: &[&str]) -> Option<&CorsRule> {
+    self
+      .rules
+      .iter()
+      .find(|rule| rule.matches(origin, method, headers))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn match_rules() {
+    let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    let config = CorsConfiguration {
+      rules: vec![
+        CorsRule {
+          allowed_origins: strings(&["https://*.example.com"]),
+          allowed_methods: strings(&["PUT", "POST"]),
+          allowed_headers: strings(&["Content-*", "x-amz-*"]),
+          ..Default::default()
+        },
+        CorsRule {
+          allowed_origins: strings(&["*"]),
+          allowed_methods: strings(&["GET"]),
+          ..Default::default()
+        },
+      ],
+    };
+    config.validate().unwrap();
+    let origin = "https://app.example.com";
+    assert!(
+      config
+        .find_rule(origin, "PUT", &["content-type", "X-Amz-Date"])
+        .is_some()
+    );
+    assert!(
+      config
+        .find_rule(origin, "PUT", &["authorization"])
+        .is_none()
+    );
+    assert!(config.find_rule("https://evil.com", "PUT", &[]).is_none());
+    assert_eq!(
+      config.find_rule("https://evil.com", "GET", &[]),
+      Some(&config.rules[1])
+    );
+
+    let mut invalid = config.clone();
+    invalid.rules[0].allowed_methods.push("PATCH".into());
+    assert!(invalid.validate().is_err());
+    assert!(CorsConfiguration::default().validate().is_err());
+  }
+}
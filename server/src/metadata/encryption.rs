@@ -0,0 +1,26 @@
+use bincode::{Decode, Encode};
+
+/// bucket 默认加密算法，目前只支持 SSE-S3（AES256）
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Encode, Decode,
+)]
+pub enum SseAlgorithm {
+  Aes256,
+}
+
+/// 数据密钥的保护方式
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum EncryptionKind {
+  /// SSE-S3：数据密钥用服务端主密钥加密
+  S3,
+  /// SSE-C：数据密钥用客户提供的密钥加密，客户密钥本身不保存，只保存其 MD5（base64）用于读取时校验
+  Customer { key_md5: String },
+}
+
+/// 加密对象的密钥信息。每个分片（普通对象只有一个）有各自随机生成的数据密钥，
+/// 数据按固定大小的块独立加密，支持只解密 range 覆盖的块
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct ObjectEncryption {
+  pub kind: EncryptionKind,
+  pub sealed_keys: Vec<Vec<u8>>, // 加密后的数据密钥，与分片一一对应
+}
@@ -0,0 +1,227 @@
+use crate::error::StorageError;
+use crate::metadata::tagging::Tag;
+use bincode::{Decode, Encode};
+use std::collections::HashSet;
+
+/// 一个 bucket 最多 1000 条生命周期规则
+pub const MAX_LIFECYCLE_RULES: usize = 1000;
+const DAY_SECS: i64 = 24 * 3600;
+
+/// 规则作用的对象范围，各条件同时满足才匹配
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq, Encode, Decode,
+)]
+pub struct LifecycleFilter {
+  pub prefix: String,
+  pub tags: Vec<Tag>,
+  pub object_size_greater_than: Option<u64>,
+  pub object_size_less_than: Option<u64>,
+}
+
+impl LifecycleFilter {
+  pub fn matches(&self, key: &str, size: u64, tags: &[Tag]) -> bool {
+    key.starts_with(&self.prefix)
+      && self.tags.iter().all(|tag| tags.contains(tag))
+      && self.object_size_greater_than.is_none_or(|min| size > min)
+      && self.object_size_less_than.is_none_or(|max| size < max)
+  }
+
+  /// 删除标记和分片上传没有标签和大小，只按前缀匹配；带标签或大小条件的规则不作用于它们
+  fn matches_prefix_only(&self, key: &str) -> bool {
+    self.tags.is_empty()
+      && self.object_size_greater_than.is_none()
+      && self.object_size_less_than.is_none()
+      && key.starts_with(&self.prefix)
+  }
+}
+
+/// 当前版本的过期时间：创建后的天数，或一个固定日期（UTC 零点的时间戳）
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum Expiration {
+  Days(u32),
+  Date(i64),
+}
+
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq, Encode, Decode,
+)]
+pub struct LifecycleRule {
+  pub id: Option<String>,
+  pub enabled: bool,
+  pub filter: LifecycleFilter,
+  pub expiration: Option<Expiration>, // 当前版本过期；开启版本控制时创建删除标记
+  pub expired_object_delete_marker: bool, // 清理没有任何其它版本的删除标记
+  pub noncurrent_days: Option<u32>,   // 版本变为非当前版本后保留的天数
+  pub newer_noncurrent_versions: Option<u32>, // 无论时间，始终保留最新的若干个非当前版本
+  pub abort_incomplete_upload_days: Option<u32>, // 中止创建后超过天数的分片上传
+}
+
+impl LifecycleRule {
+  /// 当前版本是否已过期
+  pub fn expires_current(
+    &self,
+    key: &str,
+    size: u64,
+    tags: &[Tag],
+    last_modified: i64,
+    now: i64,
+  ) -> bool {
+    let due = match self.expiration {
+      Some(Expiration::Days(days)) => last_modified + days as i64 * DAY_SECS,
+      Some(Expiration::Date(date)) => date,
+      None => return false,
+    };
+    now >= due && self.filter.matches(key, size, tags)
+  }
+
+  /// 第 `index` 个（从 0 开始、从新到旧）非当前版本是否已过期；`noncurrent_since` 为它被更新版本取代的时间
+  pub fn expires_noncurrent(
+    &self,
+    key: &str,
+    size: u64,
+    tags: &[Tag],
+    index: usize,
+    noncurrent_since: i64,
+    now: i64,
+  ) -> bool {
+    let Some(days) = self.noncurrent_days else {
+      return false;
+    };
+    index >= self.newer_noncurrent_versions.unwrap_or(0) as usize
+      && now >= noncurrent_since + days as i64 * DAY_SECS
+      && self.filter.matches(key, size, tags)
+  }
+
+  pub fn expires_delete_marker(&self, key: &str) -> bool {
+    self.expired_object_delete_marker && self.filter.matches_prefix_only(key)
+  }
+
+  pub fn aborts_upload(&self, key: &str, initiated: i64, now: i64) -> bool {
+    self
+      .abort_incomplete_upload_days
+      .is_some_and(|days| now >= initiated + days as i64 * DAY_SECS)
+      && self.filter.matches_prefix_only(key)
+  }
+}
+
+/// bucket 的生命周期配置，只有状态为 Enabled 的规则生效
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq, Encode, Decode,
+)]
+pub struct LifecycleConfiguration {
+  pub rules: Vec<LifecycleRule>,
+}
+
+impl LifecycleConfiguration {
+  pub fn enabled_rules(&self) -> impl Iterator<Item = &LifecycleRule> {
+    self.rules.iter().filter(|rule| rule.enabled)
+  }
+
+  pub fn validate(&self) -> Result<(), StorageError> {
+    let invalid = |reason| Err(StorageError::InvalidLifecycle { reason });
+    if self.rules.is_empty() || self.rules.len() > MAX_LIFECYCLE_RULES {
+      return invalid("The lifecycle configuration must have between 1 and 1000 rules");
+    }
+    let mut ids = HashSet::new();
+    for rule in &self.rules {
+      if let Some(id) = &rule.id {
+        if id.len() > 255 {
+          return invalid("ID length should not exceed allowed limit of 255");
+        }
+        if !ids.insert(id) {
+          return invalid("Rule ID must be unique. Found same ID for more than one rule");
+        }
+      }
+      if rule.expiration.is_none()
+        && !rule.expired_object_delete_marker
+        && rule.noncurrent_days.is_none()
+        && rule.abort_incomplete_upload_days.is_none()
+      {
+        return invalid("At least one action needs to be specified in a rule");
+      }
+      if rule.expiration.is_some() && rule.expired_object_delete_marker {
+        return invalid(
+          "ExpiredObjectDeleteMarker cannot be specified with Days or Date in a Lifecycle Expiration Policy",
+        );
+      }
+      if matches!(rule.expiration, Some(Expiration::Days(0)))
+        || rule.noncurrent_days == Some(0)
+        || rule.abort_incomplete_upload_days == Some(0)
+      {
+        return invalid("Days in a lifecycle action must be a positive integer");
+      }
+      if rule.newer_noncurrent_versions.is_some() && rule.noncurrent_days.is_none() {
+        return invalid("NewerNoncurrentVersions requires NoncurrentDays");
+      }
+      let filter = &rule.filter;
+      if let (Some(min), Some(max)) = (
+        filter.object_size_greater_than,
+        filter.object_size_less_than,
+      ) && min >= max
+      {
+        return invalid("ObjectSizeGreaterThan must be less than ObjectSizeLessThan");
+      }
+      let prefix_only = filter.tags.is_empty()
+        && filter.object_size_greater_than.is_none()
+        && filter.object_size_less_than.is_none();
+      if !prefix_only
+        && (rule.expired_object_delete_marker || rule.abort_incomplete_upload_days.is_some())
+      {
+        return invalid(
+          "Tag and object size based filters cannot be used with ExpiredObjectDeleteMarker or AbortIncompleteMultipartUpload",
+        );
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rule_matching() {
+    let rule = LifecycleRule {
+      enabled: true,
+      filter: LifecycleFilter {
+        prefix: "logs/".into(),
+        tags: vec![Tag::new("tier", "cold")],
+        object_size_greater_than: Some(10),
+        ..Default::default()
+      },
+      expiration: Some(Expiration::Days(1)),
+      noncurrent_days: Some(2),
+      newer_noncurrent_versions: Some(1),
+      ..Default::default()
+    };
+    let tags = [Tag::new("tier", "cold"), Tag::new("app", "web")];
+    assert!(rule.expires_current("logs/a", 11, &tags, 0, DAY_SECS));
+    assert!(!rule.expires_current("logs/a", 11, &tags, 0, DAY_SECS - 1));
+    assert!(!rule.expires_current("logs/a", 10, &tags, 0, DAY_SECS));
+    assert!(!rule.expires_current("data/a", 11, &tags, 0, DAY_SECS));
+    assert!(!rule.expires_current("logs/a", 11, &tags[1..], 0, DAY_SECS));
+
+    // 最新的一个非当前版本始终保留
+    assert!(!rule.expires_noncurrent("logs/a", 11, &tags, 0, 0, 3 * DAY_SECS));
+    assert!(rule.expires_noncurrent("logs/a", 11, &tags, 1, 0, 3 * DAY_SECS));
+    assert!(!rule.expires_noncurrent("logs/a", 11, &tags, 1, DAY_SECS, 2 * DAY_SECS));
+
+    let config = LifecycleConfiguration {
+      rules: vec![rule.clone()],
+    };
+    assert!(config.validate().is_ok());
+    let invalid = LifecycleRule {
+      abort_incomplete_upload_days: Some(1),
+      ..rule
+    };
+    assert!(
+      LifecycleConfiguration {
+        rules: vec![invalid]
+      }
+      .validate()
+      .is_err()
+    );
+    assert!(LifecycleConfiguration::default().validate().is_err());
+  }
+}
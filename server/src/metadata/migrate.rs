@@ -0,0 +1,168 @@
+//! 元数据的布局版本。值类型在 redb 中的类型名带版本号（如 `ObjectMeta@v1`），
+//! 布局不同的表在打开时由 redb 报告类型不匹配，而不是在读取时解码失败。
+//! 打开存储时把旧版本的表逐条解码后写入当前版本的表，无法解码的值使打开失败并返回错误。
+use crate::metadata::{BUCKET_TABLE, MULTIPART_TABLE, OBJECT_TABLE, PART_TABLE, VERSION_TABLE};
+use anyhow::{Context, Result};
+use redb::{
+  Key, ReadableTable, TableDefinition, TableError, TableHandle, TypeName, Value, WriteTransaction,
+};
+use std::marker::PhantomData;
+use tracing::info;
+
+/// 存入 redb 的元数据类型；布局变化时 VERSION 加一，并在 upgrade_table 中加入旧版本的转换
+pub trait Versioned: bincode::Decode<()> {
+  const NAME: &'static str;
+  const VERSION: u32;
+
+  fn versioned_name() -> TypeName {
+    TypeName::new(&format!("{}@v{}", Self::NAME, Self::VERSION))
+  }
+}
+
+/// 按当前布局解码
+pub fn decode<T: Versioned>(data: &[u8]) -> Result<T> {
+  let (value, _) = bincode::decode_from_slice(data, bincode::config::standard())
+    .with_context(|| format!("failed to decode {}", T::NAME))?;
+  Ok(value)
+}
+
+/// 以原始字节读取类型名不带版本号的旧表
+#[derive(Debug)]
+struct Unversioned<T>(PhantomData<T>);
+
+impl<T: Versioned + std::fmt::Debug> Value for Unversioned<T> {
+  type SelfType<'a>
+    = &'a [u8]
+  where
+    Self: 'a;
+  type AsBytes<'a>
+    = &'a [u8]
+  where
+    Self: 'a;
+
+  fn fixed_width() -> Option<usize> {
+    None
+  }
+
+  fn from_bytes<'a>(data: &'a [u8]) -> &'a [u8]
+  where
+    Self: 'a,
+  {
+    data
+  }
+
+  fn as_bytes<'a, 'b: 'a>(value: &'a &'b [u8]) -> &'a [u8]
+  where
+    Self: 'b,
+  {
+    value
+  }
+
+  fn type_name() -> TypeName {
+    TypeName::new(T::NAME)
+  }
+}
+
+/// 建表并把旧版本的元数据表转换为当前版本，在打开存储时调用
+pub(crate) fn upgrade(write_txn: &WriteTransaction) -> Result<()> {
+  upgrade_table(write_txn, BUCKET_TABLE)?;
+  upgrade_table(write_txn, OBJECT_TABLE)?;
+  upgrade_table(write_txn, VERSION_TABLE)?;
+  upgrade_table(write_txn, MULTIPART_TABLE)?;
+  upgrade_table(write_txn, PART_TABLE)?;
+  Ok(())
+}
+
+/// 表不存在或已是当前版本时只建表；类型名不带版本号的表是加入版本号之前写入的，布局与 v1 相同
+fn upgrade_table<K, V>(
+  write_txn: &WriteTransaction,
+  definition: TableDefinition<K, V>,
+) -> Result<()>
+where
+  K: Key + 'static,
+  V: Versioned + for<'a> Value<SelfType<'a> = V> + 'static,
+{
+  match write_txn.open_table(definition) {
+    Ok(_) => return Ok(()),
+    Err(TableError::TableTypeMismatch { .. }) => {}
+    Err(err) => return Err(err.into()),
+  }
+  let name = definition.name();
+  let legacy: TableDefinition<K, Unversioned<V>> = TableDefinition::new(name);
+  let mut entries = Vec::new();
+  {
+    let table = write_txn
+      .open_table(legacy)
+      .with_context(|| format!("metadata table {name} has an unknown layout"))?;
+    for entry in table.iter()? {
+      let (key, value) = entry?;
+      let value: V = decode(value.value()).with_context(|| format!("failed to migrate {name}"))?;
+      entries.push((K::as_bytes(&key.value()).as_ref().to_vec(), value));
+    }
+  }
+  write_txn.delete_table(legacy)?;
+  let mut table = write_txn.open_table(definition)?;
+  for (key, value) in &entries {
+    table.insert(K::from_bytes(key), value)?;
+  }
+  info!(
+    "migrated {} entries of {} to {}@v{}",
+    entries.len(),
+    name,
+    V::NAME,
+    V::VERSION
+  );
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Unversioned;
+  use crate::metadata::bucket_meta::BucketMeta;
+  use crate::storage::{Storage, test_storage};
+  use redb::{Database, TableDefinition};
+
+  const LEGACY_BUCKETS: TableDefinition<&str, Unversioned<BucketMeta>> =
+    TableDefinition::new("bucket");
+
+  #[test]
+  fn upgrade_unversioned_tables() {
+    let (dir, storage) = test_storage(Default::default());
+    let meta = storage.buckets.get_bucket("bkt").unwrap();
+    drop(storage);
+
+    // 改写成加入版本号之前的表
+    let encoded = bincode::encode_to_vec(&meta, bincode::config::standard()).unwrap();
+    let db = Database::create(dir.path().join("meta.redb")).unwrap();
+    let write_txn = db.begin_write().unwrap();
+    write_txn
+      .delete_table(crate::metadata::BUCKET_TABLE)
+      .unwrap();
+    write_txn
+      .open_table(LEGACY_BUCKETS)
+      .unwrap()
+      .insert("bkt", encoded.as_slice())
+      .unwrap();
+    write_txn.commit().unwrap();
+    drop(db);
+
+    let storage = Storage::open(dir.path(), &dir.path().join("tmp")).unwrap();
+    assert_eq!(storage.buckets.get_bucket("bkt").unwrap().id, meta.id);
+    drop(storage);
+
+    // 无法解码的旧数据使打开失败，而不是在读取时 panic
+    let db = Database::create(dir.path().join("meta.redb")).unwrap();
+    let write_txn = db.begin_write().unwrap();
+    write_txn
+      .delete_table(crate::metadata::BUCKET_TABLE)
+      .unwrap();
+    write_txn
+      .open_table(LEGACY_BUCKETS)
+      .unwrap()
+      .insert("bkt", &b"\xff\xff"[..])
+      .unwrap();
+    write_txn.commit().unwrap();
+    drop(db);
+    assert!(Storage::open(dir.path(), &dir.path().join("tmp")).is_err());
+  }
+}
@@ -1,18 +1,37 @@
-mod bucket_meta;
-mod config;
-mod constant;
-mod policy;
+pub mod acl;
+pub mod bucket_meta;
+pub mod config;
+pub mod constant;
+pub mod cors;
+pub mod encryption;
+pub mod lifecycle;
+pub mod migrate;
+pub mod multipart_meta;
+pub mod object_headers;
+pub mod object_lock;
+pub mod object_meta;
+pub mod policy;
+pub mod tagging;
 
+use crate::metadata::acl::AccessControlList;
 use crate::metadata::bucket_meta::BucketMeta;
-use crate::metadata::config::BucketConfig;
+use crate::metadata::config::{BucketConfig, VersioningStatus};
+use crate::metadata::multipart_meta::{MultipartUpload, PartMeta};
+use crate::metadata::object_meta::ObjectMeta;
 use rand::distr::Alphanumeric;
 use rand::{rng, Rng};
 use redb::{Database, TableDefinition};
 use uuid::Uuid;
 
+/// 以 bincode 编码存入 redb；`$version` 是布局版本，写入类型名，布局变化时加一（见 migrate）
 #[macro_export]
 macro_rules! impl_redb_value {
-  ($ty:ty, $type_name:expr) => {
+  ($ty:ty, $type_name:expr, $version:expr) => {
+    impl $crate::metadata::migrate::Versioned for $ty {
+      const NAME: &'static str = $type_name;
+      const VERSION: u32 = $version;
+    }
+
     impl redb::Value for $ty {
       type SelfType<'a>
         = $ty
@@ -31,9 +50,8 @@ macro_rules! impl_redb_value {
       where
         Self: 'a,
       {
-        bincode::decode_from_slice(data, bincode::config::standard())
-          .map(|(v, _)| v)
-          .expect("bincode decode failed")
+        // 布局不同的旧表在打开存储时已迁移或报错，这里只会遇到当前版本写入的数据
+        $crate::metadata::migrate::decode(data).expect("corrupted metadata")
       }
 
       fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> Self::AsBytes<'a>
@@ -45,13 +63,26 @@ macro_rules! impl_redb_value {
         std::borrow::Cow::Owned(encoded)
       }
       fn type_name() -> redb::TypeName {
-        redb::TypeName::new($type_name)
+        <$ty as $crate::metadata::migrate::Versioned>::versioned_name()
       }
     }
   };
 }
 
 pub const BUCKET_TABLE: TableDefinition<&str, BucketMeta> = TableDefinition::new("bucket");
+// (bucket, key) -> 对象元数据
+pub const OBJECT_TABLE: TableDefinition<(&str, &str), ObjectMeta> = TableDefinition::new("object");
+// (bucket, key, version_id) -> 对象的全部版本（包括删除标记），同一 key 的版本按 version_id 从新到旧排列；
+// OBJECT_TABLE 只保存不是删除标记的最新版本
+pub const VERSION_TABLE: TableDefinition<(&str, &str, &str), ObjectMeta> =
+  TableDefinition::new("object_version");
+// (bucket, key, upload_id) -> 进行中的分片上传
+pub const MULTIPART_TABLE: TableDefinition<(&str, &str, &str), MultipartUpload> =
+  TableDefinition::new("multipart_upload");
+// (upload_id, part_number) -> 已上传的分片
+pub const PART_TABLE: TableDefinition<(&str, u32), PartMeta> = TableDefinition::new("multipart_part");
+// 去重数据块：SHA-256 -> 引用计数，计数为 0 的块等待回收
+pub const CHUNK_TABLE: TableDefinition<&str, u64> = TableDefinition::new("chunk");
 
 fn random_string(len: usize) -> String {
   let rng = rng();
@@ -69,10 +100,15 @@ pub fn insert_bucket(db: &Database) -> anyhow::Result<()> {
     owner: "".to_string(),
     policy: None,
     config: BucketConfig {
-      versioning: false,
+      versioning: VersioningStatus::Unversioned,
       dedup: false,
-      lifecycle_days: None,
+      lifecycle: None,
+      encryption: None,
+      object_lock: None,
     },
+    tags: Vec::new(),
+    acl: AccessControlList::default(),
+    cors: None,
   };
 
   let tx = db.begin_write()?;
@@ -0,0 +1,37 @@
+use crate::checksum::{ChecksumAlgorithm, ChecksumType};
+use crate::impl_redb_value;
+use crate::metadata::acl::AccessControlList;
+use crate::metadata::encryption::EncryptionKind;
+use crate::metadata::object_headers::ObjectHeaders;
+use crate::metadata::object_lock::ObjectLock;
+use crate::metadata::tagging::Tag;
+use bincode::{Decode, Encode};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Encode, Decode)]
+pub struct MultipartUpload {
+  pub upload_id: String,
+  pub bucket: String,
+  pub key: String,
+  pub initiated: i64,                                      // 创建时间
+  pub headers: ObjectHeaders,                              // 完成后写入对象元数据
+  pub tags: Vec<Tag>,                                      // 完成后写入对象标签
+  pub acl: AccessControlList,                              // 完成后写入对象 ACL
+  pub encryption: Option<EncryptionKind>,                  // 各分片按此方式加密
+  pub checksum: Option<(ChecksumAlgorithm, ChecksumType)>, // 各分片计算的校验算法及合并方式
+  pub lock: ObjectLock,                                    // 完成后写入对象的 Object Lock 设置
+}
+
+impl_redb_value!(MultipartUpload, "MultipartUpload", 1);
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Encode, Decode)]
+pub struct PartMeta {
+  pub part_number: u32,
+  pub size: u64,
+  pub etag: String, // 分片数据的 MD5（hex）
+  pub last_modified: i64,
+  pub file_name: String,           // temp_dir/multipart/<upload_id>/ 下的文件名
+  pub sealed_key: Option<Vec<u8>>, // 加密上传时该分片加密后的数据密钥
+  pub checksum: Option<String>,    // 上传指定校验算法时该分片的校验值（base64）
+}
+
+impl_redb_value!(PartMeta, "PartMeta", 1);
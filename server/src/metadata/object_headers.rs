@@ -0,0 +1,34 @@
+use crate::error::StorageError;
+use bincode::{Decode, Encode};
+use std::collections::BTreeMap;
+
+/// 用户自定义元数据（x-amz-meta-*）的总大小上限，按各键值 UTF-8 字节数之和计算
+pub const MAX_USER_METADATA_SIZE: usize = 2 * 1024;
+
+/// 随对象保存的元数据记录：标准 HTTP 头和用户自定义元数据，GET/HEAD 时原样返回
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq, Encode, Decode,
+)]
+pub struct ObjectHeaders {
+  pub content_type: Option<String>,
+  pub content_disposition: Option<String>,
+  pub content_encoding: Option<String>,
+  pub content_language: Option<String>,
+  pub cache_control: Option<String>,
+  pub expires: Option<String>,
+  pub user: BTreeMap<String, String>, // 去掉 x-amz-meta- 前缀的小写键名
+}
+
+impl ObjectHeaders {
+  pub fn user_metadata_size(&self) -> usize {
+    self.user.iter().map(|(k, v)| k.len() + v.len()).sum()
+  }
+
+  pub fn validate(&self) -> anyhow::Result<()> {
+    let size = self.user_metadata_size();
+    if size > MAX_USER_METADATA_SIZE {
+      return Err(StorageError::MetadataTooLarge { size }.into());
+    }
+    Ok(())
+  }
+}
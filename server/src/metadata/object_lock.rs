@@ -0,0 +1,200 @@
+use crate::error::StorageError;
+use bincode::{Decode, Encode};
+
+const DAY_SECS: i64 = 24 * 3600;
+/// 默认保留期的上限：100 年
+const MAX_RETENTION_DAYS: u32 = 36500;
+
+/// 保留模式：治理模式可由有 `s3:BypassGovernanceRetention` 权限的用户绕过，合规模式到期前任何人都不能删除或缩短
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Encode, Decode,
+)]
+pub enum RetentionMode {
+  Governance,
+  Compliance,
+}
+
+impl RetentionMode {
+  pub fn name(&self) -> &'static str {
+    match self {
+      RetentionMode::Governance => "GOVERNANCE",
+      RetentionMode::Compliance => "COMPLIANCE",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<RetentionMode> {
+    match name {
+      "GOVERNANCE" => Some(RetentionMode::Governance),
+      "COMPLIANCE" => Some(RetentionMode::Compliance),
+      _ => None,
+    }
+  }
+}
+
+/// 对象版本的保留设置，`retain_until`（秒级时间戳）之前不能删除
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct Retention {
+  pub mode: RetentionMode,
+  pub retain_until: i64,
+}
+
+impl Retention {
+  pub fn is_active(&self, now: i64) -> bool {
+    now < self.retain_until
+  }
+
+  /// 生效中的保留只能延长：合规模式不能改变模式，治理模式可以改为合规模式；
+  /// 缩短或移除治理模式的保留需要 `bypass_governance`
+  pub fn permits_change(&self, new: Option<&Retention>, now: i64, bypass_governance: bool) -> bool {
+    if !self.is_active(now) {
+      return true;
+    }
+    let extends = new.is_some_and(|new| new.retain_until >= self.retain_until);
+    match self.mode {
+      RetentionMode::Compliance => {
+        extends && new.is_some_and(|new| new.mode == RetentionMode::Compliance)
+      }
+      RetentionMode::Governance => extends || bypass_governance,
+    }
+  }
+}
+
+/// 对象版本的 Object Lock 状态：保留设置和法律保留，两者独立生效
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq, Encode, Decode,
+)]
+pub struct ObjectLock {
+  pub retention: Option<Retention>,
+  pub legal_hold: bool,
+}
+
+impl ObjectLock {
+  pub fn is_empty(&self) -> bool {
+    self.retention.is_none() && !self.legal_hold
+  }
+
+  /// 版本是否受保护；法律保留不能绕过，治理模式的保留在 `bypass_governance` 时不生效
+  pub fn protects(&self, now: i64, bypass_governance: bool) -> bool {
+    self.legal_hold
+      || self.retention.as_ref().is_some_and(|retention| {
+        retention.is_active(now)
+          && !(bypass_governance && retention.mode == RetentionMode::Governance)
+      })
+  }
+}
+
+/// 默认保留期，按天或按年（一年按 365 天）计算
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum RetentionPeriod {
+  Days(u32),
+  Years(u32),
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct DefaultRetention {
+  pub mode: RetentionMode,
+  pub period: RetentionPeriod,
+}
+
+impl DefaultRetention {
+  fn days(&self) -> u32 {
+    match self.period {
+      RetentionPeriod::Days(days) => days,
+      RetentionPeriod::Years(years) => years.saturating_mul(365),
+    }
+  }
+
+  /// 写入时未指定保留设置的新版本，从写入时间开始计算
+  pub fn retention(&self, now: i64) -> Retention {
+    Retention {
+      mode: self.mode,
+      retain_until: now + self.days() as i64 * DAY_SECS,
+    }
+  }
+}
+
+/// bucket 的 Object Lock 配置，存在即表示已开启；只能在创建 bucket 时开启，开启后版本控制不能暂停
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq, Encode, Decode,
+)]
+pub struct ObjectLockConfiguration {
+  pub default_retention: Option<DefaultRetention>,
+}
+
+impl ObjectLockConfiguration {
+  pub fn validate(&self) -> Result<(), StorageError> {
+    let Some(default_retention) = &self.default_retention else {
+      return Ok(());
+    };
+    let reason = match default_retention.days() {
+      0 => "Default retention period must be a positive integer value",
+      days if days > MAX_RETENTION_DAYS => "Default retention period is too large",
+      _ => return Ok(()),
+    };
+    Err(StorageError::InvalidRetention { reason })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn lock_protection() {
+    let governance = Retention {
+      mode: RetentionMode::Governance,
+      retain_until: 100,
+    };
+    let lock = ObjectLock {
+      retention: Some(governance.clone()),
+      legal_hold: false,
+    };
+    assert!(lock.protects(99, false));
+    assert!(!lock.protects(99, true));
+    assert!(!lock.protects(100, false));
+
+    let compliance = ObjectLock {
+      retention: Some(Retention {
+        mode: RetentionMode::Compliance,
+        ..governance
+      }),
+      legal_hold: false,
+    };
+    assert!(compliance.protects(99, true));
+
+    // 保留只能延长，治理模式可以升级为合规模式或在绕过时缩短
+    let longer = Retention {
+      mode: RetentionMode::Compliance,
+      retain_until: 200,
+    };
+    assert!(governance.permits_change(Some(&longer), 50, false));
+    assert!(!governance.permits_change(None, 50, false));
+    assert!(governance.permits_change(None, 50, true));
+    let retention = compliance.retention.as_ref().unwrap();
+    assert!(retention.permits_change(Some(&longer), 50, false));
+    assert!(!retention.permits_change(Some(&governance), 50, true));
+    assert!(retention.permits_change(None, 100, false));
+    let held = ObjectLock {
+      legal_hold: true,
+      ..Default::default()
+    };
+    assert!(held.protects(i64::MAX, true));
+
+    let config = ObjectLockConfiguration {
+      default_retention: Some(DefaultRetention {
+        mode: RetentionMode::Governance,
+        period: RetentionPeriod::Years(1),
+      }),
+    };
+    assert!(config.validate().is_ok());
+    let default_retention = config.default_retention.as_ref().unwrap();
+    assert_eq!(default_retention.retention(0).retain_until, 365 * DAY_SECS);
+    let zero = ObjectLockConfiguration {
+      default_retention: Some(DefaultRetention {
+        mode: RetentionMode::Compliance,
+        period: RetentionPeriod::Days(0),
+      }),
+    };
+    assert!(zero.validate().is_err());
+  }
+}
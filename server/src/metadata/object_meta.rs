@@ -0,0 +1,71 @@
+use crate::checksum::ObjectChecksum;
+use crate::error::StorageError;
+use crate::impl_redb_value;
+use crate::metadata::acl::AccessControlList;
+use crate::metadata::encryption::ObjectEncryption;
+use crate::metadata::object_headers::ObjectHeaders;
+use crate::metadata::object_lock::ObjectLock;
+use crate::metadata::tagging::Tag;
+use bincode::{Decode, Encode};
+
+/// 对象数据所在位置
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum DataLocation {
+  /// 独立的数据文件
+  File { data_id: String },
+  /// 打包在 ObjectGroup 数据文件中的一段
+  Group { group_id: String, offset: u64 },
+  /// 删除标记，没有数据
+  DeleteMarker,
+  /// 去重存储：按 chunk_size 切分的数据块，以 SHA-256 寻址，可被多个对象共享
+  Chunks {
+    chunk_size: u64,
+    hashes: Vec<String>,
+  },
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Encode, Decode)]
+pub struct ObjectMeta {
+  pub bucket: String,                       // 所属 Bucket
+  pub key: String,                          // 对象 Key
+  pub size: u64,                            // 对象大小（字节）
+  pub etag: String,                         // 不带引号的 ETag
+  pub headers: ObjectHeaders,               // Content-Type 等标准头和用户元数据
+  pub last_modified: i64,                   // 最后修改时间
+  pub location: DataLocation,               // 数据位置
+  pub tags: Vec<Tag>,                       // 对象标签
+  pub acl: AccessControlList,               // 访问控制列表，owner 为上传者
+  pub parts: Vec<u64>,                      // 分片上传时各分片的大小，用于按 partNumber 读取
+  pub version_id: String,                   // 提交时分配，同一 key 内按字典序从新到旧
+  pub null_version: bool,                   // 未开启或暂停版本控制时写入的 null 版本
+  pub encryption: Option<ObjectEncryption>, // 服务端加密的密钥信息，未加密为 None
+  pub checksum: Option<ObjectChecksum>,     // 上传时指定的附加校验值（明文）
+  pub lock: ObjectLock,                     // Object Lock 保留设置和法律保留
+}
+
+impl_redb_value!(ObjectMeta, "ObjectMeta", 1);
+
+impl ObjectMeta {
+  pub fn is_delete_marker(&self) -> bool {
+    self.location == DataLocation::DeleteMarker
+  }
+
+  /// 受 Object Lock 保护的版本不能删除或被替换，返回 ObjectLocked
+  pub fn check_object_lock(&self, now: i64, bypass_governance: bool) -> Result<(), StorageError> {
+    if self.lock.protects(now, bypass_governance) {
+      return Err(StorageError::ObjectLocked {
+        key: self.key.clone(),
+      });
+    }
+    Ok(())
+  }
+
+  /// 对外的版本 ID，null 版本为 `null`
+  pub fn s3_version_id(&self) -> &str {
+    if self.null_version {
+      "null"
+    } else {
+      &self.version_id
+    }
+  }
+}
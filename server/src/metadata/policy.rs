@@ -1,10 +1,95 @@
-use crate::metadata::constant::{Action, Effect};
+use crate::metadata::constant::Effect;
 use bincode::{Decode, Encode};
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Decode, Encode)]
+/// bucket 策略：保存用户提交的原始 JSON 用于 GetBucketPolicy 原样返回，以及解析后的语句用于求值
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, Decode, Encode)]
 pub struct BucketPolicy {
+  pub document: String,
+  pub statements: Vec<PolicyStatement>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Decode, Encode)]
+pub struct PolicyStatement {
+  pub sid: Option<String>,
   pub effect: Effect,          // "Allow" or "Deny"
-  pub actions: Vec<Action>,    // 允许的操作，比如 GetObject、PutObject
-  pub resources: Vec<String>,  // 作用资源，比如 bucket/object 前缀
-  pub principals: Vec<String>, // 可以是用户 ID、角色 ID，或 "*" 表示匿名
+  pub principals: Vec<String>, // access key、IAM 用户 ARN，或 "*" 表示所有人（含匿名）
+  pub not_principal: bool,     // principals 来自 NotPrincipal
+  pub actions: Vec<String>,    // 小写的权限名，支持通配，如 s3:get*
+  pub not_action: bool,        // actions 来自 NotAction
+  pub resources: Vec<String>,  // 资源 ARN，支持 `*` `?` 通配，如 arn:aws:s3:::bucket/logs/*
+  pub not_resource: bool,      // resources 来自 NotResource
+  pub conditions: Vec<Condition>,
+}
+
+/// `"Condition": {"StringLike": {"s3:prefix": ["home/*"]}}` 中的一项
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Decode, Encode)]
+pub struct Condition {
+  pub operator: ConditionOperator,
+  /// 带 `IfExists` 后缀：请求中没有该键时视为满足
+  pub if_exists: bool,
+  /// 条件键不区分大小写，统一存为小写
+  pub key: String,
+  pub values: Vec<String>,
+}
+
+#[derive(
+  serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Decode, Encode,
+)]
+pub enum ConditionOperator {
+  StringEquals,
+  StringNotEquals,
+  StringEqualsIgnoreCase,
+  StringNotEqualsIgnoreCase,
+  StringLike,
+  StringNotLike,
+  NumericEquals,
+  NumericNotEquals,
+  NumericLessThan,
+  NumericLessThanEquals,
+  NumericGreaterThan,
+  NumericGreaterThanEquals,
+  Bool,
+  IpAddress,
+  NotIpAddress,
+  Null,
+}
+
+impl ConditionOperator {
+  const NAMES: &[(&str, ConditionOperator)] = &[
+    ("StringEquals", Self::StringEquals),
+    ("StringNotEquals", Self::StringNotEquals),
+    ("StringEqualsIgnoreCase", Self::StringEqualsIgnoreCase),
+    ("StringNotEqualsIgnoreCase", Self::StringNotEqualsIgnoreCase),
+    ("StringLike", Self::StringLike),
+    ("StringNotLike", Self::StringNotLike),
+    ("NumericEquals", Self::NumericEquals),
+    ("NumericNotEquals", Self::NumericNotEquals),
+    ("NumericLessThan", Self::NumericLessThan),
+    ("NumericLessThanEquals", Self::NumericLessThanEquals),
+    ("NumericGreaterThan", Self::NumericGreaterThan),
+    ("NumericGreaterThanEquals", Self::NumericGreaterThanEquals),
+    ("Bool", Self::Bool),
+    ("IpAddress", Self::IpAddress),
+    ("NotIpAddress", Self::NotIpAddress),
+    ("Null", Self::Null),
+  ];
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::NAMES
+      .iter()
+      .find(|(operator, _)| *operator == name)
+      .map(|(_, operator)| *operator)
+  }
+
+  /// 否定类运算符：请求中没有该键时视为满足
+  pub fn is_negated(&self) -> bool {
+    matches!(
+      self,
+      Self::StringNotEquals
+        | Self::StringNotEqualsIgnoreCase
+        | Self::StringNotLike
+        | Self::NumericNotEquals
+        | Self::NotIpAddress
+    )
+  }
 }
@@ -0,0 +1,67 @@
+use crate::error::StorageError;
+use bincode::{Decode, Encode};
+use std::collections::HashSet;
+
+/// 对象最多 10 个标签，bucket 最多 50 个
+pub const MAX_OBJECT_TAGS: usize = 10;
+pub const MAX_BUCKET_TAGS: usize = 50;
+pub const MAX_TAG_KEY_LENGTH: usize = 128;
+pub const MAX_TAG_VALUE_LENGTH: usize = 256;
+
+/// 对象或 bucket 的标签，供生命周期规则和策略条件匹配
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct Tag {
+  pub key: String,
+  pub value: String,
+}
+
+impl Tag {
+  pub fn new(key: impl Into<String>, value: impl Into<String>) -> Self {
+    Self {
+      key: key.into(),
+      value: value.into(),
+    }
+  }
+}
+
+/// 按 S3 的限制检查标签：数量、键值长度（按字符计）、键不重复且不使用 `aws:` 前缀
+pub fn validate_tags(tags: &[Tag], max_tags: usize) -> Result<(), StorageError> {
+  let invalid = |reason| Err(StorageError::InvalidTag { reason });
+  if tags.len() > max_tags {
+    return invalid("The TagSet exceeds the maximum number of tags");
+  }
+  let mut keys = HashSet::new();
+  for tag in tags {
+    if tag.key.is_empty() || tag.key.chars().count() > MAX_TAG_KEY_LENGTH {
+      return invalid("The TagKey you have provided is invalid");
+    }
+    if tag.value.chars().count() > MAX_TAG_VALUE_LENGTH {
+      return invalid("The TagValue you have provided is invalid");
+    }
+    if tag.key.starts_with("aws:") {
+      return invalid("System tags cannot be added or updated");
+    }
+    if !keys.insert(tag.key.as_str()) {
+      return invalid("Cannot provide multiple Tags with the same key");
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{MAX_OBJECT_TAGS, Tag, validate_tags};
+
+  #[test]
+  fn tag_limits() {
+    let tags: Vec<Tag> = (0..10).map(|i| Tag::new(format!("k{i}"), "v")).collect();
+    assert!(validate_tags(&tags, MAX_OBJECT_TAGS).is_ok());
+    let mut too_many = tags.clone();
+    too_many.push(Tag::new("k10", ""));
+    assert!(validate_tags(&too_many, MAX_OBJECT_TAGS).is_err());
+    assert!(validate_tags(&[Tag::new("a", "1"), Tag::new("a", "2")], 10).is_err());
+    assert!(validate_tags(&[Tag::new("aws:x", "1")], 10).is_err());
+    assert!(validate_tags(&[Tag::new("é".repeat(128), "v".repeat(256))], 10).is_ok());
+    assert!(validate_tags(&[Tag::new("k".repeat(129), "")], 10).is_err());
+  }
+}
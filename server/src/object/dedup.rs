@@ -0,0 +1,368 @@
+//! 开启 dedup 的 bucket 把对象数据按固定大小切块，每个块以 SHA-256 命名只保存一份，
+//! CHUNK_TABLE 记录引用计数；计数在增删对象版本的同一个写事务中调整。
+//! 写入或复制中、尚未提交元数据的块在内存中 pin 住，回收任务只删除计数为 0 且没有被 pin 的块。
+use crate::metadata::CHUNK_TABLE;
+use crate::metadata::object_meta::DataLocation;
+use crate::object::reader::Segment;
+use crate::object::{ObjectManager, write_file};
+use crate::storage::Storage;
+use anyhow::Result;
+use md5::Md5;
+use redb::{ReadableTable, WriteTransaction};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// 块大小 1 MiB；已写入的对象在元数据中记录自己的块大小，修改不影响已有数据
+pub const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// 写入中的块及其 pin 计数
+#[derive(Default)]
+pub(crate) struct ChunkPins(Mutex<HashMap<String, usize>>);
+
+impl ChunkPins {
+  fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, usize>> {
+    self.0.lock().unwrap_or_else(|err| err.into_inner())
+  }
+}
+
+/// 在元数据提交前保护块不被回收，drop 时解除
+pub(crate) struct PinGuard<'a> {
+  pins: &'a ChunkPins,
+  hashes: Vec<String>,
+}
+
+impl<'a> PinGuard<'a> {
+  pub fn new(pins: &'a ChunkPins) -> Self {
+    Self {
+      pins,
+      hashes: Vec::new(),
+    }
+  }
+
+  fn pin(&mut self, hash: &str) {
+    *self.pins.lock().entry(hash.to_string()).or_default() += 1;
+    self.hashes.push(hash.to_string());
+  }
+}
+
+impl Drop for PinGuard<'_> {
+  fn drop(&mut self) {
+    let mut pins = self.pins.lock();
+    for hash in &self.hashes {
+      if let Some(count) = pins.get_mut(hash) {
+        *count -= 1;
+        if *count == 0 {
+          pins.remove(hash);
+        }
+      }
+    }
+  }
+}
+
+/// 在事务中按 delta 调整对象数据块的引用计数，不是去重存储的对象不做任何事；
+/// delta 为 0 时只登记块，使没有提交的写入留下的块也能被回收
+pub(crate) fn adjust_chunk_refs(
+  write_txn: &WriteTransaction,
+  location: &DataLocation,
+  delta: i64,
+) -> Result<()> {
+  let DataLocation::Chunks { hashes, .. } = location else {
+    return Ok(());
+  };
+  let mut table = write_txn.open_table(CHUNK_TABLE)?;
+  for hash in hashes {
+    let count = table.get(hash.as_str())?.map_or(0, |count| count.value());
+    table.insert(hash.as_str(), count.saturating_add_signed(delta))?;
+  }
+  Ok(())
+}
+
+impl ObjectManager {
+  fn chunk_path(&self, hash: &str) -> PathBuf {
+    self.data_dir.join("chunks").join(&hash[..2]).join(hash)
+  }
+
+  /// 切块写入，已存在的块不再重复写；返回 (位置, 大小, MD5)，写入的块记录在 pins 中。
+  /// 中途失败时已写入的块还没有被元数据引用，登记到 CHUNK_TABLE 交给回收任务
+  pub(crate) async fn write_chunks<R: AsyncRead + Unpin>(
+    &self,
+    reader: R,
+    pins: &mut PinGuard<'_>,
+  ) -> Result<(DataLocation, u64, [u8; 16])> {
+    let mut hashes = Vec::new();
+    let written = self.write_chunk_stream(reader, pins, &mut hashes).await;
+    let location = DataLocation::Chunks {
+      chunk_size: CHUNK_SIZE as u64,
+      hashes,
+    };
+    match written {
+      Ok((size, md5)) => Ok((location, size, md5)),
+      Err(err) => {
+        self.remove_data(&location).await;
+        Err(err)
+      }
+    }
+  }
+
+  /// 逐块写入，已写入的块追加到 hashes；返回 (大小, MD5)
+  async fn write_chunk_stream<R: AsyncRead + Unpin>(
+    &self,
+    mut reader: R,
+    pins: &mut PinGuard<'_>,
+    hashes: &mut Vec<String>,
+  ) -> Result<(u64, [u8; 16])> {
+    let mut md5 = Md5::new();
+    let mut size = 0u64;
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    loop {
+      let mut filled = 0;
+      while filled < CHUNK_SIZE {
+        let n = reader.read(&mut buffer[filled..]).await?;
+        if n == 0 {
+          break;
+        }
+        filled += n;
+      }
+      if filled == 0 {
+        break;
+      }
+      let chunk = &buffer[..filled];
+      md5.update(chunk);
+      let hash = hex::encode(Sha256::digest(chunk));
+      // 先 pin 再检查块是否存在，回收任务不会在这之后删除它
+      pins.pin(&hash);
+      self.write_chunk(&hash, chunk).await?;
+      hashes.push(hash);
+      size += filled as u64;
+      if filled < CHUNK_SIZE {
+        break;
+      }
+    }
+    Ok((size, md5.finalize().into()))
+  }
+
+  async fn write_chunk(&self, hash: &str, data: &[u8]) -> Result<()> {
+    let path = self.chunk_path(hash);
+    if tokio::fs::try_exists(&path).await? {
+      return Ok(());
+    }
+    // 先写临时文件再改名，并发写入同一个块时读者不会看到不完整的块
+    let temp = path.with_extension(format!("{}.tmp", Uuid::now_v7().simple()));
+    write_file(&temp, data).await?;
+    if let Err(err) = tokio::fs::rename(&temp, &path).await {
+      let _ = tokio::fs::remove_file(&temp).await;
+      return Err(err.into());
+    }
+    Ok(())
+  }
+
+  /// 复制去重对象前 pin 住源对象的块；块已被回收（源对象已删除）时返回 false
+  pub(crate) fn pin_chunks(&self, hashes: &[String], pins: &mut PinGuard<'_>) -> Result<bool> {
+    for hash in hashes {
+      pins.pin(hash);
+    }
+    let read_txn = self.db.begin_read()?;
+    let table = read_txn.open_table(CHUNK_TABLE)?;
+    for hash in hashes {
+      if table.get(hash.as_str())?.is_none() {
+        return Ok(false);
+      }
+    }
+    Ok(true)
+  }
+
+  /// range 覆盖的各个块中的片段
+  pub(crate) fn chunk_segments(
+    &self,
+    chunk_size: u64,
+    hashes: &[String],
+    range: Range<u64>,
+  ) -> Vec<Segment> {
+    let first = (range.start / chunk_size) as usize;
+    let mut segments = Vec::new();
+    for (index, hash) in hashes.iter().enumerate().skip(first) {
+      let chunk_start = index as u64 * chunk_size;
+      if chunk_start >= range.end {
+        break;
+      }
+      let start = range.start.max(chunk_start) - chunk_start;
+      let end = range.end.min(chunk_start + chunk_size) - chunk_start;
+      segments.push(Segment {
+        path: self.chunk_path(hash),
+        offset: start,
+        len: end.saturating_sub(start),
+      });
+    }
+    segments
+  }
+
+  /// 回收引用计数为 0 且没有被写入中的对象 pin 住的块，返回回收的数量
+  pub fn sweep_chunks(&self) -> Result<usize> {
+    // 整个回收过程持有 pin 锁，写入方 pin 之后看到的块不会再被删除
+    let pins = self.chunk_pins.lock();
+    let write_txn = self.db.begin_write()?;
+    let mut unreferenced = Vec::new();
+    {
+      let mut table = write_txn.open_table(CHUNK_TABLE)?;
+      for entry in table.iter()? {
+        let (hash, count) = entry?;
+        if count.value() == 0 && !pins.contains_key(hash.value()) {
+          unreferenced.push(hash.value().to_string());
+        }
+      }
+      for hash in &unreferenced {
+        table.remove(hash.as_str())?;
+      }
+    }
+    write_txn.commit()?;
+    for hash in &unreferenced {
+      if let Err(err) = std::fs::remove_file(self.chunk_path(hash))
+        && err.kind() != std::io::ErrorKind::NotFound
+      {
+        warn!("failed to remove chunk {}: {}", hash, err);
+      }
+    }
+    Ok(unreferenced.len())
+  }
+}
+
+/// 周期性回收没有引用的块
+pub fn spawn_chunk_sweeper(storage: Arc<Storage>, interval: Duration) {
+  tokio::spawn(async move {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+      ticker.tick().await;
+      let storage = storage.clone();
+      let swept = tokio::task::spawn_blocking(move || storage.objects.sweep_chunks()).await;
+      match swept.map_err(anyhow::Error::from).and_then(|swept| swept) {
+        Ok(0) => {}
+        Ok(swept) => info!("reclaimed {} unreferenced chunks", swept),
+        Err(err) => warn!("chunk sweep failed: {:?}", err),
+      }
+    }
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::CHUNK_SIZE;
+  use crate::bucket::CreateBucketOptions;
+  use crate::metadata::CHUNK_TABLE;
+  use crate::object::PutOptions;
+  use crate::storage::test_storage;
+  use redb::ReadableTableMetadata;
+  use std::pin::Pin;
+  use std::task::{Context, Poll};
+  use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+
+  struct Broken;
+
+  impl AsyncRead for Broken {
+    fn poll_read(
+      self: Pin<&mut Self>,
+      _: &mut Context<'_>,
+      _: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+      Poll::Ready(Err(std::io::Error::other("broken")))
+    }
+  }
+
+  #[tokio::test]
+  async fn shared_chunks_and_sweep() {
+    let (dir, storage) = test_storage(CreateBucketOptions {
+      dedup: true,
+      ..Default::default()
+    });
+    let objects = &storage.objects;
+    let chunk_count = || {
+      let read_txn = objects.db.begin_read().unwrap();
+      read_txn.open_table(CHUNK_TABLE).unwrap().len().unwrap()
+    };
+
+    // 2.5 个块，两个对象内容相同
+    let data: Vec<u8> = (0..CHUNK_SIZE * 5 / 2).map(|i| (i % 251) as u8).collect();
+    let a = objects
+      .put_object("bkt", "a", PutOptions::default(), &data[..])
+      .await
+      .unwrap();
+    let b = objects
+      .put_object("bkt", "b", PutOptions::default(), &data[..])
+      .await
+      .unwrap();
+    assert_eq!(a.etag, b.etag);
+    assert_eq!(a.location, b.location);
+    assert_eq!(chunk_count(), 3);
+
+    // 跨块读取
+    let start = CHUNK_SIZE as u64 - 10;
+    let mut read = Vec::new();
+    objects
+      .open_object(&b, start..start + 20, None)
+      .await
+      .unwrap()
+      .read_to_end(&mut read)
+      .await
+      .unwrap();
+    assert_eq!(read, data[start as usize..start as usize + 20]);
+
+    let copy = objects
+      .copy_object(&a, None, "bkt", "c", PutOptions::default())
+      .await
+      .unwrap();
+    assert_eq!(copy.location, a.location);
+
+    // 仍有引用的块不会被回收
+    objects
+      .delete_object("bkt", "a", None, false)
+      .await
+      .unwrap();
+    objects
+      .delete_object("bkt", "b", None, false)
+      .await
+      .unwrap();
+    assert_eq!(objects.sweep_chunks().unwrap(), 0);
+    let mut read = Vec::new();
+    let copy = objects.head_object("bkt", "c").unwrap();
+    objects
+      .open_object(&copy, 0..copy.size, None)
+      .await
+      .unwrap()
+      .read_to_end(&mut read)
+      .await
+      .unwrap();
+    assert_eq!(read, data);
+
+    objects
+      .delete_object("bkt", "c", None, false)
+      .await
+      .unwrap();
+    assert_eq!(objects.sweep_chunks().unwrap(), 3);
+    assert_eq!(chunk_count(), 0);
+
+    // 读取中途失败，已写入的块登记后被回收
+    let reader = (&data[..CHUNK_SIZE + 10]).chain(Broken);
+    assert!(
+      objects
+        .put_object("bkt", "d", PutOptions::default(), reader)
+        .await
+        .is_err()
+    );
+    assert_eq!(chunk_count(), 1);
+    assert_eq!(objects.sweep_chunks().unwrap(), 1);
+    assert!(
+      !dir
+        .path()
+        .join("objects/chunks")
+        .read_dir()
+        .unwrap()
+        .any(|dir| { dir.unwrap().path().read_dir().unwrap().next().is_some() })
+    );
+  }
+}
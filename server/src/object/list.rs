@@ -0,0 +1,208 @@
+use crate::bucket::no_such_bucket;
+use crate::metadata::object_meta::ObjectMeta;
+use crate::metadata::{BUCKET_TABLE, OBJECT_TABLE};
+use crate::object::ObjectManager;
+use anyhow::Result;
+use std::ops::Bound;
+
+#[derive(Debug, Clone)]
+pub struct ListOptions {
+  pub prefix: String,
+  pub delimiter: Option<String>,
+  /// 从该 key（或上一页返回的公共前缀）之后开始列举，不包含它本身
+  pub start_after: Option<String>,
+  pub max_keys: usize,
+}
+
+impl Default for ListOptions {
+  fn default() -> Self {
+    Self {
+      prefix: String::new(),
+      delimiter: None,
+      start_after: None,
+      max_keys: 1000,
+    }
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct ListPage {
+  pub objects: Vec<ObjectMeta>,
+  pub common_prefixes: Vec<String>,
+  pub is_truncated: bool,
+  /// 本页最后返回的 key 或公共前缀，作为下一页的 start_after
+  pub next_marker: Option<String>,
+}
+
+impl ListPage {
+  fn len(&self) -> usize {
+    self.objects.len() + self.common_prefixes.len()
+  }
+}
+
+/// 大于所有以 `prefix` 开头的字符串的最小字符串，用于跳过整个公共前缀；
+/// 去掉末尾的 `char::MAX` 后把最后一个字符加一，不存在时（全部为 `char::MAX`）返回 None
+pub(crate) fn prefix_successor(prefix: &str) -> Option<String> {
+  let mut successor = prefix.trim_end_matches(char::MAX).to_string();
+  let last = successor.pop()?;
+  // 跳过代理区，U+D7FF 之后是 U+E000
+  let next = (last as u32 + 1..=char::MAX as u32).find_map(char::from_u32)?;
+  successor.push(next);
+  Some(successor)
+}
+
+impl ObjectManager {
+  /// 按 key 字典序分页列举；指定 delimiter 时把同一“目录”下的 key 折叠成公共前缀，
+  /// 并直接跳到该前缀之后继续扫描，而不是逐个遍历目录内的 key
+  pub fn list_objects(&self, bucket: &str, options: &ListOptions) -> Result<ListPage> {
+    let read_txn = self.db.begin_read()?;
+    if read_txn.open_table(BUCKET_TABLE)?.get(bucket)?.is_none() {
+      return Err(no_such_bucket(bucket));
+    }
+    let table = read_txn.open_table(OBJECT_TABLE)?;
+    let prefix = options.prefix.as_str();
+    let delimiter = options.delimiter.as_deref().filter(|d| !d.is_empty());
+
+    let mut page = ListPage::default();
+    let (mut seek, mut exclusive) = match options.start_after.as_deref() {
+      Some(start_after) if start_after >= prefix => (start_after.to_string(), true),
+      _ => (prefix.to_string(), false),
+    };
+    // 上一页以公共前缀结束时，继续跳过该前缀
+    let mut last_prefix = options.start_after.clone();
+
+    loop {
+      let lower = if exclusive {
+        Bound::Excluded((bucket, seek.as_str()))
+      } else {
+        Bound::Included((bucket, seek.as_str()))
+      };
+      let mut jump = None;
+      for entry in table.range::<(&str, &str)>((lower, Bound::Unbounded))? {
+        let (k, v) = entry?;
+        let (entry_bucket, key) = k.value();
+        if entry_bucket != bucket || !key.starts_with(prefix) {
+          return Ok(page);
+        }
+
+        let common_prefix = delimiter.and_then(|d| {
+          key[prefix.len()..]
+            .find(d)
+            .map(|pos| key[..prefix.len() + pos + d.len()].to_string())
+        });
+        if let Some(common_prefix) = common_prefix {
+          if last_prefix.as_deref() != Some(common_prefix.as_str()) {
+            if page.len() == options.max_keys {
+              page.is_truncated = true;
+              return Ok(page);
+            }
+            page.common_prefixes.push(common_prefix.clone());
+            page.next_marker = Some(common_prefix.clone());
+          }
+          jump = Some(common_prefix);
+          break;
+        }
+
+        if page.len() == options.max_keys {
+          page.is_truncated = true;
+          return Ok(page);
+        }
+        page.next_marker = Some(key.to_string());
+        page.objects.push(v.value());
+      }
+
+      let Some(common_prefix) = jump else {
+        return Ok(page);
+      };
+      match prefix_successor(&common_prefix) {
+        Some(successor) => seek = successor,
+        None => return Ok(page),
+      }
+      exclusive = false;
+      last_prefix = Some(common_prefix);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::ListOptions;
+  use crate::storage::test_storage;
+
+  #[tokio::test]
+  async fn delimiter_and_paging() {
+    let (_dir, storage) = test_storage(Default::default());
+    for key in ["a.txt", "dir/1", "dir/2", "dir/sub/3", "docs/x", "z.txt"] {
+      storage
+        .objects
+        .put_object("bkt", key, Default::default(), &b"x"[..])
+        .await
+        .unwrap();
+    }
+
+    let mut options = ListOptions {
+      delimiter: Some("/".into()),
+      max_keys: 2,
+      ..Default::default()
+    };
+    let mut seen = Vec::new();
+    loop {
+      let page = storage.objects.list_objects("bkt", &options).unwrap();
+      seen.extend(page.objects.iter().map(|o| o.key.clone()));
+      seen.extend(page.common_prefixes.iter().cloned());
+      if !page.is_truncated {
+        break;
+      }
+      options.start_after = page.next_marker;
+    }
+    seen.sort();
+    assert_eq!(seen, ["a.txt", "dir/", "docs/", "z.txt"]);
+
+    let options = ListOptions {
+      prefix: "dir/".into(),
+      delimiter: Some("/".into()),
+      ..Default::default()
+    };
+    let page = storage.objects.list_objects("bkt", &options).unwrap();
+    let keys: Vec<_> = page.objects.iter().map(|o| o.key.as_str()).collect();
+    assert_eq!(keys, ["dir/1", "dir/2"]);
+    assert_eq!(page.common_prefixes, ["dir/sub/"]);
+    assert!(!page.is_truncated);
+  }
+
+  #[tokio::test]
+  async fn delimiter_with_max_char() {
+    let (_dir, storage) = test_storage(Default::default());
+    for key in ["dir/\u{10FFFF}", "dir/\u{10FFFF}/x", "dir0", "\u{10FFFF}/y"] {
+      storage
+        .objects
+        .put_object("bkt", key, Default::default(), &b"x"[..])
+        .await
+        .unwrap();
+    }
+    let options = ListOptions {
+      delimiter: Some("/".into()),
+      ..Default::default()
+    };
+    let page = storage.objects.list_objects("bkt", &options).unwrap();
+    let keys: Vec<_> = page.objects.iter().map(|o| o.key.as_str()).collect();
+    assert_eq!(keys, ["dir0"]);
+    assert_eq!(page.common_prefixes, ["dir/", "\u{10FFFF}/"]);
+
+    // 分隔符本身是 U+10FFFF 时前缀没有后继
+    let options = ListOptions {
+      delimiter: Some("\u{10FFFF}".into()),
+      ..Default::default()
+    };
+    let page = storage.objects.list_objects("bkt", &options).unwrap();
+    assert_eq!(page.common_prefixes, ["dir/\u{10FFFF}", "\u{10FFFF}"]);
+    assert_eq!(page.objects.len(), 1);
+
+    assert_eq!(super::prefix_successor("a/").as_deref(), Some("a0"));
+    assert_eq!(
+      super::prefix_successor("a\u{D7FF}").as_deref(),
+      Some("a\u{E000}")
+    );
+    assert_eq!(super::prefix_successor("\u{10FFFF}"), None);
+  }
+}
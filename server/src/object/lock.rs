@@ -0,0 +1,267 @@
+//! Object Lock：修改对象版本的保留设置和法律保留。删除和替换版本时的保护检查在 `version` 中进行。
+use crate::error::StorageError;
+use crate::metadata::object_lock::Retention;
+use crate::metadata::object_meta::ObjectMeta;
+use crate::metadata::{OBJECT_TABLE, VERSION_TABLE};
+use crate::object::ObjectManager;
+use crate::object::version::{find_version, txn_bucket_config};
+use anyhow::Result;
+use redb::ReadableTable;
+
+impl ObjectManager {
+  /// 设置或移除（`None`）版本的保留设置，version_id 缺省为当前版本。
+  /// 生效中的保留只能延长，缩短或移除治理模式的保留需要 `bypass_governance`
+  pub fn put_object_retention(
+    &self,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+    retention: Option<Retention>,
+    bypass_governance: bool,
+  ) -> Result<ObjectMeta> {
+    let now = chrono::Utc::now().timestamp();
+    if retention
+      .as_ref()
+      .is_some_and(|retention| !retention.is_active(now))
+    {
+      return Err(
+        StorageError::InvalidRetention {
+          reason: "The retain until date must be in the future!",
+        }
+        .into(),
+      );
+    }
+    self.update_object_lock(bucket, key, version_id, |meta| {
+      if let Some(current) = &meta.lock.retention
+        && !current.permits_change(retention.as_ref(), now, bypass_governance)
+      {
+        return Err(StorageError::ObjectLocked {
+          key: meta.key.clone(),
+        });
+      }
+      meta.lock.retention = retention;
+      Ok(())
+    })
+  }
+
+  /// 开启或解除版本的法律保留，version_id 缺省为当前版本
+  pub fn put_object_legal_hold(
+    &self,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+    legal_hold: bool,
+  ) -> Result<ObjectMeta> {
+    self.update_object_lock(bucket, key, version_id, |meta| {
+      meta.lock.legal_hold = legal_hold;
+      Ok(())
+    })
+  }
+
+  /// 在一个写事务内修改版本的 Object Lock 状态；修改的是当前版本时同步更新 OBJECT_TABLE
+  fn update_object_lock(
+    &self,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+    update: impl FnOnce(&mut ObjectMeta) -> Result<(), StorageError>,
+  ) -> Result<ObjectMeta> {
+    let write_txn = self.db.begin_write()?;
+    let meta = {
+      if txn_bucket_config(&write_txn, bucket)?.object_lock.is_none() {
+        return Err(StorageError::MissingObjectLockConfiguration.into());
+      }
+      let mut objects = write_txn.open_table(OBJECT_TABLE)?;
+      let mut versions = write_txn.open_table(VERSION_TABLE)?;
+      let found = match version_id {
+        Some(version_id) => {
+          find_version(&versions, bucket, key, version_id)?.filter(|meta| !meta.is_delete_marker())
+        }
+        None => objects.get((bucket, key))?.map(|v| v.value()),
+      };
+      let Some(mut meta) = found else {
+        return Err(
+          match version_id {
+            Some(version_id) => StorageError::NoSuchVersion {
+              key: key.to_string(),
+              version_id: version_id.to_string(),
+            },
+            None => StorageError::NoSuchKey {
+              bucket: bucket.to_string(),
+              key: key.to_string(),
+            },
+          }
+          .into(),
+        );
+      };
+      update(&mut meta)?;
+      versions.insert((bucket, key, meta.version_id.as_str()), &meta)?;
+      let current = objects
+        .get((bucket, key))?
+        .is_some_and(|current| current.value().version_id == meta.version_id);
+      if current {
+        objects.insert((bucket, key), &meta)?;
+      }
+      meta
+    };
+    write_txn.commit()?;
+    Ok(meta)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::bucket::CreateBucketOptions;
+  use crate::error::StorageError;
+  use crate::metadata::config::VersioningStatus;
+  use crate::metadata::object_lock::{
+    DefaultRetention, ObjectLockConfiguration, Retention, RetentionMode, RetentionPeriod,
+  };
+  use crate::object::{DeleteTarget, PutOptions};
+  use crate::storage::test_storage;
+
+  fn is_locked(err: &anyhow::Error) -> bool {
+    matches!(
+      err.downcast_ref::<StorageError>(),
+      Some(StorageError::ObjectLocked { .. })
+    )
+  }
+
+  #[tokio::test]
+  async fn retention_and_legal_hold() {
+    let (_dir, storage) = test_storage(CreateBucketOptions {
+      object_lock: true,
+      ..Default::default()
+    });
+    let buckets = &storage.buckets;
+    let objects = &storage.objects;
+    buckets
+      .create_bucket("plain", "owner", Default::default())
+      .unwrap();
+    assert!(
+      buckets
+        .put_object_lock_configuration("plain", ObjectLockConfiguration::default())
+        .is_err()
+    );
+    assert!(
+      buckets
+        .put_bucket_versioning("bkt", VersioningStatus::Suspended)
+        .is_err()
+    );
+    buckets
+      .put_object_lock_configuration(
+        "bkt",
+        ObjectLockConfiguration {
+          default_retention: Some(DefaultRetention {
+            mode: RetentionMode::Governance,
+            period: RetentionPeriod::Days(1),
+          }),
+        },
+      )
+      .unwrap();
+
+    // 新版本使用默认保留：永久删除被拒绝，删除标记不受影响，绕过治理模式后可以删除
+    let v1 = objects
+      .put_object("bkt", "k", PutOptions::default(), &b"v1"[..])
+      .await
+      .unwrap();
+    let retention = v1.lock.retention.clone().unwrap();
+    assert_eq!(retention.mode, RetentionMode::Governance);
+    let err = objects
+      .delete_object("bkt", "k", Some(&v1.version_id), false)
+      .await
+      .unwrap_err();
+    assert!(is_locked(&err));
+    let marker = objects
+      .delete_object("bkt", "k", None, false)
+      .await
+      .unwrap()
+      .unwrap();
+    assert!(marker.is_delete_marker());
+
+    // 法律保留在绕过治理模式时仍然生效
+    objects
+      .put_object_legal_hold("bkt", "k", Some(&v1.version_id), true)
+      .unwrap();
+    let err = objects
+      .delete_object("bkt", "k", Some(&v1.version_id), true)
+      .await
+      .unwrap_err();
+    assert!(is_locked(&err));
+    objects
+      .put_object_legal_hold("bkt", "k", Some(&v1.version_id), false)
+      .unwrap();
+
+    // 合规模式只能延长，绕过也不能缩短
+    let compliance = Retention {
+      mode: RetentionMode::Compliance,
+      retain_until: retention.retain_until + 10,
+    };
+    objects
+      .put_object_retention("bkt", "k", Some(&v1.version_id), Some(compliance), false)
+      .unwrap();
+    let err = objects
+      .put_object_retention("bkt", "k", Some(&v1.version_id), Some(retention), true)
+      .unwrap_err();
+    assert!(is_locked(&err));
+    let err = objects
+      .delete_object("bkt", "k", Some(&v1.version_id), true)
+      .await
+      .unwrap_err();
+    assert!(is_locked(&err));
+
+    // 没有开启 Object Lock 的 bucket 不能设置保留
+    objects
+      .put_object("plain", "k", PutOptions::default(), &b"x"[..])
+      .await
+      .unwrap();
+    assert!(
+      objects
+        .put_object_legal_hold("plain", "k", None, true)
+        .is_err()
+    );
+
+    // 治理模式的版本在绕过时可以删除
+    let v2 = objects
+      .put_object("bkt", "k2", PutOptions::default(), &b"v2"[..])
+      .await
+      .unwrap();
+    objects
+      .delete_object("bkt", "k2", Some(&v2.version_id), true)
+      .await
+      .unwrap();
+
+    // 批量删除中受保护的项单独返回错误，前后的项照常删除
+    let v3 = objects
+      .put_object("bkt", "k3", PutOptions::default(), &b"v3"[..])
+      .await
+      .unwrap();
+    let v4 = objects
+      .put_object("bkt", "k4", PutOptions::default(), &b"v4"[..])
+      .await
+      .unwrap();
+    let targets = [
+      DeleteTarget {
+        key: "k3",
+        version_id: Some(&v3.version_id),
+        bypass_governance: true,
+      },
+      DeleteTarget {
+        key: "k",
+        version_id: Some(&v1.version_id),
+        bypass_governance: true,
+      },
+      DeleteTarget {
+        key: "k4",
+        version_id: Some(&v4.version_id),
+        bypass_governance: true,
+      },
+    ];
+    let results = objects.delete_objects("bkt", &targets).await.unwrap();
+    assert!(matches!(results[0], Ok(Some(_))));
+    assert!(is_locked(results[1].as_ref().unwrap_err()));
+    assert!(matches!(results[2], Ok(Some(_))));
+    assert!(objects.head_object("bkt", "k3").is_err());
+    assert!(objects.head_object("bkt", "k4").is_err());
+  }
+}
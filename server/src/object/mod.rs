@@ -0,0 +1,840 @@
+use crate::bucket::no_such_bucket;
+use crate::checksum::{ChecksumReader, ChecksumType, IntegrityCheck, ObjectChecksum};
+use crate::encryption::{EncryptReader, Encryption, WrappingKey};
+use crate::error::StorageError;
+use crate::metadata::acl::AccessControlList;
+use crate::metadata::config::BucketConfig;
+use crate::metadata::encryption::ObjectEncryption;
+use crate::metadata::object_headers::ObjectHeaders;
+use crate::metadata::object_lock::ObjectLock;
+use crate::metadata::object_meta::{DataLocation, ObjectMeta};
+use crate::metadata::tagging::{MAX_OBJECT_TAGS, Tag, validate_tags};
+use crate::metadata::{BUCKET_TABLE, OBJECT_TABLE, VERSION_TABLE};
+use crate::object::dedup::{ChunkPins, PinGuard, adjust_chunk_refs};
+use crate::object::reader::{ObjectReader, Segment, SegmentReader};
+use crate::object::sse::{check_customer_key, etag_md5, resolve_encryption, same_encryption};
+use crate::object::version::{delete_in_txn, put_version, txn_bucket_config};
+use crate::writer::object_group::ObjectGroup;
+use anyhow::Result;
+use md5::{Digest, Md5};
+use redb::{Database, ReadableTable, WriteTransaction};
+use std::io::Cursor;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+use tracing::warn;
+use uuid::Uuid;
+
+pub mod dedup;
+pub mod lifecycle;
+pub mod list;
+pub mod lock;
+pub mod multipart;
+pub mod reader;
+pub mod sse;
+pub mod version;
+
+const WRITE_BUFFER_SIZE: usize = 64 * 1024;
+/// 不超过该大小的对象打包写入 ObjectGroup，而不是单独成文件
+pub const SMALL_OBJECT_THRESHOLD: usize = 128 * 1024;
+
+/// 写入对象时的条件，在提交元数据的事务内检查，保证并发写入时的原子性
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PutCondition {
+  /// `If-None-Match: *`，只在对象不存在时创建
+  IfNoneMatch,
+  /// `If-Match`，当前对象的 ETag 一致时才覆盖（compare-and-swap）
+  IfMatch(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PutOptions {
+  pub headers: ObjectHeaders,
+  pub tags: Vec<Tag>,
+  pub acl: AccessControlList,
+  pub condition: Option<PutCondition>,
+  pub encryption: Option<Encryption>, // 未指定时使用 bucket 的默认加密
+  pub integrity: IntegrityCheck,
+  pub lock: ObjectLock, // 未指定保留设置时使用 bucket 的默认保留
+}
+
+/// 批量删除中的一项
+#[derive(Debug, Clone, Copy)]
+pub struct DeleteTarget<'a> {
+  pub key: &'a str,
+  pub version_id: Option<&'a str>,
+  pub bypass_governance: bool, // 请求方已通过 s3:BypassGovernanceRetention 授权
+}
+
+/// 对象数据写在 data_dir 下的独立文件或 ObjectGroup 组文件中，开启去重的 bucket 写成共享的数据块；
+/// 元数据保存在 redb 的 OBJECT_TABLE（当前版本）和 VERSION_TABLE（全部版本）；分片上传的中间数据暂存在 temp_dir
+pub struct ObjectManager {
+  db: Arc<Database>,
+  data_dir: PathBuf,
+  temp_dir: PathBuf,
+  group: Mutex<Option<ObjectGroup>>, // 当前正在追加的组
+  chunk_pins: ChunkPins,
+  master_key: Option<WrappingKey>, // SSE-S3 主密钥
+}
+
+impl ObjectManager {
+  pub fn new(db: Arc<Database>, data_dir: PathBuf, temp_dir: PathBuf) -> Result<Self> {
+    std::fs::create_dir_all(&data_dir)?;
+    std::fs::create_dir_all(&temp_dir)?;
+    Ok(Self {
+      db,
+      data_dir,
+      temp_dir,
+      group: Mutex::new(None),
+      chunk_pins: ChunkPins::default(),
+      master_key: None,
+    })
+  }
+
+  // 按 id 末两位分目录，避免单目录文件过多
+  fn data_path(&self, data_id: &str) -> PathBuf {
+    self
+      .data_dir
+      .join(&data_id[data_id.len() - 2..])
+      .join(data_id)
+  }
+
+  /// 流式写入对象数据，边写边计算 MD5 作为 ETag；小对象打包进 ObjectGroup，开启去重的 bucket 切块写入。
+  /// 写入后校验 Content-MD5 和附加校验值，不匹配时丢弃数据
+  pub async fn put_object<R: AsyncRead + Unpin>(
+    &self,
+    bucket: &str,
+    key: &str,
+    options: PutOptions,
+    reader: R,
+  ) -> Result<ObjectMeta> {
+    options.headers.validate()?;
+    validate_tags(&options.tags, MAX_OBJECT_TAGS)?;
+    let config = self.bucket_config(bucket)?;
+    // 数据块在元数据提交前一直 pin 住
+    let mut pins = PinGuard::new(&self.chunk_pins);
+    let mut reader = ChecksumReader::new(reader, options.integrity.algorithm);
+    let (location, size, plain_md5, etag, encryption) =
+      match resolve_encryption(&config, options.encryption) {
+        None => {
+          let (location, size, md5) = self
+            .store_data(config.dedup, &mut reader, &mut pins)
+            .await?;
+          (location, size, md5, md5, None)
+        }
+        Some(encryption) => {
+          let (kind, data_key, sealed_key) = self.new_data_key(&encryption)?;
+          let mut reader = EncryptReader::new(&mut reader, &data_key);
+          let (location, _, cipher_md5) = self
+            .store_data(config.dedup, &mut reader, &mut pins)
+            .await?;
+          let (size, plain_md5) = reader.finish();
+          let etag = etag_md5(&kind, plain_md5, cipher_md5);
+          let encryption = ObjectEncryption {
+            kind,
+            sealed_keys: vec![sealed_key],
+          };
+          (location, size, plain_md5, etag, Some(encryption))
+        }
+      };
+    let checksum = reader.finish();
+    if let Err(err) = options.integrity.verify(&plain_md5, checksum.as_deref()) {
+      self.remove_data(&location).await;
+      return Err(err.into());
+    }
+
+    let meta = ObjectMeta {
+      bucket: bucket.to_string(),
+      key: key.to_string(),
+      size,
+      etag: hex::encode(etag),
+      headers: options.headers,
+      last_modified: chrono::Utc::now().timestamp(),
+      location,
+      tags: options.tags,
+      acl: options.acl,
+      parts: Vec::new(),
+      version_id: String::new(),
+      null_version: false,
+      encryption,
+      checksum: options
+        .integrity
+        .algorithm
+        .zip(checksum)
+        .map(|(algorithm, value)| ObjectChecksum {
+          algorithm,
+          checksum_type: ChecksumType::FullObject,
+          value,
+        }),
+      lock: options.lock,
+    };
+    self.commit_object(meta, options.condition.as_ref()).await
+  }
+
+  /// bucket 的配置，bucket 不存在时返回 NoSuchBucket
+  pub(crate) fn bucket_config(&self, bucket: &str) -> Result<BucketConfig> {
+    let read_txn = self.db.begin_read()?;
+    match read_txn.open_table(BUCKET_TABLE)?.get(bucket)? {
+      Some(meta) => Ok(meta.value().config),
+      None => Err(no_such_bucket(bucket)),
+    }
+  }
+
+  /// 写入对象数据（加密时为密文），返回 (位置, 大小, MD5)
+  async fn store_data<R: AsyncRead + Unpin>(
+    &self,
+    dedup: bool,
+    reader: R,
+    pins: &mut PinGuard<'_>,
+  ) -> Result<(DataLocation, u64, [u8; 16])> {
+    if dedup {
+      self.write_chunks(reader, pins).await
+    } else {
+      self.write_data(reader).await
+    }
+  }
+
+  /// 写入非去重数据，返回 (位置, 大小, MD5)
+  async fn write_data<R: AsyncRead + Unpin>(
+    &self,
+    mut reader: R,
+  ) -> Result<(DataLocation, u64, [u8; 16])> {
+    // 先读取至多 SMALL_OBJECT_THRESHOLD + 1 字节来判断是否为小对象
+    let mut head = Vec::new();
+    (&mut reader)
+      .take(SMALL_OBJECT_THRESHOLD as u64 + 1)
+      .read_to_end(&mut head)
+      .await?;
+    Ok(if head.len() <= SMALL_OBJECT_THRESHOLD {
+      let location = self.append_to_group(&head).await?;
+      (location, head.len() as u64, Md5::digest(&head).into())
+    } else {
+      let data_id = Uuid::now_v7().simple().to_string();
+      let reader = Cursor::new(head).chain(reader);
+      let (size, md5) = write_file(&self.data_path(&data_id), reader).await?;
+      (DataLocation::File { data_id }, size, md5)
+    })
+  }
+
+  /// 服务端复制对象：独立文件通过硬链接共享数据块，组内对象和去重对象直接引用同一份数据，不重写字节。
+  /// ETag 和分片信息沿用源对象，元数据由调用方按 metadata-directive 决定。
+  /// 目标的加密方式与源对象不同时，解密后按目标的加密方式重新写入
+  pub async fn copy_object(
+    &self,
+    source: &ObjectMeta,
+    source_key: Option<&WrappingKey>,
+    bucket: &str,
+    key: &str,
+    options: PutOptions,
+  ) -> Result<ObjectMeta> {
+    options.headers.validate()?;
+    validate_tags(&options.tags, MAX_OBJECT_TAGS)?;
+    check_customer_key(source, source_key)?;
+    let encryption = resolve_encryption(&self.bucket_config(bucket)?, options.encryption.clone());
+    if !same_encryption(source.encryption.as_ref(), encryption.as_ref()) {
+      let reader = self
+        .open_copy_source(source, 0..source.size, source_key)
+        .await?;
+      // 重新写入时按源对象的算法重新计算校验值
+      let options = PutOptions {
+        encryption,
+        integrity: IntegrityCheck {
+          algorithm: source.checksum.as_ref().map(|checksum| checksum.algorithm),
+          ..Default::default()
+        },
+        ..options
+      };
+      return self.put_object(bucket, key, options, reader).await;
+    }
+    let mut pins = PinGuard::new(&self.chunk_pins);
+    let location = match &source.location {
+      DataLocation::File { data_id } => {
+        let new_id = Uuid::now_v7().simple().to_string();
+        self.link_data(source, data_id, &new_id).await?;
+        DataLocation::File { data_id: new_id }
+      }
+      // 组内空间不随对象删除回收，可以直接共享
+      DataLocation::Group { .. } => source.location.clone(),
+      DataLocation::Chunks { hashes, .. } => {
+        // 源对象在读取元数据后被删除且数据块已回收
+        if !self.pin_chunks(hashes, &mut pins)? {
+          return Err(
+            StorageError::NoSuchKey {
+              bucket: source.bucket.clone(),
+              key: source.key.clone(),
+            }
+            .into(),
+          );
+        }
+        source.location.clone()
+      }
+      DataLocation::DeleteMarker => anyhow::bail!("cannot copy a delete marker: {}", source.key),
+    };
+    let meta = ObjectMeta {
+      bucket: bucket.to_string(),
+      key: key.to_string(),
+      size: source.size,
+      etag: source.etag.clone(),
+      headers: options.headers,
+      last_modified: chrono::Utc::now().timestamp(),
+      location,
+      tags: options.tags,
+      acl: options.acl,
+      parts: source.parts.clone(),
+      version_id: String::new(),
+      null_version: false,
+      encryption: source.encryption.clone(),
+      checksum: source.checksum.clone(),
+      lock: options.lock,
+    };
+    self.commit_object(meta, options.condition.as_ref()).await
+  }
+
+  /// 为源数据文件创建硬链接；文件系统不支持时退回为复制
+  async fn link_data(&self, source: &ObjectMeta, data_id: &str, new_id: &str) -> Result<()> {
+    let from = self.data_path(data_id);
+    let to = self.data_path(new_id);
+    if let Some(parent) = to.parent() {
+      tokio::fs::create_dir_all(parent).await?;
+    }
+    match tokio::fs::hard_link(&from, &to).await {
+      Ok(()) => Ok(()),
+      // 源对象在读取元数据后被并发删除
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => Err(
+        StorageError::NoSuchKey {
+          bucket: source.bucket.clone(),
+          key: source.key.clone(),
+        }
+        .into(),
+      ),
+      Err(err) => {
+        warn!("hard link {} failed, copying data: {}", data_id, err);
+        write_file(&to, File::open(&from).await?).await?;
+        Ok(())
+      }
+    }
+  }
+
+  async fn append_to_group(&self, data: &[u8]) -> Result<DataLocation> {
+    let mut current = self.group.lock().await;
+    let mut group = match current.take() {
+      Some(group) if !group.is_full() => group,
+      _ => {
+        let group_id = Uuid::now_v7().simple().to_string();
+        ObjectGroup::create(group_id.clone(), &self.data_path(&group_id)).await?
+      }
+    };
+    // 写入失败的组不再复用，下次写入时新建
+    let offset = group.add_file(data).await?;
+    let location = DataLocation::Group {
+      group_id: group.group_id.clone(),
+      offset,
+    };
+    *current = Some(group);
+    Ok(location)
+  }
+
+  /// 数据已落盘后提交元数据；失败时清理新数据，替换 null 版本时清理旧数据
+  async fn commit_object(
+    &self,
+    mut meta: ObjectMeta,
+    condition: Option<&PutCondition>,
+  ) -> Result<ObjectMeta> {
+    let replaced = match self.insert_meta(&mut meta, condition) {
+      Ok(replaced) => replaced,
+      Err(err) => {
+        self.remove_data(&meta.location).await;
+        return Err(err);
+      }
+    };
+    // 覆盖写入时，旧数据在元数据提交后再清理
+    if let Some(replaced) = replaced {
+      self.remove_data(&replaced.location).await;
+    }
+    Ok(meta)
+  }
+
+  fn insert_meta(
+    &self,
+    meta: &mut ObjectMeta,
+    condition: Option<&PutCondition>,
+  ) -> Result<Option<ObjectMeta>> {
+    let write_txn = self.db.begin_write()?;
+    let replaced = insert_object_meta(&write_txn, meta, condition)?;
+    write_txn.commit()?;
+    Ok(replaced)
+  }
+
+  pub fn head_object(&self, bucket: &str, key: &str) -> Result<ObjectMeta> {
+    let read_txn = self.db.begin_read()?;
+    let table = read_txn.open_table(OBJECT_TABLE)?;
+    match table.get((bucket, key))? {
+      Some(meta) => Ok(meta.value()),
+      None if read_txn.open_table(BUCKET_TABLE)?.get(bucket)?.is_none() => {
+        Err(no_such_bucket(bucket))
+      }
+      None => Err(
+        StorageError::NoSuchKey {
+          bucket: bucket.to_string(),
+          key: key.to_string(),
+        }
+        .into(),
+      ),
+    }
+  }
+
+  /// 替换对象的全部标签，空列表即删除标签；返回更新后的元数据
+  pub fn put_object_tagging(&self, bucket: &str, key: &str, tags: Vec<Tag>) -> Result<ObjectMeta> {
+    validate_tags(&tags, MAX_OBJECT_TAGS)?;
+    self.update_object_meta(bucket, key, |meta| meta.tags = tags)
+  }
+
+  /// 替换对象的 ACL
+  pub fn put_object_acl(
+    &self,
+    bucket: &str,
+    key: &str,
+    acl: AccessControlList,
+  ) -> Result<ObjectMeta> {
+    self.update_object_meta(bucket, key, |meta| meta.acl = acl)
+  }
+
+  /// 在一个写事务内读取、修改并写回当前版本的元数据，不改动数据和 last_modified
+  fn update_object_meta(
+    &self,
+    bucket: &str,
+    key: &str,
+    update: impl FnOnce(&mut ObjectMeta),
+  ) -> Result<ObjectMeta> {
+    let write_txn = self.db.begin_write()?;
+    let meta = {
+      let mut table = write_txn.open_table(OBJECT_TABLE)?;
+      let current = table.get((bucket, key))?.map(|v| v.value());
+      let mut meta = match current {
+        Some(meta) => meta,
+        None => {
+          if write_txn.open_table(BUCKET_TABLE)?.get(bucket)?.is_none() {
+            return Err(no_such_bucket(bucket));
+          }
+          return Err(
+            StorageError::NoSuchKey {
+              bucket: bucket.to_string(),
+              key: key.to_string(),
+            }
+            .into(),
+          );
+        }
+      };
+      update(&mut meta);
+      table.insert((bucket, key), &meta)?;
+      write_txn
+        .open_table(VERSION_TABLE)?
+        .insert((bucket, key, meta.version_id.as_str()), &meta)?;
+      meta
+    };
+    write_txn.commit()?;
+    Ok(meta)
+  }
+
+  /// 打开对象数据并定位到 range（左闭右开），只读取所需的部分；SSE-C 对象需要提供客户密钥
+  pub async fn open_object(
+    &self,
+    meta: &ObjectMeta,
+    range: Range<u64>,
+    customer_key: Option<&WrappingKey>,
+  ) -> Result<ObjectReader> {
+    check_customer_key(meta, customer_key)?;
+    if let Some(encryption) = &meta.encryption {
+      return self
+        .open_encrypted(meta, encryption, range, customer_key)
+        .await;
+    }
+    let segments = self.data_segments(meta, range)?;
+    Ok(ObjectReader::Plain(SegmentReader::open(segments).await?))
+  }
+
+  /// 读取复制源，源对象在读取元数据后被删除时返回 NoSuchKey
+  pub async fn open_copy_source(
+    &self,
+    source: &ObjectMeta,
+    range: Range<u64>,
+    source_key: Option<&WrappingKey>,
+  ) -> Result<ObjectReader> {
+    match self.open_object(source, range, source_key).await {
+      Err(err)
+        if err
+          .downcast_ref::<std::io::Error>()
+          .is_some_and(|err| err.kind() == std::io::ErrorKind::NotFound) =>
+      {
+        Err(
+          StorageError::NoSuchKey {
+            bucket: source.bucket.clone(),
+            key: source.key.clone(),
+          }
+          .into(),
+        )
+      }
+      opened => opened,
+    }
+  }
+
+  /// 存储的数据（加密对象为密文）中 range 对应的文件片段
+  fn data_segments(&self, meta: &ObjectMeta, range: Range<u64>) -> Result<Vec<Segment>> {
+    let len = range.end.saturating_sub(range.start);
+    let segments = match &meta.location {
+      DataLocation::File { data_id } => vec![Segment {
+        path: self.data_path(data_id),
+        offset: range.start,
+        len,
+      }],
+      DataLocation::Group { group_id, offset } => vec![Segment {
+        path: self.data_path(group_id),
+        offset: offset + range.start,
+        len,
+      }],
+      DataLocation::Chunks { chunk_size, hashes } => {
+        self.chunk_segments(*chunk_size, hashes, range)
+      }
+      DataLocation::DeleteMarker => anyhow::bail!("delete marker has no data: {}", meta.key),
+    };
+    Ok(segments)
+  }
+
+  /// 删除对象或指定版本，返回被删除的版本或新建的删除标记；对象或版本不存在时返回 None
+  pub async fn delete_object(
+    &self,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+    bypass_governance: bool,
+  ) -> Result<Option<ObjectMeta>> {
+    let target = DeleteTarget {
+      key,
+      version_id,
+      bypass_governance,
+    };
+    let mut results = self.delete_objects(bucket, &[target]).await?;
+    results.pop().unwrap_or(Ok(None))
+  }
+
+  /// 批量删除，所有元数据在同一个写事务中修改；返回与 targets 一一对应的结果，
+  /// 受 Object Lock 保护的版本对应 ObjectLocked 错误，不影响其它项；其它错误使整个请求失败
+  pub async fn delete_objects(
+    &self,
+    bucket: &str,
+    targets: &[DeleteTarget<'_>],
+  ) -> Result<Vec<Result<Option<ObjectMeta>>>> {
+    let write_txn = self.db.begin_write()?;
+    // 桶不存在属于请求级错误，不展开成逐项的 NoSuchBucket
+    txn_bucket_config(&write_txn, bucket)?;
+    let mut deletions = Vec::with_capacity(targets.len());
+    for target in targets {
+      // 保护检查在修改之前，失败的项不会在事务中留下改动
+      match delete_in_txn(
+        &write_txn,
+        bucket,
+        target.key,
+        target.version_id,
+        target.bypass_governance,
+      ) {
+        Err(err) if matches!(err.downcast_ref(), Some(StorageError::ObjectLocked { .. })) => {
+          deletions.push(Err(err));
+        }
+        deletion => deletions.push(Ok(deletion?)),
+      }
+    }
+    write_txn.commit()?;
+    let mut results = Vec::with_capacity(deletions.len());
+    for deletion in deletions {
+      if let Ok(Some(removed)) = deletion.as_ref().map(|deletion| &deletion.removed) {
+        self.remove_data(&removed.location).await;
+      }
+      results.push(deletion.map(|deletion| deletion.result));
+    }
+    Ok(results)
+  }
+
+  async fn remove_data(&self, location: &DataLocation) {
+    match location {
+      DataLocation::File { data_id } => {
+        if let Err(err) = tokio::fs::remove_file(self.data_path(data_id)).await {
+          warn!("failed to remove object data {}: {}", data_id, err);
+        }
+      }
+      // 引用计数已在元数据事务中调整，这里只登记数据块，使未能提交的写入留下的块也能被回收
+      DataLocation::Chunks { .. } => {
+        let registered = (|| {
+          let write_txn = self.db.begin_write()?;
+          adjust_chunk_refs(&write_txn, location, 0)?;
+          write_txn.commit()?;
+          Ok::<_, anyhow::Error>(())
+        })();
+        if let Err(err) = registered {
+          warn!("failed to register chunks for reclaim: {}", err);
+        }
+      }
+      // 组内空间暂不回收，留给后续的组压缩处理
+      DataLocation::Group { .. } | DataLocation::DeleteMarker => {}
+    }
+  }
+}
+
+/// 在事务中写入对象的新版本并分配 version_id，返回被替换的 null 版本；条件不满足时不写入。
+/// 开启 Object Lock 的 bucket 中，未指定保留设置的新版本使用默认保留
+pub(crate) fn insert_object_meta(
+  write_txn: &WriteTransaction,
+  meta: &mut ObjectMeta,
+  condition: Option<&PutCondition>,
+) -> Result<Option<ObjectMeta>> {
+  let config = txn_bucket_config(write_txn, &meta.bucket)?;
+  match &config.object_lock {
+    None if !meta.lock.is_empty() => {
+      return Err(StorageError::MissingObjectLockConfiguration.into());
+    }
+    Some(lock) if meta.lock.retention.is_none() => {
+      meta.lock.retention = lock
+        .default_retention
+        .as_ref()
+        .map(|default| default.retention(meta.last_modified));
+    }
+    _ => {}
+  }
+  let mut table = write_txn.open_table(OBJECT_TABLE)?;
+  if let Some(condition) = condition {
+    let current = table.get((meta.bucket.as_str(), meta.key.as_str()))?;
+    match (condition, current) {
+      (PutCondition::IfNoneMatch, None) => {}
+      (PutCondition::IfMatch(etag), Some(current)) if current.value().etag == *etag => {}
+      // 与 S3 一致：If-Match 的对象不存在时返回 NoSuchKey
+      (PutCondition::IfMatch(_), None) => {
+        return Err(
+          StorageError::NoSuchKey {
+            bucket: meta.bucket.clone(),
+            key: meta.key.clone(),
+          }
+          .into(),
+        );
+      }
+      _ => return Err(StorageError::PreconditionFailed.into()),
+    }
+  }
+  let replaced = put_version(
+    &mut write_txn.open_table(VERSION_TABLE)?,
+    meta,
+    config.versioning,
+  )?;
+  table.insert((meta.bucket.as_str(), meta.key.as_str()), &*meta)?;
+  adjust_chunk_refs(write_txn, &meta.location, 1)?;
+  if let Some(replaced) = &replaced {
+    adjust_chunk_refs(write_txn, &replaced.location, -1)?;
+  }
+  Ok(replaced)
+}
+
+/// 把 reader 中的数据写入 path 并计算 MD5，返回 (大小, MD5)；失败时删除半成品文件
+pub(crate) async fn write_file<R: AsyncRead + Unpin>(
+  path: &Path,
+  mut reader: R,
+) -> Result<(u64, [u8; 16])> {
+  if let Some(parent) = path.parent() {
+    tokio::fs::create_dir_all(parent).await?;
+  }
+  let mut file = tokio::fs::File::create(path).await?;
+  let result = async {
+    let mut hasher = Md5::new();
+    let mut buffer = vec![0u8; WRITE_BUFFER_SIZE];
+    let mut size = 0u64;
+    loop {
+      let n = reader.read(&mut buffer).await?;
+      if n == 0 {
+        break;
+      }
+      hasher.update(&buffer[..n]);
+      file.write_all(&buffer[..n]).await?;
+      size += n as u64;
+    }
+    file.sync_all().await?;
+    Ok::<_, anyhow::Error>((size, hasher.finalize().into()))
+  }
+  .await;
+  if result.is_err() {
+    let _ = tokio::fs::remove_file(path).await;
+  }
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{DeleteTarget, PutCondition, PutOptions};
+  use crate::error::StorageError;
+  use crate::metadata::object_headers::ObjectHeaders;
+  use crate::metadata::object_meta::DataLocation;
+  use crate::metadata::tagging::Tag;
+  use crate::storage::test_storage;
+  use tokio::io::AsyncReadExt;
+
+  #[tokio::test]
+  async fn put_get_overwrite_delete() {
+    let (_dir, storage) = test_storage(Default::default());
+    let objects = &storage.objects;
+
+    let meta = objects
+      .put_object("bkt", "a/b.txt", PutOptions::default(), &b"hello world"[..])
+      .await
+      .unwrap();
+    assert_eq!(meta.etag, "5eb63bbbe01eeed093cb22bb8f5acdc3");
+
+    objects
+      .put_object(
+        "bkt",
+        "a/b.txt",
+        PutOptions {
+          headers: ObjectHeaders {
+            content_type: Some("text/plain".into()),
+            ..Default::default()
+          },
+          condition: Some(PutCondition::IfMatch(meta.etag.clone())),
+          ..Default::default()
+        },
+        &b"hi"[..],
+      )
+      .await
+      .unwrap();
+    let meta = objects.head_object("bkt", "a/b.txt").unwrap();
+    assert_eq!(meta.size, 2);
+    assert_eq!(meta.headers.content_type.as_deref(), Some("text/plain"));
+    // 两个小对象写在同一个组里，第二个从偏移 11 开始
+    assert!(matches!(
+      meta.location,
+      DataLocation::Group { offset: 11, .. }
+    ));
+    let mut data = Vec::new();
+    objects
+      .open_object(&meta, 1..2, None)
+      .await
+      .unwrap()
+      .read_to_end(&mut data)
+      .await
+      .unwrap();
+    assert_eq!(data, b"i");
+
+    // 条件写入：对象已存在时 If-None-Match 失败，ETag 过期时 If-Match 失败
+    for condition in [
+      PutCondition::IfNoneMatch,
+      PutCondition::IfMatch("5eb63bbbe01eeed093cb22bb8f5acdc3".into()),
+    ] {
+      let options = PutOptions {
+        condition: Some(condition),
+        ..Default::default()
+      };
+      let err = objects
+        .put_object("bkt", "a/b.txt", options, &b"lost"[..])
+        .await
+        .unwrap_err();
+      assert!(matches!(
+        err.downcast_ref::<StorageError>(),
+        Some(StorageError::PreconditionFailed)
+      ));
+    }
+    assert_eq!(
+      objects.head_object("bkt", "a/b.txt").unwrap().etag,
+      meta.etag
+    );
+
+    let mut headers = ObjectHeaders::default();
+    headers.user.insert("big".into(), "x".repeat(2048));
+    let options = PutOptions {
+      headers,
+      ..Default::default()
+    };
+    let err = objects
+      .put_object("bkt", "meta", options, &b""[..])
+      .await
+      .unwrap_err();
+    assert!(matches!(
+      err.downcast_ref::<StorageError>(),
+      Some(StorageError::MetadataTooLarge { size: 2051 })
+    ));
+
+    let tagged = objects
+      .put_object_tagging("bkt", "a/b.txt", vec![Tag::new("team", "infra")])
+      .unwrap();
+    assert_eq!(tagged.etag, meta.etag);
+    assert_eq!(
+      objects.head_object("bkt", "a/b.txt").unwrap().tags,
+      [Tag::new("team", "infra")]
+    );
+    assert!(
+      objects
+        .put_object_tagging("bkt", "nope", Vec::new())
+        .is_err()
+    );
+
+    let removed = objects
+      .delete_objects(
+        "bkt",
+        &[
+          DeleteTarget {
+            key: "a/b.txt",
+            version_id: None,
+            bypass_governance: false,
+          },
+          DeleteTarget {
+            key: "missing",
+            version_id: None,
+            bypass_governance: false,
+          },
+        ],
+      )
+      .await
+      .unwrap();
+    assert!(matches!(removed[..], [Ok(Some(_)), Ok(None)]));
+    assert!(objects.head_object("bkt", "a/b.txt").is_err());
+    assert!(storage.buckets.delete_bucket("bkt").is_ok());
+    assert!(objects.head_object("bkt", "a/b.txt").is_err());
+    // 桶不存在时整个请求失败
+    let target = DeleteTarget {
+      key: "a/b.txt",
+      version_id: None,
+      bypass_governance: false,
+    };
+    assert!(objects.delete_objects("bkt", &[target]).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn copy_shares_data() {
+    let (_dir, storage) = test_storage(Default::default());
+    let objects = &storage.objects;
+
+    let big = vec![7u8; super::SMALL_OBJECT_THRESHOLD + 1];
+    for (key, data) in [("big", &big[..]), ("small", &b"tiny"[..])] {
+      let source = objects
+        .put_object("bkt", key, PutOptions::default(), data)
+        .await
+        .unwrap();
+      let copy_key = format!("{key}-copy");
+      let copy = objects
+        .copy_object(&source, None, "bkt", &copy_key, PutOptions::default())
+        .await
+        .unwrap();
+      assert_eq!(copy.etag, source.etag);
+      // 删除源对象后副本仍然可读
+      objects
+        .delete_object("bkt", key, None, false)
+        .await
+        .unwrap();
+      let mut read = Vec::new();
+      objects
+        .open_object(&copy, 0..copy.size, None)
+        .await
+        .unwrap()
+        .read_to_end(&mut read)
+        .await
+        .unwrap();
+      assert_eq!(read, data);
+    }
+  }
+}
@@ -0,0 +1,700 @@
+use crate::bucket::no_such_bucket;
+use crate::checksum::{
+  ChecksumAlgorithm, ChecksumReader, ChecksumType, IntegrityCheck, ObjectChecksum,
+  multipart_checksum,
+};
+use crate::encryption::{EncryptReader, Encryption, WrappingKey, cipher_len};
+use crate::error::StorageError;
+use crate::metadata::acl::AccessControlList;
+use crate::metadata::encryption::{EncryptionKind, ObjectEncryption};
+use crate::metadata::multipart_meta::{MultipartUpload, PartMeta};
+use crate::metadata::object_headers::ObjectHeaders;
+use crate::metadata::object_lock::ObjectLock;
+use crate::metadata::object_meta::{DataLocation, ObjectMeta};
+use crate::metadata::tagging::{MAX_OBJECT_TAGS, Tag, validate_tags};
+use crate::metadata::{BUCKET_TABLE, MULTIPART_TABLE, PART_TABLE};
+use crate::object::dedup::PinGuard;
+use crate::object::reader::{Segment, SegmentReader};
+use crate::object::sse::{etag_md5, resolve_encryption};
+use crate::object::{ObjectManager, insert_object_meta, write_file};
+use anyhow::{Result, anyhow};
+use md5::{Digest, Md5};
+use redb::{ReadableTable, WriteTransaction};
+use std::ops::Bound;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWriteExt};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// 除最后一个分片外，每个分片至少 5 MiB
+pub const MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+pub const MAX_PART_NUMBER: u32 = 10_000;
+
+#[derive(Debug, Clone, Default)]
+pub struct ListUploadsOptions {
+  pub prefix: String,
+  pub delimiter: Option<String>,
+  pub key_marker: Option<String>,
+  pub upload_id_marker: Option<String>,
+  pub max_uploads: usize,
+}
+
+/// 创建分片上传时确定、完成后写入对象的属性
+#[derive(Debug, Clone, Default)]
+pub struct UploadOptions {
+  pub headers: ObjectHeaders,
+  pub tags: Vec<Tag>,
+  pub acl: AccessControlList,
+  pub encryption: Option<Encryption>, // 未指定时使用 bucket 的默认加密
+  pub checksum: Option<(ChecksumAlgorithm, ChecksumType)>,
+  pub lock: ObjectLock,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PartOptions {
+  pub customer_key: Option<WrappingKey>, // SSE-C 上传的每个分片都需要提供同一个客户密钥
+  pub integrity: IntegrityCheck,
+}
+
+/// CompleteMultipartUpload 请求中列出的分片
+#[derive(Debug, Clone)]
+pub struct CompletedPart {
+  pub part_number: u32,
+  pub etag: String,
+  pub checksum: Option<String>, // 客户端记录的分片校验值，给出时须与上传时一致
+}
+
+#[derive(Debug, Default)]
+pub struct UploadsPage {
+  pub uploads: Vec<MultipartUpload>,
+  pub common_prefixes: Vec<String>,
+  pub is_truncated: bool,
+}
+
+#[derive(Debug)]
+pub struct PartsPage {
+  pub upload: MultipartUpload,
+  pub parts: Vec<PartMeta>,
+  pub is_truncated: bool,
+}
+
+fn no_such_upload(upload_id: &str) -> anyhow::Error {
+  StorageError::NoSuchUpload {
+    upload_id: upload_id.to_string(),
+  }
+  .into()
+}
+
+impl ObjectManager {
+  fn upload_dir(&self, upload_id: &str) -> PathBuf {
+    self.temp_dir.join("multipart").join(upload_id)
+  }
+
+  fn get_upload(&self, bucket: &str, key: &str, upload_id: &str) -> Result<MultipartUpload> {
+    let read_txn = self.db.begin_read()?;
+    let uploads = read_txn.open_table(MULTIPART_TABLE)?;
+    match uploads.get((bucket, key, upload_id))? {
+      Some(upload) => Ok(upload.value()),
+      None => Err(no_such_upload(upload_id)),
+    }
+  }
+
+  /// 创建分片上传；加密方式和校验算法在创建时确定，之后每个分片按此处理
+  pub fn create_multipart_upload(
+    &self,
+    bucket: &str,
+    key: &str,
+    options: UploadOptions,
+  ) -> Result<MultipartUpload> {
+    options.headers.validate()?;
+    validate_tags(&options.tags, MAX_OBJECT_TAGS)?;
+    if let Some((algorithm, ChecksumType::FullObject)) = options.checksum
+      && !algorithm.combinable()
+    {
+      return Err(
+        StorageError::InvalidChecksum {
+          reason: "The FULL_OBJECT checksum type can only be used with CRC checksum algorithms",
+        }
+        .into(),
+      );
+    }
+    let config = self.bucket_config(bucket)?;
+    // 尽早拒绝，避免上传完全部分片后才在完成时失败
+    if config.object_lock.is_none() && !options.lock.is_empty() {
+      return Err(StorageError::MissingObjectLockConfiguration.into());
+    }
+    let encryption = match resolve_encryption(&config, options.encryption) {
+      Some(Encryption::S3) => {
+        self.wrapping_key(&EncryptionKind::S3, None)?;
+        Some(EncryptionKind::S3)
+      }
+      Some(Encryption::Customer(key)) => Some(EncryptionKind::Customer {
+        key_md5: key.key_md5(),
+      }),
+      None => None,
+    };
+    let upload = MultipartUpload {
+      upload_id: Uuid::now_v7().simple().to_string(),
+      bucket: bucket.to_string(),
+      key: key.to_string(),
+      initiated: chrono::Utc::now().timestamp(),
+      headers: options.headers,
+      tags: options.tags,
+      acl: options.acl,
+      encryption,
+      checksum: options.checksum,
+      lock: options.lock,
+    };
+    let write_txn = self.db.begin_write()?;
+    {
+      if write_txn.open_table(BUCKET_TABLE)?.get(bucket)?.is_none() {
+        return Err(no_such_bucket(bucket));
+      }
+      let mut uploads = write_txn.open_table(MULTIPART_TABLE)?;
+      uploads.insert((bucket, key, upload.upload_id.as_str()), &upload)?;
+    }
+    write_txn.commit()?;
+    Ok(upload)
+  }
+
+  /// 写入一个分片；同一分片号重复上传时以最后一次为准。分片按上传指定的算法计算校验值
+  pub async fn upload_part<R: AsyncRead + Unpin>(
+    &self,
+    bucket: &str,
+    key: &str,
+    upload_id: &str,
+    part_number: u32,
+    options: PartOptions,
+    reader: R,
+  ) -> Result<PartMeta> {
+    let upload = self.get_upload(bucket, key, upload_id)?;
+    let customer_key = options.customer_key.as_ref();
+    if customer_key.is_some() && !matches!(upload.encryption, Some(EncryptionKind::Customer { .. }))
+    {
+      return Err(
+        StorageError::InvalidEncryption {
+          reason: "The encryption parameters are not applicable to this upload.",
+        }
+        .into(),
+      );
+    }
+    let algorithm = match (upload.checksum, options.integrity.algorithm) {
+      (Some((expected, _)), Some(algorithm)) if expected != algorithm => {
+        return Err(
+          StorageError::InvalidChecksum {
+            reason: "The checksum algorithm of the part does not match the upload",
+          }
+          .into(),
+        );
+      }
+      (upload_algorithm, algorithm) => upload_algorithm
+        .map(|(algorithm, _)| algorithm)
+        .or(algorithm),
+    };
+    let mut reader = ChecksumReader::new(reader, algorithm);
+    let file_name = format!("{}-{}", part_number, Uuid::now_v7().simple());
+    let path = self.upload_dir(upload_id).join(&file_name);
+    // 加密上传的每个分片使用各自的数据密钥
+    let (size, plain_md5, md5, sealed_key) = match &upload.encryption {
+      None => {
+        let (size, md5) = write_file(&path, &mut reader).await?;
+        (size, md5, md5, None)
+      }
+      Some(kind) => {
+        let (data_key, sealed_key) = self.wrapping_key(kind, customer_key)?.new_data_key()?;
+        let mut reader = EncryptReader::new(&mut reader, &data_key);
+        let (_, cipher_md5) = write_file(&path, &mut reader).await?;
+        let (size, plain_md5) = reader.finish();
+        (
+          size,
+          plain_md5,
+          etag_md5(kind, plain_md5, cipher_md5),
+          Some(sealed_key),
+        )
+      }
+    };
+    let checksum = reader.finish();
+    if let Err(err) = options.integrity.verify(&plain_md5, checksum.as_deref()) {
+      let _ = tokio::fs::remove_file(&path).await;
+      return Err(err.into());
+    }
+    let part = PartMeta {
+      part_number,
+      size,
+      etag: hex::encode(md5),
+      last_modified: chrono::Utc::now().timestamp(),
+      file_name,
+      sealed_key,
+      checksum,
+    };
+
+    let insert = || -> Result<Option<PartMeta>> {
+      let write_txn = self.db.begin_write()?;
+      let previous = {
+        // 上传可能已被并发地完成或中止
+        if write_txn
+          .open_table(MULTIPART_TABLE)?
+          .get((bucket, key, upload_id))?
+          .is_none()
+        {
+          return Err(no_such_upload(upload_id));
+        }
+        let mut parts = write_txn.open_table(PART_TABLE)?;
+        parts
+          .insert((upload_id, part_number), &part)?
+          .map(|v| v.value())
+      };
+      write_txn.commit()?;
+      Ok(previous)
+    };
+    match insert() {
+      Ok(Some(previous)) => self.remove_part_file(upload_id, &previous.file_name).await,
+      Ok(None) => {}
+      Err(err) => {
+        self.remove_part_file(upload_id, &part.file_name).await;
+        return Err(err);
+      }
+    }
+    Ok(part)
+  }
+
+  pub fn list_parts(
+    &self,
+    bucket: &str,
+    key: &str,
+    upload_id: &str,
+    part_number_marker: u32,
+    max_parts: usize,
+  ) -> Result<PartsPage> {
+    let upload = self.get_upload(bucket, key, upload_id)?;
+    let read_txn = self.db.begin_read()?;
+    let table = read_txn.open_table(PART_TABLE)?;
+    let mut parts = Vec::new();
+    let mut is_truncated = false;
+    for entry in table.range((
+      Bound::Excluded((upload_id, part_number_marker)),
+      Bound::Included((upload_id, u32::MAX)),
+    ))? {
+      if parts.len() == max_parts {
+        is_truncated = true;
+        break;
+      }
+      parts.push(entry?.1.value());
+    }
+    Ok(PartsPage {
+      upload,
+      parts,
+      is_truncated,
+    })
+  }
+
+  /// 按客户端给出的分片列表合并为最终对象，ETag 为各分片 MD5 拼接后的 MD5 加 `-N`
+  pub async fn complete_multipart_upload(
+    &self,
+    bucket: &str,
+    key: &str,
+    upload_id: &str,
+    requested: &[CompletedPart],
+  ) -> Result<ObjectMeta> {
+    let upload = self.get_upload(bucket, key, upload_id)?;
+    if requested.is_empty() {
+      return Err(StorageError::InvalidPart { part_number: 0 }.into());
+    }
+    if requested
+      .windows(2)
+      .any(|w| w[0].part_number >= w[1].part_number)
+    {
+      return Err(StorageError::InvalidPartOrder.into());
+    }
+
+    let mut parts = Vec::with_capacity(requested.len());
+    {
+      let read_txn = self.db.begin_read()?;
+      let table = read_txn.open_table(PART_TABLE)?;
+      for (index, completed) in requested.iter().enumerate() {
+        let part_number = completed.part_number;
+        let part = table
+          .get((upload_id, part_number))?
+          .map(|v| v.value())
+          .filter(|part| part.etag == completed.etag.trim_matches('"'))
+          .filter(|part| completed.checksum.is_none() || part.checksum == completed.checksum)
+          .ok_or(StorageError::InvalidPart { part_number })?;
+        if index + 1 < requested.len() && part.size < MIN_PART_SIZE {
+          return Err(StorageError::EntityTooSmall { part_number }.into());
+        }
+        parts.push(part);
+      }
+    }
+
+    let mut composite = Md5::new();
+    for part in &parts {
+      composite.update(hex::decode(&part.etag)?);
+    }
+    let size = parts.iter().map(|part| part.size).sum();
+    let checksum = match upload.checksum {
+      Some((algorithm, checksum_type)) => {
+        let checksums = parts
+          .iter()
+          .map(|part| match &part.checksum {
+            Some(checksum) => Ok((checksum.as_str(), part.size)),
+            None => Err(StorageError::InvalidPart {
+              part_number: part.part_number,
+            }),
+          })
+          .collect::<Result<Vec<_>, _>>()?;
+        Some(ObjectChecksum {
+          algorithm,
+          checksum_type,
+          value: multipart_checksum(algorithm, checksum_type, &checksums)?,
+        })
+      }
+      None => None,
+    };
+    // 加密上传的分片各自保留数据密钥，拼接后的密文按分片解密
+    let encryption = match upload.encryption {
+      Some(kind) => {
+        let sealed_keys = parts
+          .iter()
+          .map(|part| {
+            part.sealed_key.clone().ok_or_else(|| {
+              anyhow!(
+                "part {} of {} is not encrypted",
+                part.part_number,
+                upload_id
+              )
+            })
+          })
+          .collect::<Result<_>>()?;
+        Some(ObjectEncryption { kind, sealed_keys })
+      }
+      None => None,
+    };
+    // 按顺序拼接分片数据；开启去重时直接把分片切块写入
+    let mut pins = PinGuard::new(&self.chunk_pins);
+    let location = if self.bucket_config(bucket)?.dedup {
+      let segments = parts
+        .iter()
+        .map(|part| Segment {
+          path: self.upload_dir(upload_id).join(&part.file_name),
+          offset: 0,
+          len: match encryption {
+            Some(_) => cipher_len(part.size),
+            None => part.size,
+          },
+        })
+        .collect();
+      let reader = SegmentReader::open(segments).await?;
+      self.write_chunks(reader, &mut pins).await?.0
+    } else {
+      self.concatenate_parts(upload_id, &parts).await?
+    };
+
+    let mut meta = ObjectMeta {
+      bucket: bucket.to_string(),
+      key: key.to_string(),
+      size,
+      etag: format!("{}-{}", hex::encode(composite.finalize()), parts.len()),
+      headers: upload.headers,
+      last_modified: chrono::Utc::now().timestamp(),
+      location,
+      tags: upload.tags,
+      acl: upload.acl,
+      parts: parts.iter().map(|part| part.size).collect(),
+      version_id: String::new(),
+      null_version: false,
+      encryption,
+      checksum,
+      lock: upload.lock,
+    };
+    // 移除上传记录与写入对象元数据在同一事务中，确保同一上传只会完成一次
+    let committed = (|| {
+      let write_txn = self.db.begin_write()?;
+      if !remove_upload(&write_txn, bucket, key, upload_id)? {
+        return Err(no_such_upload(upload_id));
+      }
+      let replaced = insert_object_meta(&write_txn, &mut meta, None)?;
+      write_txn.commit()?;
+      Ok(replaced)
+    })();
+    match committed {
+      Ok(Some(replaced)) => self.remove_data(&replaced.location).await,
+      Ok(None) => {}
+      Err(err) => {
+        self.remove_data(&meta.location).await;
+        return Err(err);
+      }
+    }
+    self.remove_upload_dir(upload_id).await;
+    Ok(meta)
+  }
+
+  /// 把分片依次写入一个新的数据文件
+  async fn concatenate_parts(&self, upload_id: &str, parts: &[PartMeta]) -> Result<DataLocation> {
+    let data_id = Uuid::now_v7().simple().to_string();
+    let path = self.data_path(&data_id);
+    if let Some(parent) = path.parent() {
+      tokio::fs::create_dir_all(parent).await?;
+    }
+    let assemble = async {
+      let mut file = tokio::fs::File::create(&path).await?;
+      for part in parts {
+        let mut source =
+          tokio::fs::File::open(self.upload_dir(upload_id).join(&part.file_name)).await?;
+        tokio::io::copy(&mut source, &mut file).await?;
+      }
+      file.flush().await?;
+      file.sync_all().await?;
+      Ok::<_, anyhow::Error>(())
+    };
+    if let Err(err) = assemble.await {
+      let _ = tokio::fs::remove_file(&path).await;
+      return Err(err);
+    }
+    Ok(DataLocation::File { data_id })
+  }
+
+  pub async fn abort_multipart_upload(
+    &self,
+    bucket: &str,
+    key: &str,
+    upload_id: &str,
+  ) -> Result<()> {
+    let write_txn = self.db.begin_write()?;
+    if !remove_upload(&write_txn, bucket, key, upload_id)? {
+      return Err(no_such_upload(upload_id));
+    }
+    write_txn.commit()?;
+    self.remove_upload_dir(upload_id).await;
+    Ok(())
+  }
+
+  /// 按 (key, upload_id) 顺序列出进行中的上传
+  pub fn list_multipart_uploads(
+    &self,
+    bucket: &str,
+    options: &ListUploadsOptions,
+  ) -> Result<UploadsPage> {
+    let read_txn = self.db.begin_read()?;
+    if read_txn.open_table(BUCKET_TABLE)?.get(bucket)?.is_none() {
+      return Err(no_such_bucket(bucket));
+    }
+    let table = read_txn.open_table(MULTIPART_TABLE)?;
+    let prefix = options.prefix.as_str();
+    let delimiter = options.delimiter.as_deref().filter(|d| !d.is_empty());
+    let key_marker = options.key_marker.as_deref().unwrap_or_default();
+    let upload_id_marker = options.upload_id_marker.as_deref().unwrap_or_default();
+
+    let mut page = UploadsPage::default();
+    for entry in table.range((bucket, prefix.max(key_marker), "")..)? {
+      let (k, v) = entry?;
+      let (entry_bucket, key, upload_id) = k.value();
+      if entry_bucket != bucket || !key.starts_with(prefix) {
+        break;
+      }
+      // 没有 upload-id-marker 时跳过 key-marker 本身的全部上传
+      if !key_marker.is_empty()
+        && key == key_marker
+        && (upload_id_marker.is_empty() || upload_id <= upload_id_marker)
+      {
+        continue;
+      }
+      let common_prefix = delimiter.and_then(|d| {
+        key[prefix.len()..]
+          .find(d)
+          .map(|pos| key[..prefix.len() + pos + d.len()].to_string())
+      });
+      if common_prefix.is_some() && page.common_prefixes.last() == common_prefix.as_ref() {
+        continue;
+      }
+      if page.uploads.len() + page.common_prefixes.len() == options.max_uploads {
+        page.is_truncated = true;
+        break;
+      }
+      match common_prefix {
+        Some(common_prefix) => page.common_prefixes.push(common_prefix),
+        None => page.uploads.push(v.value()),
+      }
+    }
+    Ok(page)
+  }
+
+  /// 中止创建时间早于 max_age 的上传，返回清理的数量
+  pub async fn abort_expired_uploads(&self, max_age: Duration) -> Result<usize> {
+    let deadline = chrono::Utc::now().timestamp() - max_age.as_secs() as i64;
+    let expired = {
+      let read_txn = self.db.begin_read()?;
+      let table = read_txn.open_table(MULTIPART_TABLE)?;
+      let mut expired = Vec::new();
+      for entry in table.iter()? {
+        let upload = entry?.1.value();
+        if upload.initiated < deadline {
+          expired.push(upload);
+        }
+      }
+      expired
+    };
+
+    let mut aborted = 0;
+    for upload in expired {
+      match self
+        .abort_multipart_upload(&upload.bucket, &upload.key, &upload.upload_id)
+        .await
+      {
+        Ok(()) => aborted += 1,
+        Err(err) => warn!("failed to abort upload {}: {}", upload.upload_id, err),
+      }
+    }
+    if aborted > 0 {
+      info!("aborted {} expired multipart uploads", aborted);
+    }
+    Ok(aborted)
+  }
+
+  async fn remove_part_file(&self, upload_id: &str, file_name: &str) {
+    let path = self.upload_dir(upload_id).join(file_name);
+    if let Err(err) = tokio::fs::remove_file(&path).await {
+      warn!("failed to remove part {}: {}", path.display(), err);
+    }
+  }
+
+  async fn remove_upload_dir(&self, upload_id: &str) {
+    let path = self.upload_dir(upload_id);
+    if let Err(err) = tokio::fs::remove_dir_all(&path).await
+      && err.kind() != std::io::ErrorKind::NotFound
+    {
+      warn!("failed to remove upload dir {}: {}", path.display(), err);
+    }
+  }
+}
+
+/// 在事务中删除上传记录及其全部分片，返回上传是否存在
+fn remove_upload(
+  write_txn: &WriteTransaction,
+  bucket: &str,
+  key: &str,
+  upload_id: &str,
+) -> Result<bool> {
+  let mut uploads = write_txn.open_table(MULTIPART_TABLE)?;
+  if uploads.remove((bucket, key, upload_id))?.is_none() {
+    return Ok(false);
+  }
+  let mut parts = write_txn.open_table(PART_TABLE)?;
+  parts.retain_in((upload_id, 0)..=(upload_id, u32::MAX), |_, _| false)?;
+  Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{CompletedPart, MIN_PART_SIZE, PartOptions, UploadOptions};
+  use crate::checksum::{ChecksumAlgorithm, ChecksumType};
+  use crate::storage::test_storage;
+  use md5::{Digest, Md5};
+  use tokio::io::AsyncReadExt;
+
+  #[tokio::test]
+  async fn complete_concatenates_parts() {
+    let (dir, storage) = test_storage(Default::default());
+    let objects = &storage.objects;
+
+    let options = UploadOptions {
+      checksum: Some((ChecksumAlgorithm::Crc32, ChecksumType::FullObject)),
+      ..Default::default()
+    };
+    let upload = objects
+      .create_multipart_upload("bkt", "big", options)
+      .unwrap();
+    let first = vec![1u8; MIN_PART_SIZE as usize];
+    let p1 = objects
+      .upload_part(
+        "bkt",
+        "big",
+        &upload.upload_id,
+        1,
+        PartOptions::default(),
+        &first[..],
+      )
+      .await
+      .unwrap();
+    let p2 = objects
+      .upload_part(
+        "bkt",
+        "big",
+        &upload.upload_id,
+        2,
+        PartOptions::default(),
+        &b"tail"[..],
+      )
+      .await
+      .unwrap();
+
+    let completed = |part_number, etag: &str, checksum: &Option<String>| CompletedPart {
+      part_number,
+      etag: etag.to_string(),
+      checksum: checksum.clone(),
+    };
+    let out_of_order = [completed(2, &p2.etag, &None), completed(1, &p1.etag, &None)];
+    assert!(
+      objects
+        .complete_multipart_upload("bkt", "big", &upload.upload_id, &out_of_order)
+        .await
+        .is_err()
+    );
+
+    // 分片校验值与上传时不一致
+    let wrong_checksum = [
+      completed(1, &p1.etag, &p2.checksum),
+      completed(2, &p2.etag, &p2.checksum),
+    ];
+    assert!(
+      objects
+        .complete_multipart_upload("bkt", "big", &upload.upload_id, &wrong_checksum)
+        .await
+        .is_err()
+    );
+
+    let parts = [
+      completed(1, &p1.etag, &p1.checksum),
+      completed(2, &format!("\"{}\"", p2.etag), &None),
+    ];
+    let meta = objects
+      .complete_multipart_upload("bkt", "big", &upload.upload_id, &parts)
+      .await
+      .unwrap();
+    let mut composite = Md5::new();
+    composite.update(hex::decode(&p1.etag).unwrap());
+    composite.update(hex::decode(&p2.etag).unwrap());
+    assert_eq!(
+      meta.etag,
+      format!("{}-2", hex::encode(composite.finalize()))
+    );
+    assert_eq!(meta.size, MIN_PART_SIZE + 4);
+    let mut crc = ChecksumAlgorithm::Crc32.hasher();
+    crc.update(&first);
+    crc.update(b"tail");
+    assert_eq!(meta.checksum.as_ref().unwrap().value, crc.finalize());
+
+    assert_eq!(meta.parts, [MIN_PART_SIZE, 4]);
+    let mut data = Vec::new();
+    objects
+      .open_object(&meta, MIN_PART_SIZE - 1..meta.size, None)
+      .await
+      .unwrap()
+      .read_to_end(&mut data)
+      .await
+      .unwrap();
+    assert_eq!(data, b"\x01tail");
+    assert!(
+      !dir
+        .path()
+        .join("tmp/multipart")
+        .join(&upload.upload_id)
+        .exists()
+    );
+    assert!(
+      objects
+        .list_parts("bkt", "big", &upload.upload_id, 0, 10)
+        .is_err()
+    );
+  }
+}
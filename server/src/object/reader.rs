@@ -0,0 +1,188 @@
+use crate::encryption::{BLOCK_SIZE, TAG_SIZE, decrypt_block};
+use aes_gcm::Aes256Gcm;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io::{self, SeekFrom};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, ReadBuf, Take};
+
+/// 数据文件中的一段
+#[derive(Debug, Clone)]
+pub(crate) struct Segment {
+  pub path: PathBuf,
+  pub offset: u64,
+  pub len: u64,
+}
+
+type OpenFuture = Pin<Box<dyn Future<Output = io::Result<Take<File>>> + Send>>;
+
+async fn open_segment(segment: Segment) -> io::Result<Take<File>> {
+  let mut file = File::open(&segment.path).await?;
+  file.seek(SeekFrom::Start(segment.offset)).await?;
+  Ok(file.take(segment.len))
+}
+
+/// 按顺序读取若干段数据：独立文件和组内对象只有一段，去重对象每个块一段。
+/// 后面的段在前一段读完后才打开，避免同时持有大量文件句柄
+pub struct SegmentReader {
+  segments: VecDeque<Segment>,
+  current: Option<Take<File>>,
+  opening: Option<OpenFuture>,
+}
+
+impl SegmentReader {
+  /// 立即打开第一段，数据文件不存在时在这里返回 NotFound
+  pub(crate) async fn open(segments: Vec<Segment>) -> io::Result<Self> {
+    let mut segments: VecDeque<Segment> = segments.into_iter().filter(|s| s.len > 0).collect();
+    let current = match segments.pop_front() {
+      Some(segment) => Some(open_segment(segment).await?),
+      None => None,
+    };
+    Ok(Self {
+      segments,
+      current,
+      opening: None,
+    })
+  }
+}
+
+impl AsyncRead for SegmentReader {
+  fn poll_read(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+  ) -> Poll<io::Result<()>> {
+    let this = &mut *self;
+    loop {
+      if let Some(opening) = &mut this.opening {
+        let opened = ready!(opening.as_mut().poll(cx));
+        this.opening = None;
+        this.current = Some(opened?);
+      }
+      if let Some(current) = &mut this.current {
+        let filled = buf.filled().len();
+        ready!(Pin::new(&mut *current).poll_read(cx, buf))?;
+        if buf.filled().len() > filled || buf.remaining() == 0 {
+          return Poll::Ready(Ok(()));
+        }
+        // 数据文件比元数据记录的短
+        if current.limit() > 0 {
+          return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+        }
+        this.current = None;
+      }
+      match this.segments.pop_front() {
+        Some(segment) => this.opening = Some(Box::pin(open_segment(segment))),
+        None => return Poll::Ready(Ok(())),
+      }
+    }
+  }
+}
+
+/// 加密对象中需要解密的一个分片：解密 [next_block, end_block) 这些块
+pub(crate) struct CipherPart {
+  pub cipher: Aes256Gcm,
+  pub plain_size: u64,
+  pub next_block: u64,
+  pub end_block: u64,
+}
+
+/// 逐块解密连续的密文，跳过第一块开头的 skip 字节，共输出 remaining 字节明文
+pub struct DecryptReader {
+  raw: SegmentReader,
+  parts: VecDeque<CipherPart>,
+  frame: Vec<u8>,
+  pos: usize,
+  decrypted: bool, // frame 中是否为已解密的明文
+  skip: usize,
+  remaining: u64,
+}
+
+impl DecryptReader {
+  pub(crate) fn new(raw: SegmentReader, parts: Vec<CipherPart>, skip: usize, len: u64) -> Self {
+    Self {
+      raw,
+      parts: parts.into(),
+      frame: Vec::with_capacity(BLOCK_SIZE + TAG_SIZE),
+      pos: 0,
+      decrypted: false,
+      skip,
+      remaining: len,
+    }
+  }
+}
+
+impl AsyncRead for DecryptReader {
+  fn poll_read(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+  ) -> Poll<io::Result<()>> {
+    let this = &mut *self;
+    loop {
+      if this.decrypted {
+        if this.pos < this.frame.len() && this.remaining > 0 {
+          let n = (this.frame.len() - this.pos)
+            .min(buf.remaining())
+            .min(this.remaining.try_into().unwrap_or(usize::MAX));
+          buf.put_slice(&this.frame[this.pos..this.pos + n]);
+          this.pos += n;
+          this.remaining -= n as u64;
+          return Poll::Ready(Ok(()));
+        }
+        this.frame.clear();
+        this.decrypted = false;
+      }
+      if this.remaining == 0 {
+        return Poll::Ready(Ok(()));
+      }
+      let Some(part) = this.parts.front_mut() else {
+        return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+      };
+      if part.next_block >= part.end_block {
+        this.parts.pop_front();
+        continue;
+      }
+      let block_start = part.next_block * BLOCK_SIZE as u64;
+      let frame_len = (part.plain_size - block_start).min(BLOCK_SIZE as u64) as usize + TAG_SIZE;
+      let filled = this.frame.len();
+      this.frame.resize(frame_len, 0);
+      let mut read_buf = ReadBuf::new(&mut this.frame[filled..]);
+      let polled = Pin::new(&mut this.raw).poll_read(cx, &mut read_buf);
+      let n = read_buf.filled().len();
+      this.frame.truncate(filled + n);
+      ready!(polled)?;
+      if n == 0 {
+        return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+      }
+      if this.frame.len() == frame_len {
+        decrypt_block(&part.cipher, part.next_block, &mut this.frame)?;
+        part.next_block += 1;
+        this.pos = std::mem::take(&mut this.skip);
+        this.decrypted = true;
+      }
+    }
+  }
+}
+
+/// open_object 返回的对象数据：未加密对象直接读取数据文件，加密对象边读边解密
+pub enum ObjectReader {
+  Plain(SegmentReader),
+  Decrypt(DecryptReader),
+}
+
+impl AsyncRead for ObjectReader {
+  fn poll_read(
+    self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+  ) -> Poll<io::Result<()>> {
+    match self.get_mut() {
+      ObjectReader::Plain(reader) => Pin::new(reader).poll_read(cx, buf),
+      ObjectReader::Decrypt(reader) => Pin::new(reader).poll_read(cx, buf),
+    }
+  }
+}
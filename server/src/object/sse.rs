@@ -0,0 +1,241 @@
+//! 加密对象的读写：选择保护数据密钥的密钥、校验 SSE-C 密钥，以及把明文 range 映射到需要解密的密文块。
+use crate::encryption::{BLOCK_SIZE, DataKey, Encryption, TAG_SIZE, WrappingKey, cipher_len};
+use crate::error::StorageError;
+use crate::metadata::config::BucketConfig;
+use crate::metadata::encryption::{EncryptionKind, ObjectEncryption, SseAlgorithm};
+use crate::metadata::object_meta::ObjectMeta;
+use crate::object::ObjectManager;
+use crate::object::reader::{CipherPart, DecryptReader, ObjectReader, SegmentReader};
+use anyhow::Result;
+use std::ops::Range;
+
+/// 请求未指定加密方式时使用 bucket 的默认加密
+pub(crate) fn resolve_encryption(
+  config: &BucketConfig,
+  requested: Option<Encryption>,
+) -> Option<Encryption> {
+  requested.or(config.encryption.map(|SseAlgorithm::Aes256| Encryption::S3))
+}
+
+/// 读取对象时检查 SSE-C 密钥：用客户密钥加密的对象必须提供同一个密钥，其它对象不能提供
+pub fn check_customer_key(meta: &ObjectMeta, customer_key: Option<&WrappingKey>) -> Result<()> {
+  let key_md5 = match &meta.encryption {
+    Some(ObjectEncryption {
+      kind: EncryptionKind::Customer { key_md5 },
+      ..
+    }) => Some(key_md5),
+    _ => None,
+  };
+  let err = match (key_md5, customer_key) {
+    (None, None) => return Ok(()),
+    (Some(key_md5), Some(key)) if key.key_md5() == *key_md5 => return Ok(()),
+    (Some(_), None) => StorageError::InvalidEncryption {
+      reason: "The object was stored using a form of Server Side Encryption. The correct parameters must be provided to retrieve the object.",
+    },
+    (None, Some(_)) => StorageError::InvalidEncryption {
+      reason: "The encryption parameters are not applicable to this object.",
+    },
+    (Some(_), Some(_)) => StorageError::EncryptionKeyMismatch,
+  };
+  Err(err.into())
+}
+
+/// 复制时能否直接共享源对象的密文：加密方式相同，SSE-C 时为同一个密钥
+pub(crate) fn same_encryption(
+  source: Option<&ObjectEncryption>,
+  target: Option<&Encryption>,
+) -> bool {
+  match (source.map(|encryption| &encryption.kind), target) {
+    (None, None) | (Some(EncryptionKind::S3), Some(Encryption::S3)) => true,
+    (Some(EncryptionKind::Customer { key_md5 }), Some(Encryption::Customer(key))) => {
+      key.key_md5() == *key_md5
+    }
+    _ => false,
+  }
+}
+
+/// 用作 ETag 的 MD5：SSE-C 对象使用密文的 MD5，不暴露明文的摘要
+pub(crate) fn etag_md5(kind: &EncryptionKind, plain: [u8; 16], cipher: [u8; 16]) -> [u8; 16] {
+  match kind {
+    EncryptionKind::S3 => plain,
+    EncryptionKind::Customer { .. } => cipher,
+  }
+}
+
+impl ObjectManager {
+  /// 设置 SSE-S3 的主密钥，未设置时拒绝 SSE-S3 请求
+  pub(crate) fn set_master_key(&mut self, key: WrappingKey) {
+    self.master_key = Some(key);
+  }
+
+  /// 是否配置了主密钥，可以使用 SSE-S3
+  pub fn sse_s3_enabled(&self) -> bool {
+    self.master_key.is_some()
+  }
+
+  /// 加密方式对应的保护数据密钥的密钥；SSE-C 时校验请求中的密钥
+  pub(crate) fn wrapping_key<'a>(
+    &'a self,
+    kind: &EncryptionKind,
+    customer_key: Option<&'a WrappingKey>,
+  ) -> Result<&'a WrappingKey> {
+    match kind {
+      EncryptionKind::S3 => self.master_key.as_ref().ok_or_else(|| {
+        StorageError::InvalidEncryption {
+          reason: "Server-side encryption is not configured on this server",
+        }
+        .into()
+      }),
+      EncryptionKind::Customer { key_md5 } => match customer_key {
+        Some(key) if key.key_md5() == *key_md5 => Ok(key),
+        Some(_) => Err(StorageError::EncryptionKeyMismatch.into()),
+        None => Err(
+          StorageError::InvalidEncryption {
+            reason: "The customer-provided encryption key is required for this upload",
+          }
+          .into(),
+        ),
+      },
+    }
+  }
+
+  /// 为一段新数据生成数据密钥，返回 (加密方式, 数据密钥, 加密后的数据密钥)
+  pub(crate) fn new_data_key(
+    &self,
+    encryption: &Encryption,
+  ) -> Result<(EncryptionKind, DataKey, Vec<u8>)> {
+    let (kind, customer_key) = match encryption {
+      Encryption::S3 => (EncryptionKind::S3, None),
+      Encryption::Customer(key) => (
+        EncryptionKind::Customer {
+          key_md5: key.key_md5(),
+        },
+        Some(key),
+      ),
+    };
+    let (data_key, sealed_key) = self.wrapping_key(&kind, customer_key)?.new_data_key()?;
+    Ok((kind, data_key, sealed_key))
+  }
+
+  /// 打开加密对象的 range：只读取并解密覆盖到的块
+  pub(crate) async fn open_encrypted(
+    &self,
+    meta: &ObjectMeta,
+    encryption: &ObjectEncryption,
+    range: Range<u64>,
+    customer_key: Option<&WrappingKey>,
+  ) -> Result<ObjectReader> {
+    let wrapping_key = self.wrapping_key(&encryption.kind, customer_key)?;
+    let whole = [meta.size];
+    let sizes: &[u64] = if meta.parts.is_empty() {
+      &whole
+    } else {
+      &meta.parts
+    };
+    if sizes.len() != encryption.sealed_keys.len() {
+      anyhow::bail!("encryption keys do not match the parts of {}", meta.key);
+    }
+
+    let (block, frame) = (BLOCK_SIZE as u64, (BLOCK_SIZE + TAG_SIZE) as u64);
+    let mut parts = Vec::new();
+    let mut cipher_range: Option<Range<u64>> = None;
+    let mut skip = 0;
+    let (mut plain_start, mut cipher_start) = (0u64, 0u64);
+    for (&size, sealed_key) in sizes.iter().zip(&encryption.sealed_keys) {
+      let plain_end = plain_start + size;
+      if range.start < plain_end && range.end > plain_start {
+        let start = range.start.max(plain_start) - plain_start;
+        let end = range.end.min(plain_end) - plain_start;
+        let first_block = start / block;
+        let end_block = end.div_ceil(block);
+        let cipher_end = cipher_start + (end_block * frame).min(cipher_len(size));
+        match &mut cipher_range {
+          Some(cipher_range) => cipher_range.end = cipher_end,
+          None => {
+            cipher_range = Some(cipher_start + first_block * frame..cipher_end);
+            skip = (start - first_block * block) as usize;
+          }
+        }
+        parts.push(CipherPart {
+          cipher: wrapping_key.open_data_key(sealed_key)?.cipher(),
+          plain_size: size,
+          next_block: first_block,
+          end_block,
+        });
+      }
+      plain_start = plain_end;
+      cipher_start += cipher_len(size);
+    }
+
+    let segments = self.data_segments(meta, cipher_range.unwrap_or(0..0))?;
+    let raw = SegmentReader::open(segments).await?;
+    let len = range.end.min(plain_start).saturating_sub(range.start);
+    Ok(ObjectReader::Decrypt(DecryptReader::new(
+      raw, parts, skip, len,
+    )))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::encryption::{BLOCK_SIZE, Encryption, WrappingKey};
+  use crate::metadata::encryption::EncryptionKind;
+  use crate::object::PutOptions;
+  use crate::storage::test_storage;
+  use base64::Engine;
+  use base64::engine::general_purpose::STANDARD;
+  use tokio::io::AsyncReadExt;
+
+  #[tokio::test]
+  async fn encrypted_range_reads() {
+    let master = WrappingKey::from_base64(&STANDARD.encode([3u8; 32])).unwrap();
+    let (_dir, storage) = test_storage(Default::default());
+    let storage = storage.with_master_key(master);
+    let objects = &storage.objects;
+    let customer = WrappingKey::from_base64(&STANDARD.encode([9u8; 32])).unwrap();
+    let data: Vec<u8> = (0..BLOCK_SIZE * 3 + 7).map(|i| (i % 251) as u8).collect();
+
+    for (key, encryption) in [
+      ("s3", Encryption::S3),
+      ("c", Encryption::Customer(customer.clone())),
+    ] {
+      let options = PutOptions {
+        encryption: Some(encryption),
+        ..Default::default()
+      };
+      let meta = objects
+        .put_object("bkt", key, options, &data[..])
+        .await
+        .unwrap();
+      assert_eq!(meta.size, data.len() as u64);
+      let customer_key = match meta.encryption.as_ref().unwrap().kind {
+        EncryptionKind::S3 => None,
+        EncryptionKind::Customer { .. } => Some(&customer),
+      };
+      // 跨块的 range 以及整个对象
+      let start = BLOCK_SIZE as u64 - 3;
+      for range in [start..start + BLOCK_SIZE as u64 + 10, 0..meta.size] {
+        let mut read = Vec::new();
+        objects
+          .open_object(&meta, range.clone(), customer_key)
+          .await
+          .unwrap()
+          .read_to_end(&mut read)
+          .await
+          .unwrap();
+        assert_eq!(read, data[range.start as usize..range.end as usize]);
+      }
+    }
+
+    // SSE-C 对象缺少密钥或密钥错误时拒绝读取
+    let meta = objects.head_object("bkt", "c").unwrap();
+    let other = WrappingKey::from_base64(&STANDARD.encode([1u8; 32])).unwrap();
+    assert!(objects.open_object(&meta, 0..1, None).await.is_err());
+    assert!(
+      objects
+        .open_object(&meta, 0..1, Some(&other))
+        .await
+        .is_err()
+    );
+  }
+}
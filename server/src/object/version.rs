@@ -0,0 +1,479 @@
+use crate::bucket::no_such_bucket;
+use crate::error::StorageError;
+use crate::metadata::config::{BucketConfig, VersioningStatus};
+use crate::metadata::object_meta::{DataLocation, ObjectMeta};
+use crate::metadata::{BUCKET_TABLE, OBJECT_TABLE, VERSION_TABLE};
+use crate::object::ObjectManager;
+use crate::object::dedup::adjust_chunk_refs;
+use crate::object::list::prefix_successor;
+use anyhow::Result;
+use redb::{ReadableTable, Table, WriteTransaction};
+use std::ops::Bound;
+
+type VersionKey = (&'static str, &'static str, &'static str);
+type VersionTable<'txn> = Table<'txn, VersionKey, ObjectMeta>;
+
+/// 大于任何 version_id，用于取同一 key 的全部版本
+pub(crate) const VERSION_ID_END: &str = "~";
+
+#[derive(Debug, Clone)]
+pub struct ListVersionsOptions {
+  pub prefix: String,
+  pub delimiter: Option<String>,
+  pub key_marker: Option<String>,
+  /// 与 key_marker 一起使用，从该版本之后开始；可以是 `null`
+  pub version_id_marker: Option<String>,
+  pub max_keys: usize,
+}
+
+impl Default for ListVersionsOptions {
+  fn default() -> Self {
+    Self {
+      prefix: String::new(),
+      delimiter: None,
+      key_marker: None,
+      version_id_marker: None,
+      max_keys: 1000,
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct VersionEntry {
+  pub meta: ObjectMeta,
+  pub is_latest: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct VersionsPage {
+  pub versions: Vec<VersionEntry>,
+  pub common_prefixes: Vec<String>,
+  pub is_truncated: bool,
+  pub next_key_marker: Option<String>,
+  pub next_version_id_marker: Option<String>,
+}
+
+impl VersionsPage {
+  fn len(&self) -> usize {
+    self.versions.len() + self.common_prefixes.len()
+  }
+}
+
+/// 删除的结果：`result` 是被删除的版本或新建的删除标记，`removed` 是需要清理数据的版本
+pub(crate) struct Deletion {
+  pub result: Option<ObjectMeta>,
+  pub removed: Option<ObjectMeta>,
+}
+
+/// 在写事务中读取 bucket 的配置，bucket 不存在时返回 NoSuchBucket
+pub(crate) fn txn_bucket_config(
+  write_txn: &WriteTransaction,
+  bucket: &str,
+) -> Result<BucketConfig> {
+  match write_txn.open_table(BUCKET_TABLE)?.get(bucket)? {
+    Some(meta) => Ok(meta.value().config),
+    None => Err(no_such_bucket(bucket)),
+  }
+}
+
+/// 同一 key 的版本从新到旧
+fn newest_version(
+  table: &impl ReadableTable<VersionKey, ObjectMeta>,
+  bucket: &str,
+  key: &str,
+) -> Result<Option<ObjectMeta>> {
+  let newest = table
+    .range((bucket, key, "")..(bucket, key, VERSION_ID_END))?
+    .next()
+    .transpose()?;
+  Ok(newest.map(|(_, meta)| meta.value()))
+}
+
+/// 按对外的版本 ID 查找，`null` 对应 null 版本
+pub(crate) fn find_version(
+  table: &impl ReadableTable<VersionKey, ObjectMeta>,
+  bucket: &str,
+  key: &str,
+  version_id: &str,
+) -> Result<Option<ObjectMeta>> {
+  if version_id != "null" {
+    return Ok(table.get((bucket, key, version_id))?.map(|v| v.value()));
+  }
+  for entry in table.range((bucket, key, "")..(bucket, key, VERSION_ID_END))? {
+    let meta = entry?.1.value();
+    if meta.null_version {
+      return Ok(Some(meta));
+    }
+  }
+  Ok(None)
+}
+
+/// 新版本的 ID：高 64 位随时间递减，使同一 key 的版本按字典序从新到旧；
+/// 时钟回拨时仍然排在已有版本之前
+fn next_version_id(versions: &VersionTable, bucket: &str, key: &str) -> Result<String> {
+  let nanos = chrono::Utc::now()
+    .timestamp_nanos_opt()
+    .unwrap_or_default()
+    .max(0) as u64;
+  let now = u64::MAX - nanos;
+  let newest = newest_version(versions, bucket, key)?
+    .and_then(|meta| u64::from_str_radix(meta.version_id.get(..16)?, 16).ok());
+  let sequence = newest.map_or(now, |newest| now.min(newest.saturating_sub(1)));
+  Ok(format!("{sequence:016x}{:016x}", rand::random::<u64>()))
+}
+
+/// 永久删除一个版本，受 Object Lock 保护的版本返回 ObjectLocked
+fn remove_version(
+  versions: &mut VersionTable,
+  bucket: &str,
+  key: &str,
+  version_id: &str,
+  bypass_governance: bool,
+) -> Result<Option<ObjectMeta>> {
+  let found = find_version(versions, bucket, key, version_id)?;
+  if let Some(meta) = &found {
+    meta.check_object_lock(chrono::Utc::now().timestamp(), bypass_governance)?;
+    versions.remove((bucket, key, meta.version_id.as_str()))?;
+  }
+  Ok(found)
+}
+
+/// 为新版本分配 version_id 并写入 VERSION_TABLE；未开启或暂停版本控制时新版本是 null 版本，
+/// 会替换已有的 null 版本，返回被替换的版本；受保护的 null 版本不能被替换
+pub(crate) fn put_version(
+  versions: &mut VersionTable,
+  meta: &mut ObjectMeta,
+  status: VersioningStatus,
+) -> Result<Option<ObjectMeta>> {
+  meta.null_version = status != VersioningStatus::Enabled;
+  meta.version_id = next_version_id(versions, &meta.bucket, &meta.key)?;
+  let replaced = if meta.null_version {
+    remove_version(versions, &meta.bucket, &meta.key, "null", false)?
+  } else {
+    None
+  };
+  versions.insert(
+    (
+      meta.bucket.as_str(),
+      meta.key.as_str(),
+      meta.version_id.as_str(),
+    ),
+    &*meta,
+  )?;
+  Ok(replaced)
+}
+
+/// 删除某个版本后，OBJECT_TABLE 指向剩下的最新版本；最新版本是删除标记时对象不可见
+fn refresh_current(
+  objects: &mut Table<(&'static str, &'static str), ObjectMeta>,
+  versions: &VersionTable,
+  bucket: &str,
+  key: &str,
+) -> Result<()> {
+  match newest_version(versions, bucket, key)? {
+    Some(newest) if !newest.is_delete_marker() => {
+      objects.insert((bucket, key), &newest)?;
+    }
+    _ => {
+      objects.remove((bucket, key))?;
+    }
+  }
+  Ok(())
+}
+
+/// 在事务中删除对象：指定版本时永久删除该版本；否则未开启版本控制时直接删除，
+/// 开启或暂停时写入删除标记（暂停时删除标记是 null 版本）。
+/// 永久删除受 Object Lock 保护的版本时返回 ObjectLocked，`bypass_governance` 时治理模式的保留不生效
+pub(crate) fn delete_in_txn(
+  write_txn: &WriteTransaction,
+  bucket: &str,
+  key: &str,
+  version_id: Option<&str>,
+  bypass_governance: bool,
+) -> Result<Deletion> {
+  let status = txn_bucket_config(write_txn, bucket)?.versioning;
+  let mut objects = write_txn.open_table(OBJECT_TABLE)?;
+  let mut versions = write_txn.open_table(VERSION_TABLE)?;
+  let deletion = match (version_id, status) {
+    (Some(version_id), _) => {
+      let removed = remove_version(&mut versions, bucket, key, version_id, bypass_governance)?;
+      if removed.is_some() {
+        refresh_current(&mut objects, &versions, bucket, key)?;
+      }
+      Deletion {
+        result: removed.clone(),
+        removed,
+      }
+    }
+    (None, VersioningStatus::Unversioned) => {
+      let removed = remove_version(&mut versions, bucket, key, "null", bypass_governance)?;
+      objects.remove((bucket, key))?;
+      Deletion {
+        result: removed.clone(),
+        removed,
+      }
+    }
+    (None, status) => {
+      let mut marker = ObjectMeta {
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+        size: 0,
+        etag: String::new(),
+        headers: Default::default(),
+        last_modified: chrono::Utc::now().timestamp(),
+        location: DataLocation::DeleteMarker,
+        tags: Vec::new(),
+        acl: Default::default(),
+        parts: Vec::new(),
+        version_id: String::new(),
+        null_version: false,
+        encryption: None,
+        checksum: None,
+        lock: Default::default(),
+      };
+      let removed = put_version(&mut versions, &mut marker, status)?;
+      objects.remove((bucket, key))?;
+      Deletion {
+        result: Some(marker),
+        removed,
+      }
+    }
+  };
+  // 被删除版本的数据块引用与元数据在同一个事务中释放
+  if let Some(removed) = &deletion.removed {
+    adjust_chunk_refs(write_txn, &removed.location, -1)?;
+  }
+  Ok(deletion)
+}
+
+impl ObjectManager {
+  /// 按版本 ID 读取元数据，可能是删除标记
+  pub fn get_object_version(
+    &self,
+    bucket: &str,
+    key: &str,
+    version_id: &str,
+  ) -> Result<ObjectMeta> {
+    let read_txn = self.db.begin_read()?;
+    let versions = read_txn.open_table(VERSION_TABLE)?;
+    match find_version(&versions, bucket, key, version_id)? {
+      Some(meta) => Ok(meta),
+      None if read_txn.open_table(BUCKET_TABLE)?.get(bucket)?.is_none() => {
+        Err(no_such_bucket(bucket))
+      }
+      None => Err(
+        StorageError::NoSuchVersion {
+          key: key.to_string(),
+          version_id: version_id.to_string(),
+        }
+        .into(),
+      ),
+    }
+  }
+
+  /// 按 key 字典序、同一 key 内从新到旧列举全部版本和删除标记，delimiter 的处理与 `list_objects` 相同
+  pub fn list_object_versions(
+    &self,
+    bucket: &str,
+    options: &ListVersionsOptions,
+  ) -> Result<VersionsPage> {
+    let read_txn = self.db.begin_read()?;
+    if read_txn.open_table(BUCKET_TABLE)?.get(bucket)?.is_none() {
+      return Err(no_such_bucket(bucket));
+    }
+    let table = read_txn.open_table(VERSION_TABLE)?;
+    let prefix = options.prefix.as_str();
+    let delimiter = options.delimiter.as_deref().filter(|d| !d.is_empty());
+
+    let mut page = VersionsPage::default();
+    // 从 key_marker 的某个版本之后开始时，该 key 剩下的版本都不是最新版本
+    let mut previous_key = None;
+    let ((mut seek_key, mut seek_version), mut exclusive) = match options.key_marker.as_deref() {
+      Some(key_marker) if key_marker >= prefix => {
+        let version = match options.version_id_marker.as_deref() {
+          Some(marker) => find_version(&table, bucket, key_marker, marker)?
+            .map(|meta| meta.version_id)
+            .inspect(|_| previous_key = Some(key_marker.to_string())),
+          None => None,
+        };
+        let version = version.unwrap_or_else(|| VERSION_ID_END.to_string());
+        ((key_marker.to_string(), version), true)
+      }
+      _ => ((prefix.to_string(), String::new()), false),
+    };
+    let mut last_prefix = options.key_marker.clone();
+
+    loop {
+      let seek = (bucket, seek_key.as_str(), seek_version.as_str());
+      let lower = if exclusive {
+        Bound::Excluded(seek)
+      } else {
+        Bound::Included(seek)
+      };
+      let mut jump = None;
+      for entry in table.range::<(&str, &str, &str)>((lower, Bound::Unbounded))? {
+        let (k, v) = entry?;
+        let (entry_bucket, key, _) = k.value();
+        if entry_bucket != bucket || !key.starts_with(prefix) {
+          return Ok(page);
+        }
+
+        let common_prefix = delimiter.and_then(|d| {
+          key[prefix.len()..]
+            .find(d)
+            .map(|pos| key[..prefix.len() + pos + d.len()].to_string())
+        });
+        if let Some(common_prefix) = common_prefix {
+          if last_prefix.as_deref() != Some(common_prefix.as_str()) {
+            if page.len() == options.max_keys {
+              page.is_truncated = true;
+              return Ok(page);
+            }
+            page.common_prefixes.push(common_prefix.clone());
+            page.next_key_marker = Some(common_prefix.clone());
+            page.next_version_id_marker = None;
+          }
+          jump = Some(common_prefix);
+          break;
+        }
+
+        if page.len() == options.max_keys {
+          page.is_truncated = true;
+          return Ok(page);
+        }
+        let meta = v.value();
+        let is_latest = previous_key.as_deref() != Some(key);
+        previous_key = Some(key.to_string());
+        page.next_key_marker = Some(key.to_string());
+        page.next_version_id_marker = Some(meta.s3_version_id().to_string());
+        page.versions.push(VersionEntry { meta, is_latest });
+      }
+
+      let Some(common_prefix) = jump else {
+        return Ok(page);
+      };
+      match prefix_successor(&common_prefix) {
+        Some(successor) => seek_key = successor,
+        None => return Ok(page),
+      }
+      seek_version = String::new();
+      exclusive = false;
+      last_prefix = Some(common_prefix);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::ListVersionsOptions;
+  use crate::metadata::config::VersioningStatus;
+  use crate::object::PutOptions;
+  use crate::storage::test_storage;
+
+  #[tokio::test]
+  async fn versions_and_delete_markers() {
+    let (_dir, storage) = test_storage(Default::default());
+    let objects = &storage.objects;
+    let put = |data: &'static [u8]| objects.put_object("bkt", "k", PutOptions::default(), data);
+
+    // 开启前写入的是 null 版本
+    let null = put(b"v0").await.unwrap();
+    assert_eq!(null.s3_version_id(), "null");
+    storage
+      .buckets
+      .put_bucket_versioning("bkt", VersioningStatus::Enabled)
+      .unwrap();
+    let v1 = put(b"v1").await.unwrap();
+    let v2 = put(b"v2").await.unwrap();
+    assert!(v2.version_id < v1.version_id && v1.version_id < null.version_id);
+
+    // 删除标记使对象不可见，删除标记本身被删除后恢复
+    let marker = objects
+      .delete_object("bkt", "k", None, false)
+      .await
+      .unwrap()
+      .unwrap();
+    assert!(marker.is_delete_marker());
+    assert!(objects.head_object("bkt", "k").is_err());
+    objects
+      .delete_object("bkt", "k", Some(&marker.version_id), false)
+      .await
+      .unwrap();
+    assert_eq!(objects.head_object("bkt", "k").unwrap().etag, v2.etag);
+
+    // 删除最新版本后上一个版本成为当前版本
+    objects
+      .delete_object("bkt", "k", Some(&v2.version_id), false)
+      .await
+      .unwrap();
+    assert_eq!(objects.head_object("bkt", "k").unwrap().etag, v1.etag);
+    assert_eq!(
+      objects.get_object_version("bkt", "k", "null").unwrap().etag,
+      null.etag
+    );
+
+    // 暂停后写入的 null 版本替换原来的 null 版本
+    storage
+      .buckets
+      .put_bucket_versioning("bkt", VersioningStatus::Suspended)
+      .unwrap();
+    let null2 = put(b"v3").await.unwrap();
+    let page = objects
+      .list_object_versions("bkt", &ListVersionsOptions::default())
+      .unwrap();
+    let listed: Vec<_> = page
+      .versions
+      .iter()
+      .map(|entry| (entry.meta.version_id.as_str(), entry.is_latest))
+      .collect();
+    assert_eq!(
+      listed,
+      [
+        (null2.version_id.as_str(), true),
+        (v1.version_id.as_str(), false)
+      ]
+    );
+
+    // 分页从版本中间继续
+    let options = ListVersionsOptions {
+      key_marker: Some("k".into()),
+      version_id_marker: Some("null".into()),
+      ..Default::default()
+    };
+    let page = objects.list_object_versions("bkt", &options).unwrap();
+    assert_eq!(page.versions.len(), 1);
+    assert!(!page.versions[0].is_latest);
+    assert!(storage.buckets.delete_bucket("bkt").is_err());
+  }
+
+  #[tokio::test]
+  async fn delimiter_with_max_char() {
+    let (_dir, storage) = test_storage(Default::default());
+    storage
+      .buckets
+      .put_bucket_versioning("bkt", VersioningStatus::Enabled)
+      .unwrap();
+    for key in ["dir/\u{10FFFF}", "dir/\u{10FFFF}", "dir0", "\u{10FFFF}/y"] {
+      storage
+        .objects
+        .put_object("bkt", key, PutOptions::default(), &b"x"[..])
+        .await
+        .unwrap();
+    }
+    let options = ListVersionsOptions {
+      delimiter: Some("/".into()),
+      ..Default::default()
+    };
+    let page = storage
+      .objects
+      .list_object_versions("bkt", &options)
+      .unwrap();
+    let keys: Vec<_> = page
+      .versions
+      .iter()
+      .map(|entry| entry.meta.key.as_str())
+      .collect();
+    assert_eq!(keys, ["dir0"]);
+    assert_eq!(page.common_prefixes, ["dir/", "\u{10FFFF}/"]);
+  }
+}
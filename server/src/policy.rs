@@ -0,0 +1,606 @@
+//! bucket 策略：解析 IAM 策略 JSON，以及按请求求值。
+//! 显式 Deny 优先于 Allow，没有语句匹配时由调用方决定是否放行（通常只放行 bucket owner）。
+use crate::error::StorageError;
+use crate::metadata::constant::{Action, Effect};
+use crate::metadata::policy::{BucketPolicy, Condition, ConditionOperator, PolicyStatement};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+pub const ARN_PREFIX: &str = "arn:aws:s3:::";
+/// bucket 策略 JSON 最大 20 KB
+pub const MAX_POLICY_SIZE: usize = 20 * 1024;
+const POLICY_VERSIONS: [&str; 2] = ["2012-10-17", "2008-10-17"];
+
+/// 条件键（小写，如 `aws:sourceip`、`s3:prefix`）到请求中取值的映射
+pub type ConditionContext = HashMap<String, String>;
+
+/// 求值结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+  /// 有 Allow 语句匹配且没有 Deny
+  Allow,
+  /// 有 Deny 语句匹配
+  Deny,
+  /// 没有任何语句匹配
+  NotApplicable,
+}
+
+/// 一次待授权的操作
+#[derive(Debug, Clone)]
+pub struct PolicyRequest<'a> {
+  /// 请求方的 access key，匿名请求为 None
+  pub principal: Option<&'a str>,
+  pub action: Action,
+  /// 资源 ARN，见 [`resource_arn`]
+  pub resource: &'a str,
+  pub context: &'a ConditionContext,
+}
+
+/// bucket 为 `arn:aws:s3:::bucket`，对象为 `arn:aws:s3:::bucket/key`
+pub fn resource_arn(bucket: &str, key: Option<&str>) -> String {
+  match key {
+    Some(key) => format!("{ARN_PREFIX}{bucket}/{key}"),
+    None => format!("{ARN_PREFIX}{bucket}"),
+  }
+}
+
+pub fn evaluate(policy: &BucketPolicy, request: &PolicyRequest) -> Decision {
+  let mut decision = Decision::NotApplicable;
+  for statement in policy
+    .statements
+    .iter()
+    .filter(|statement| statement_matches(statement, request))
+  {
+    match statement.effect {
+      Effect::Deny => return Decision::Deny,
+      Effect::Allow => decision = Decision::Allow,
+    }
+  }
+  decision
+}
+
+fn statement_matches(statement: &PolicyStatement, request: &PolicyRequest) -> bool {
+  let action = request.action.as_str().to_ascii_lowercase();
+  statement
+    .principals
+    .iter()
+    .any(|principal| principal_matches(principal, request.principal))
+    != statement.not_principal
+    && statement
+      .actions
+      .iter()
+      .any(|pattern| wildcard_match(pattern, &action))
+      != statement.not_action
+    && statement
+      .resources
+      .iter()
+      .any(|resource| wildcard_match(resource, request.resource))
+      != statement.not_resource
+    && statement
+      .conditions
+      .iter()
+      .all(|condition| condition_matches(condition, request.context))
+}
+
+/// `*` 匹配所有人（含匿名）；否则匹配 access key，或以 `user/<access key>` 结尾的 IAM 用户 ARN
+fn principal_matches(pattern: &str, principal: Option<&str>) -> bool {
+  if pattern == "*" {
+    return true;
+  }
+  let Some(principal) = principal else {
+    return false;
+  };
+  pattern == principal
+    || pattern.starts_with("arn:")
+      && pattern
+        .rsplit_once(":user/")
+        .is_some_and(|(_, user)| user == principal)
+}
+
+fn condition_matches(condition: &Condition, context: &ConditionContext) -> bool {
+  use ConditionOperator::*;
+  let operator = condition.operator;
+  let Some(value) = context.get(&condition.key) else {
+    return match operator {
+      Null => condition.values.iter().any(|expected| expected == "true"),
+      _ => condition.if_exists || operator.is_negated(),
+    };
+  };
+  let number = |s: &str| s.parse::<f64>().ok();
+  let test = |expected: &str| match operator {
+    StringEquals | StringNotEquals => expected == value,
+    StringEqualsIgnoreCase | StringNotEqualsIgnoreCase | Bool => {
+      expected.eq_ignore_ascii_case(value)
+    }
+    StringLike | StringNotLike => wildcard_match(expected, value),
+    NumericEquals
+    | NumericNotEquals
+    | NumericLessThan
+    | NumericLessThanEquals
+    | NumericGreaterThan
+    | NumericGreaterThanEquals => {
+      let (Some(expected), Some(value)) = (number(expected), number(value)) else {
+        return false;
+      };
+      match operator {
+        NumericLessThan => value < expected,
+        NumericLessThanEquals => value <= expected,
+        NumericGreaterThan => value > expected,
+        NumericGreaterThanEquals => value >= expected,
+        _ => value == expected,
+      }
+    }
+    IpAddress | NotIpAddress => value
+      .parse()
+      .is_ok_and(|ip| parse_cidr(expected).is_some_and(|cidr| cidr_contains(cidr, ip))),
+    // 键存在时，只有 `"false"` 满足 Null 条件
+    Null => expected == "false",
+  };
+  let matched = condition.values.iter().any(|expected| test(expected));
+  matched != operator.is_negated()
+}
+
+/// 解析 `10.0.0.0/8`、`2001:db8::/32` 或单个 IP
+fn parse_cidr(value: &str) -> Option<(IpAddr, u8)> {
+  let (addr, prefix) = value.split_once('/').unwrap_or((value, ""));
+  let addr: IpAddr = addr.parse().ok()?;
+  let max = if addr.is_ipv4() { 32 } else { 128 };
+  let prefix = if prefix.is_empty() {
+    max
+  } else {
+    prefix.parse().ok()?
+  };
+  (prefix <= max).then_some((addr, prefix))
+}
+
+fn cidr_contains((network, prefix): (IpAddr, u8), ip: IpAddr) -> bool {
+  let (network, ip, bits) = match (network, ip) {
+    (IpAddr::V4(network), IpAddr::V4(ip)) => {
+      (u32::from(network) as u128, u32::from(ip) as u128, 32)
+    }
+    (IpAddr::V6(network), IpAddr::V6(ip)) => (u128::from(network), u128::from(ip), 128),
+    _ => return false,
+  };
+  let shift = bits - prefix as u32;
+  shift >= 128 || network >> shift == ip >> shift
+}
+
+/// `*` 匹配任意长度（含空）的字符，`?` 匹配单个字符
+pub fn wildcard_match(pattern: &str, value: &str) -> bool {
+  let pattern: Vec<char> = pattern.chars().collect();
+  let value: Vec<char> = value.chars().collect();
+  let (mut p, mut v) = (0, 0);
+  // 最近一个 `*` 的位置，以及它当前吞到的 value 位置，失配时回溯
+  let mut star: Option<(usize, usize)> = None;
+  while v < value.len() {
+    if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+      p += 1;
+      v += 1;
+    } else if p < pattern.len() && pattern[p] == '*' {
+      star = Some((p, v));
+      p += 1;
+    } else if let Some((star_p, star_v)) = star {
+      p = star_p + 1;
+      v = star_v + 1;
+      star = Some((star_p, star_v + 1));
+    } else {
+      return false;
+    }
+  }
+  pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn malformed(reason: impl Into<String>) -> StorageError {
+  StorageError::MalformedPolicy {
+    reason: reason.into(),
+  }
+}
+
+/// 解析并校验 bucket 策略 JSON，资源必须属于该 bucket
+pub fn parse_policy(bucket: &str, document: &str) -> Result<BucketPolicy, StorageError> {
+  if document.len() > MAX_POLICY_SIZE {
+    return Err(malformed(
+      "Policy exceeds the maximum allowed document size",
+    ));
+  }
+  let value: Value = serde_json::from_str(document)
+    .map_err(|_| malformed("Policies must be valid JSON and the first byte must be '{'"))?;
+  let Value::Object(root) = value else {
+    return Err(malformed(
+      "Policies must be valid JSON and the first byte must be '{'",
+    ));
+  };
+  for field in root.keys() {
+    if !["Version", "Id", "Statement"].contains(&field.as_str()) {
+      return Err(malformed(format!("Unknown field {field}")));
+    }
+  }
+  if let Some(version) = root.get("Version")
+    && !version
+      .as_str()
+      .is_some_and(|v| POLICY_VERSIONS.contains(&v))
+  {
+    return Err(malformed("The policy must contain a valid version string"));
+  }
+  let statements = match root.get("Statement") {
+    Some(Value::Array(statements)) if !statements.is_empty() => statements.iter().collect(),
+    Some(statement @ Value::Object(_)) => vec![statement],
+    _ => return Err(malformed("Missing required field Statement")),
+  };
+  let mut sids = HashSet::new();
+  let statements = statements
+    .into_iter()
+    .map(|statement| {
+      let Value::Object(statement) = statement else {
+        return Err(malformed("Statement must be a JSON object"));
+      };
+      let statement = parse_statement(bucket, statement)?;
+      if let Some(sid) = &statement.sid
+        && !sids.insert(sid.clone())
+      {
+        return Err(malformed("Statement IDs (SID) must be unique"));
+      }
+      Ok(statement)
+    })
+    .collect::<Result<_, _>>()?;
+  Ok(BucketPolicy {
+    document: document.to_string(),
+    statements,
+  })
+}
+
+const STATEMENT_FIELDS: &[&str] = &[
+  "Sid",
+  "Effect",
+  "Principal",
+  "NotPrincipal",
+  "Action",
+  "NotAction",
+  "Resource",
+  "NotResource",
+  "Condition",
+];
+
+fn parse_statement(
+  bucket: &str,
+  statement: &Map<String, Value>,
+) -> Result<PolicyStatement, StorageError> {
+  for field in statement.keys() {
+    if !STATEMENT_FIELDS.contains(&field.as_str()) {
+      return Err(malformed(format!("Unknown field {field}")));
+    }
+  }
+  let sid = match statement.get("Sid") {
+    None => None,
+    Some(Value::String(sid)) => Some(sid.clone()),
+    Some(_) => return Err(malformed("Sid must be a string")),
+  };
+  let effect = match statement.get("Effect").and_then(Value::as_str) {
+    Some("Allow") => Effect::Allow,
+    Some("Deny") => Effect::Deny,
+    Some(_) => return Err(malformed("Invalid effect")),
+    None => return Err(malformed("Missing required field Effect")),
+  };
+  let (principal, not_principal) = either(statement, "Principal", "NotPrincipal")?;
+  let principals = parse_principals(principal)?;
+  let (action, not_action) = either(statement, "Action", "NotAction")?;
+  let actions = string_list(action, "Action")?
+    .into_iter()
+    .map(|action| {
+      let action = action.to_ascii_lowercase();
+      let valid = action == "*"
+        || action.starts_with("s3:")
+          && (action.contains(['*', '?']) || Action::from_name(&action).is_some());
+      if valid {
+        Ok(action)
+      } else {
+        Err(malformed("Policy has invalid action"))
+      }
+    })
+    .collect::<Result<_, _>>()?;
+  let (resource, not_resource) = either(statement, "Resource", "NotResource")?;
+  let resources = string_list(resource, "Resource")?;
+  for resource in &resources {
+    let valid = resource == "*"
+      || resource
+        .strip_prefix(ARN_PREFIX)
+        .is_some_and(|rest| wildcard_match(rest.split('/').next().unwrap_or_default(), bucket));
+    if !valid {
+      return Err(malformed("Policy has invalid resource"));
+    }
+  }
+  let conditions = match statement.get("Condition") {
+    None => Vec::new(),
+    Some(Value::Object(conditions)) => parse_conditions(conditions)?,
+    Some(_) => return Err(malformed("Condition must be a JSON object")),
+  };
+  Ok(PolicyStatement {
+    sid,
+    effect,
+    principals,
+    not_principal,
+    actions,
+    not_action,
+    resources,
+    not_resource,
+    conditions,
+  })
+}
+
+/// `Action`/`NotAction` 这类互斥字段必须且只能出现一个，返回字段值以及是否为否定形式
+fn either<'a>(
+  statement: &'a Map<String, Value>,
+  field: &str,
+  not_field: &str,
+) -> Result<(&'a Value, bool), StorageError> {
+  match (statement.get(field), statement.get(not_field)) {
+    (Some(value), None) => Ok((value, false)),
+    (None, Some(value)) => Ok((value, true)),
+    (Some(_), Some(_)) => Err(malformed(format!(
+      "Statement cannot contain both {field} and {not_field}"
+    ))),
+    (None, None) => Err(malformed(format!("Missing required field {field}"))),
+  }
+}
+
+/// 字符串或非空字符串数组
+fn string_list(value: &Value, field: &str) -> Result<Vec<String>, StorageError> {
+  let invalid = || {
+    malformed(format!(
+      "{field} must be a string or a non-empty array of strings"
+    ))
+  };
+  match value {
+    Value::String(value) => Ok(vec![value.clone()]),
+    Value::Array(values) if !values.is_empty() => values
+      .iter()
+      .map(|value| value.as_str().map(str::to_string).ok_or_else(invalid))
+      .collect(),
+    _ => Err(invalid()),
+  }
+}
+
+/// `"*"`，或 `{"AWS": ...}` / `{"CanonicalUser": ...}`；Service、Federated 主体不对应网关用户，不会匹配
+fn parse_principals(value: &Value) -> Result<Vec<String>, StorageError> {
+  match value {
+    Value::String(principal) if principal == "*" => Ok(vec!["*".to_string()]),
+    Value::Object(principals) => {
+      let mut result = Vec::new();
+      for (kind, value) in principals {
+        let values = string_list(value, "Principal")?;
+        match kind.as_str() {
+          "AWS" | "CanonicalUser" => result.extend(values),
+          "Service" | "Federated" => {}
+          _ => return Err(malformed("Invalid principal in policy")),
+        }
+      }
+      Ok(result)
+    }
+    _ => Err(malformed("Invalid principal in policy")),
+  }
+}
+
+fn parse_conditions(conditions: &Map<String, Value>) -> Result<Vec<Condition>, StorageError> {
+  let mut result = Vec::new();
+  for (name, entries) in conditions {
+    let (base, if_exists) = match name.strip_suffix("IfExists") {
+      Some(base) => (base, true),
+      None => (name.as_str(), false),
+    };
+    let operator = ConditionOperator::from_name(base)
+      .ok_or_else(|| malformed(format!("Invalid Condition type : {name}")))?;
+    let Value::Object(entries) = entries else {
+      return Err(malformed(format!("Invalid Condition block : {name}")));
+    };
+    for (key, values) in entries {
+      let values = condition_values(values)
+        .ok_or_else(|| malformed(format!("Invalid Condition value for {key}")))?;
+      let valid = values.iter().all(|value| match operator {
+        ConditionOperator::IpAddress | ConditionOperator::NotIpAddress => {
+          parse_cidr(value).is_some()
+        }
+        ConditionOperator::Bool | ConditionOperator::Null => value == "true" || value == "false",
+        ConditionOperator::NumericEquals
+        | ConditionOperator::NumericNotEquals
+        | ConditionOperator::NumericLessThan
+        | ConditionOperator::NumericLessThanEquals
+        | ConditionOperator::NumericGreaterThan
+        | ConditionOperator::NumericGreaterThanEquals => value.parse::<f64>().is_ok(),
+        _ => true,
+      });
+      if !valid {
+        return Err(malformed(format!("Invalid Condition value for {key}")));
+      }
+      result.push(Condition {
+        operator,
+        if_exists,
+        key: key.to_ascii_lowercase(),
+        values,
+      });
+    }
+  }
+  Ok(result)
+}
+
+/// 条件值可以是字符串、布尔、数字或它们的数组，统一转成字符串
+fn condition_values(value: &Value) -> Option<Vec<String>> {
+  let scalar = |value: &Value| match value {
+    Value::String(value) => Some(value.clone()),
+    Value::Bool(value) => Some(value.to_string()),
+    Value::Number(value) => Some(value.to_string()),
+    _ => None,
+  };
+  match value {
+    Value::Array(values) if !values.is_empty() => values.iter().map(scalar).collect(),
+    value => scalar(value).map(|value| vec![value]),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn wildcards() {
+    assert!(wildcard_match(
+      "arn:aws:s3:::bkt/*",
+      "arn:aws:s3:::bkt/a/b.txt"
+    ));
+    assert!(wildcard_match("arn:aws:s3:::bkt/*", "arn:aws:s3:::bkt/"));
+    assert!(!wildcard_match("arn:aws:s3:::bkt/*", "arn:aws:s3:::bkt"));
+    assert!(wildcard_match(
+      "arn:aws:s3:::bkt/log?/*.gz",
+      "arn:aws:s3:::bkt/logs/x/y.gz"
+    ));
+    assert!(!wildcard_match(
+      "arn:aws:s3:::bkt/log?/*.gz",
+      "arn:aws:s3:::bkt/logs/y.txt"
+    ));
+    assert!(wildcard_match("*", ""));
+    assert!(cidr_contains(
+      parse_cidr("10.0.0.0/8").unwrap(),
+      "10.1.2.3".parse().unwrap()
+    ));
+    assert!(!cidr_contains(
+      parse_cidr("10.0.0.0/8").unwrap(),
+      "11.0.0.1".parse().unwrap()
+    ));
+    assert!(cidr_contains(
+      parse_cidr("0.0.0.0/0").unwrap(),
+      "1.2.3.4".parse().unwrap()
+    ));
+    assert!(cidr_contains(
+      parse_cidr("::1").unwrap(),
+      "::1".parse().unwrap()
+    ));
+  }
+
+  #[test]
+  fn explicit_deny_wins() {
+    let policy = parse_policy(
+      "bkt",
+      r#"{
+        "Version": "2012-10-17",
+        "Statement": [
+          {"Sid": "Public", "Effect": "Allow", "Principal": "*", "Action": "s3:*", "Resource": "arn:aws:s3:::bkt/*"},
+          {"Effect": "Deny", "Principal": {"AWS": ["arn:aws:iam::123456789012:user/alice"]},
+           "Action": ["s3:Delete*"], "Resource": "arn:aws:s3:::bkt/keep/*"}
+        ]
+      }"#,
+    )
+    .unwrap();
+    let arn = resource_arn("bkt", Some("keep/a"));
+    let context = ConditionContext::new();
+    let request = |principal, action| PolicyRequest {
+      principal,
+      action,
+      resource: &arn,
+      context: &context,
+    };
+    assert_eq!(
+      evaluate(&policy, &request(None, Action::GetObject)),
+      Decision::Allow
+    );
+    assert_eq!(
+      evaluate(&policy, &request(Some("alice"), Action::DeleteObject)),
+      Decision::Deny
+    );
+    assert_eq!(
+      evaluate(&policy, &request(Some("bob"), Action::DeleteObject)),
+      Decision::Allow
+    );
+    let bucket_arn = resource_arn("bkt", None);
+    let list = PolicyRequest {
+      principal: Some("bob"),
+      action: Action::ListBucket,
+      resource: &bucket_arn,
+      context: &context,
+    };
+    assert_eq!(evaluate(&policy, &list), Decision::NotApplicable);
+  }
+
+  #[test]
+  fn conditions_and_negation() {
+    let policy = parse_policy(
+      "bkt",
+      r#"{
+        "Statement": [
+          {"Effect": "Allow", "Principal": {"AWS": "*"}, "Action": "s3:ListBucket", "Resource": "arn:aws:s3:::bkt",
+           "Condition": {"StringLike": {"s3:prefix": ["home/*", ""]}, "IpAddress": {"aws:SourceIp": "10.0.0.0/8"}}},
+          {"Effect": "Deny", "Principal": "*", "NotAction": "s3:Get*", "Resource": "arn:aws:s3:::bkt/*",
+           "Condition": {"Bool": {"aws:SecureTransport": false}}}
+        ]
+      }"#,
+    )
+    .unwrap();
+    let bucket_arn = resource_arn("bkt", None);
+    let object_arn = resource_arn("bkt", Some("a"));
+    let check = |action, resource: &str, pairs: &[(&str, &str)]| {
+      let context = pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+      evaluate(
+        &policy,
+        &PolicyRequest {
+          principal: Some("bob"),
+          action,
+          resource,
+          context: &context,
+        },
+      )
+    };
+    let list = [("s3:prefix", "home/bob/"), ("aws:sourceip", "10.0.0.5")];
+    assert_eq!(
+      check(Action::ListBucket, &bucket_arn, &list),
+      Decision::Allow
+    );
+    let outside = [("s3:prefix", "home/bob/"), ("aws:sourceip", "192.168.0.1")];
+    assert_eq!(
+      check(Action::ListBucket, &bucket_arn, &outside),
+      Decision::NotApplicable
+    );
+    // 没有 s3:prefix 时 StringLike 不满足
+    assert_eq!(
+      check(
+        Action::ListBucket,
+        &bucket_arn,
+        &[("aws:sourceip", "10.0.0.5")]
+      ),
+      Decision::NotApplicable
+    );
+    let insecure = [("aws:securetransport", "false")];
+    assert_eq!(
+      check(Action::PutObject, &object_arn, &insecure),
+      Decision::Deny
+    );
+    assert_eq!(
+      check(Action::GetObject, &object_arn, &insecure),
+      Decision::NotApplicable
+    );
+  }
+
+  #[test]
+  fn malformed_policies() {
+    for document in [
+      "[]",
+      r#"{"Statement": []}"#,
+      r#"{"Version": "2020-01-01", "Statement": {"Effect": "Allow", "Principal": "*", "Action": "s3:GetObject", "Resource": "arn:aws:s3:::bkt/*"}}"#,
+      r#"{"Statement": {"Effect": "Allow", "Principal": "*", "Action": "s3:GetObjekt", "Resource": "arn:aws:s3:::bkt/*"}}"#,
+      r#"{"Statement": {"Effect": "Allow", "Principal": "*", "Action": "s3:GetObject", "Resource": "arn:aws:s3:::other/*"}}"#,
+      r#"{"Statement": {"Effect": "Allow", "Principal": "*", "Action": "s3:GetObject", "NotAction": "s3:PutObject", "Resource": "*"}}"#,
+      r#"{"Statement": {"Effect": "Allow", "Action": "s3:GetObject", "Resource": "*"}}"#,
+      r#"{"Statement": {"Effect": "Allow", "Principal": "*", "Action": "s3:GetObject", "Resource": "*", "Condition": {"IpAddress": {"aws:SourceIp": "nope"}}}}"#,
+      r#"{"Statement": [{"Sid": "a", "Effect": "Allow", "Principal": "*", "Action": "s3:GetObject", "Resource": "*"}, {"Sid": "a", "Effect": "Deny", "Principal": "*", "Action": "s3:GetObject", "Resource": "*"}]}"#,
+    ] {
+      assert!(
+        matches!(
+          parse_policy("bkt", document),
+          Err(StorageError::MalformedPolicy { .. })
+        ),
+        "{document}"
+      );
+    }
+  }
+}
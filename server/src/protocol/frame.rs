@@ -1,9 +1,9 @@
-struct FrameHeader {
-    version: u8,            // 协议版本
-    frame_type: u8,         // 类型
-    flags: u8,              // 压缩/加密等
-    reserved: u8,           // 保留
-    payload_len: u32,       // 负载长度
+pub struct FrameHeader {
+    pub version: u8,            // 协议版本
+    pub frame_type: u8,         // 类型
+    pub flags: u8,              // 压缩/加密等
+    pub reserved: u8,           // 保留
+    pub payload_len: u32,       // 负载长度
 }
 
 pub enum Frame {
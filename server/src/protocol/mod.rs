@@ -1 +1 @@
-mod frame;
\ No newline at end of file
+pub mod frame;
\ No newline at end of file
@@ -0,0 +1,54 @@
+use crate::bucket::BucketManager;
+use crate::encryption::WrappingKey;
+use crate::metadata::CHUNK_TABLE;
+use crate::metadata::migrate;
+use crate::object::ObjectManager;
+use anyhow::Result;
+use redb::Database;
+use std::path::Path;
+use std::sync::Arc;
+
+/// 存储句柄：redb 元数据库 + 对象数据目录
+pub struct Storage {
+  pub buckets: BucketManager,
+  pub objects: ObjectManager,
+}
+
+impl Storage {
+  /// `temp_dir` 用于暂存分片上传等中间数据，对应 ServiceConfig.temp_dir
+  pub fn open(root: &Path, temp_dir: &Path) -> Result<Self> {
+    std::fs::create_dir_all(root)?;
+    let db = Arc::new(Database::create(root.join("meta.redb"))?);
+
+    // 提前建表，避免只读事务打开不存在的表时报错；旧版本的元数据表在这里转换
+    let write_txn = db.begin_write()?;
+    migrate::upgrade(&write_txn)?;
+    write_txn.open_table(CHUNK_TABLE)?;
+    write_txn.commit()?;
+
+    Ok(Self {
+      buckets: BucketManager::new(db.clone()),
+      objects: ObjectManager::new(db, root.join("objects"), temp_dir.to_path_buf())?,
+    })
+  }
+
+  /// 设置 SSE-S3 使用的主密钥，对应 SecurityConfig.master_key
+  pub fn with_master_key(mut self, key: WrappingKey) -> Self {
+    self.objects.set_master_key(key);
+    self
+  }
+}
+
+/// 测试用：在临时目录中打开存储并创建 bucket `bkt`，返回的目录需要在测试期间保留
+#[cfg(test)]
+pub(crate) fn test_storage(
+  options: crate::bucket::CreateBucketOptions,
+) -> (tempfile::TempDir, Storage) {
+  let dir = tempfile::tempdir().unwrap();
+  let storage = Storage::open(dir.path(), &dir.path().join("tmp")).unwrap();
+  storage
+    .buckets
+    .create_bucket("bkt", "owner", options)
+    .unwrap();
+  (dir, storage)
+}
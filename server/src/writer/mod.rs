@@ -1 +1 @@
-mod object_group;
\ No newline at end of file
+pub mod object_group;
\ No newline at end of file
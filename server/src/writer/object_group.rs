@@ -1,25 +1,45 @@
-use std::collections::BTreeMap;
+use anyhow::Result;
+use std::path::Path;
 use std::time::Instant;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
 
-// 在内存中聚合多个小文件为一个逻辑块
-struct ObjectGroup {
-    buffer: Vec<u8>,          // 合并后的数据块（如 64MB）
-    meta_index: BTreeMap<String, (u64, u64)>, // 文件名 -> (offset, length)
-    created_at: Instant,
+/// 单个组文件的大小上限，超过后开启新组
+pub const GROUP_SIZE_LIMIT: u64 = 64 * 1024 * 1024;
+
+// 把多个小文件顺序追加到同一个数据文件（逻辑块），减少小文件数量；
+// 每次追加后落盘，对象位置 (group_id, offset) 记录在对象元数据中
+pub struct ObjectGroup {
+    pub group_id: String,
+    file: File,
+    pub size: u64, // 已写入的字节数，即下一个对象的偏移
+    pub created_at: Instant,
 }
 
 impl ObjectGroup {
-    // 添加小文件到组
-    pub fn add_file(&mut self, name: &str, data: &[u8]) {
-        let offset = self.buffer.len();
-        self.buffer.extend_from_slice(data);
-        self.meta_index.insert(name.to_string(), (offset as u64, data.len() as u64));
+    pub async fn create(group_id: String, path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let file = File::create(path).await?;
+        Ok(Self {
+            group_id,
+            file,
+            size: 0,
+            created_at: Instant::now(),
+        })
+    }
+
+    // 添加小文件到组，返回其在组内的偏移
+    pub async fn add_file(&mut self, data: &[u8]) -> Result<u64> {
+        let offset = self.size;
+        self.file.write_all(data).await?;
+        self.file.sync_data().await?;
+        self.size += data.len() as u64;
+        Ok(offset)
     }
 
-    // // 达到阈值后持久化（触发纠删码编码）
-    // pub fn flush_to_disk(&self, backend: &StorageBackend) {
-    //     let group_id = generate_uuid();
-    //     backend.write_merged(&group_id, &self.buffer); // 大块写入
-    //     backend.write_index(&group_id, &self.meta_index); // 元数据单独存储
-    // }
-}
\ No newline at end of file
+    pub fn is_full(&self) -> bool {
+        self.size >= GROUP_SIZE_LIMIT
+    }
+}